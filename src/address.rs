@@ -0,0 +1,181 @@
+use bech32::hrp;
+use sha3::{Digest, Keccak256};
+
+// Offline validation and normalization of wallet addresses, so typos and wrong-case addresses
+// are caught before they're sent to the API or silently fail to match.
+
+// Chain families addresses are validated, filtered and grouped by
+pub const CHAIN_FAMILIES: [&str; 3] = ["evm", "solana", "bitcoin"];
+
+// A validated address in its canonical form
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Address {
+    pub chain: &'static str,
+    pub normalized: String,
+}
+
+// Family of a chain code as reported by the API (EVM, ETH, SOL, BTC) or given on the command line
+pub fn chain_family(code: &str) -> Option<&'static str> {
+    match code.to_lowercase().as_str() {
+        "evm" | "eth" => Some("evm"),
+        "sol" | "solana" => Some("solana"),
+        "btc" | "bitcoin" => Some("bitcoin"),
+        _ => None,
+    }
+}
+
+// Validate an address, detecting its chain unless a family or chain code is given
+pub fn validate(address: &str, chain: Option<&str>) -> Result<Address, String> {
+    let address = address.trim();
+    let chain = match chain {
+        Some(chain) => chain_family(chain).ok_or_else(|| format!("can't validate addresses on chain '{}'", chain))?,
+        None => detect(address).ok_or("not a recognized EVM, Solana or Bitcoin address")?,
+    };
+
+    let normalized = match chain {
+        "evm" => evm(address)?,
+        "solana" => solana(address)?,
+        _ => bitcoin(address)?,
+    };
+    Ok(Address { chain, normalized })
+}
+
+// Compare two addresses on the same chain, ignoring differences normalization removes
+pub fn same_address(a: &str, b: &str) -> bool {
+    match (validate(a, None), validate(b, None)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a.trim() == b.trim(),
+    }
+}
+
+// Guess the chain from the address's shape; the chain's own check then decides validity
+fn detect(address: &str) -> Option<&'static str> {
+    let lower = address.to_lowercase();
+    if lower.starts_with("0x") {
+        return Some("evm");
+    }
+    if ["bc1", "tb1", "bcrt1"].iter().any(|prefix| lower.starts_with(prefix)) {
+        return Some("bitcoin");
+    }
+
+    // Base58: Solana public keys are 32 bytes, Bitcoin base58check addresses 25
+    match bs58::decode(address).into_vec().ok()?.len() {
+        32 => Some("solana"),
+        25 => Some("bitcoin"),
+        _ => None,
+    }
+}
+
+// 0x + 40 hex digits. Mixed case must carry a valid EIP-55 checksum; normalized to checksum case.
+fn evm(address: &str) -> Result<String, String> {
+    let hex = address
+        .strip_prefix("0x")
+        .ok_or("EVM addresses start with 0x")?;
+    if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("EVM addresses are 0x followed by 40 hex digits".to_string());
+    }
+
+    let checksummed = eip55(hex);
+    let single_case = hex == hex.to_lowercase() || hex == hex.to_uppercase();
+    if !single_case && checksummed != address {
+        return Err("EIP-55 checksum mismatch; the address probably has a typo".to_string());
+    }
+    Ok(checksummed)
+}
+
+fn eip55(hex: &str) -> String {
+    let lower = hex.to_lowercase();
+    let hash = Keccak256::digest(lower.as_bytes());
+
+    let digits: String = lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            if nibble >= 8 { c.to_ascii_uppercase() } else { c }
+        })
+        .collect();
+    format!("0x{}", digits)
+}
+
+// Base58-encoded 32-byte public key
+fn solana(address: &str) -> Result<String, String> {
+    let bytes = bs58::decode(address)
+        .into_vec()
+        .map_err(|_| "Solana addresses are base58 (no 0, O, I or l)".to_string())?;
+    if bytes.len() != 32 {
+        return Err(format!("Solana public keys are 32 bytes, this is {}", bytes.len()));
+    }
+    Ok(address.to_string())
+}
+
+// Segwit (bech32/bech32m, normalized to lowercase) or legacy base58check P2PKH/P2SH
+fn bitcoin(address: &str) -> Result<String, String> {
+    let lower = address.to_lowercase();
+    if ["bc1", "tb1", "bcrt1"].iter().any(|prefix| lower.starts_with(prefix)) {
+        // bech32 forbids mixed case
+        if address != lower && address != address.to_uppercase() {
+            return Err("bech32 addresses can't mix upper and lower case".to_string());
+        }
+        let (network, _, _) = bech32::segwit::decode(&lower).map_err(|e| format!("invalid bech32 address: {}", e))?;
+        if ![hrp::BC, hrp::TB, hrp::BCRT].contains(&network) {
+            return Err(format!("unknown Bitcoin network '{}'", network));
+        }
+        return Ok(lower);
+    }
+
+    let payload = bs58::decode(address)
+        .with_check(None)
+        .into_vec()
+        .map_err(|e| match e {
+            bs58::decode::Error::InvalidChecksum { .. } => "base58check checksum mismatch; the address probably has a typo".to_string(),
+            _ => "not a valid base58check address".to_string(),
+        })?;
+    // Version byte, then a 20-byte hash: 0x00/0x05 on mainnet, 0x6f/0xc4 on testnet
+    match payload.as_slice() {
+        [0x00 | 0x05 | 0x6f | 0xc4, hash @ ..] if hash.len() == 20 => Ok(address.to_string()),
+        _ => Err("not a Bitcoin P2PKH or P2SH address".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evm_addresses_normalize_to_checksum_case() {
+        let checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let lower = validate(&checksummed.to_lowercase(), None).unwrap();
+        assert_eq!(lower, Address { chain: "evm", normalized: checksummed.to_string() });
+        assert!(validate(checksummed, Some("evm")).is_ok());
+
+        // One letter with the wrong case breaks the checksum
+        assert!(validate("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD", None).is_err());
+        assert!(validate("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA", None).is_err());
+    }
+
+    #[test]
+    fn solana_public_keys() {
+        let address = validate("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA", None).unwrap();
+        assert_eq!(address.chain, "solana");
+        assert!(validate("11111111111111111111111111111111", Some("solana")).is_ok());
+        assert!(validate("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5D0", Some("solana")).is_err());
+    }
+
+    #[test]
+    fn bitcoin_addresses() {
+        assert_eq!(validate("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa", None).unwrap().chain, "bitcoin");
+        assert!(validate("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb", None).is_err());
+
+        let segwit = validate("BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4", None).unwrap();
+        assert_eq!(segwit.normalized, "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+        assert!(validate("bc1p5d7rjq7g6rdk2yhzks9smlaqtedr4dekq08ge8ztwac72sfr9rusxg3297", None).is_ok());
+        assert!(validate("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5", None).is_err());
+    }
+
+    #[test]
+    fn chain_hint_is_enforced() {
+        assert!(validate("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa", Some("solana")).is_err());
+        assert!(validate("not an address", None).is_err());
+    }
+}
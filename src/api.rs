@@ -1,95 +1,991 @@
 use anyhow::Result;
-use reqwest::{header, Client};
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
-//use std::sync::Arc;
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use colored::*;
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt};
+use reqwest::{header, Client, Method, Request, StatusCode};
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+
+use crate::cassette::Cassette;
+use crate::error::ApiError;
+use crate::models::{Export, Organization, OrganizationMember, User, Wallet};
+use crate::retry::{self, RetryPolicy, IDEMPOTENCY_KEY};
+
+// Pagination settings for list endpoints
+#[derive(Debug, Clone, Copy)]
+pub struct PageOptions {
+    pub page_size: usize,
+    // Maximum number of items to yield; None follows every page
+    pub limit: Option<usize>,
+}
+
+// Where a paginated listing has got to
+struct PageState {
+    path: String,
+    // Field the items are wrapped under; None takes the first array in the page
+    key: Option<&'static str>,
+    options: PageOptions,
+    offset: usize,
+    cursor: Option<String>,
+    buffer: VecDeque<Value>,
+    yielded: usize,
+    done: bool,
+}
+
+impl PageState {
+    fn page_path(&self) -> String {
+        let separator = if self.path.contains('?') { '&' } else { '?' };
+        let remaining = self.options.limit.map(|limit| limit - self.yielded);
+        let page_size = remaining.map_or(self.options.page_size, |r| r.min(self.options.page_size));
+
+        // Cursors are opaque and may hold characters like + and &, so they're form-encoded
+        let mut url = reqwest::Url::parse("http://localhost/").expect("static URL parses");
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("limit", &page_size.to_string());
+            match &self.cursor {
+                Some(cursor) => query.append_pair("cursor", cursor),
+                None => query.append_pair("offset", &self.offset.to_string()),
+            };
+        }
+        format!("{}{}{}", self.path, separator, url.query().unwrap_or_default())
+    }
+
+    // Queue a page of results and work out whether another page follows
+    fn absorb(&mut self, page: Value) {
+        let next_cursor = ["nextCursor", "cursor"]
+            .iter()
+            .find_map(|key| page.get(*key).and_then(Value::as_str))
+            .filter(|cursor| !cursor.is_empty())
+            .map(str::to_string);
+        let total = page.get("count").and_then(Value::as_u64);
+
+        let items = page_items(page, self.key);
+        let received = items.len();
+        self.offset += received;
+        self.buffer.extend(items);
+
+        self.done = match (&next_cursor, total) {
+            _ if received == 0 => true,
+            (Some(_), _) => false,
+            (None, Some(total)) => self.offset as u64 >= total,
+            // Without a total, a short page is the last one. A page larger than requested means
+            // the endpoint ignores pagination, so there's nothing more to fetch.
+            (None, None) => received != self.options.page_size,
+        };
+        self.cursor = next_cursor;
+    }
+}
+
+// Items of a list response, which is either a bare array or wrapped under `key`
+fn page_items(page: Value, key: Option<&str>) -> Vec<Value> {
+    match page {
+        Value::Array(items) => items,
+        Value::Object(mut map) => {
+            let items = match key {
+                Some(key) => map.remove(key),
+                None => map.into_iter().map(|(_, value)| value).find(Value::is_array),
+            };
+            match items {
+                Some(Value::Array(items)) => items,
+                _ => Vec::new(),
+            }
+        }
+        _ => Vec::new(),
+    }
+}
+
+// A file saved by `DynamicApiClient::download`
+#[derive(Debug, Clone)]
+pub struct Download {
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+// Lowercase hex, as digests are shown and compared
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Bytes back from hex, or None if it isn't valid hex
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// Export types and file formats offered by the API
+pub const EXPORT_TYPES: [&str; 3] = ["users", "organizations", "wallets"];
+pub const EXPORT_FORMATS: [&str; 2] = ["csv", "json"];
+
+// Body of a create-export request. Built with `ExportRequest::builder`, which validates it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportRequest {
+    #[serde(rename = "type")]
+    pub export_type: String,
+    pub format: String,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub filter: Map<String, Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<String>,
+}
+
+impl ExportRequest {
+    pub fn builder(export_type: &str) -> ExportRequestBuilder {
+        ExportRequestBuilder {
+            export_type: export_type.to_string(),
+            format: EXPORT_FORMATS[0].to_string(),
+            created_after: None,
+            created_before: None,
+            filters: Vec::new(),
+            columns: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportRequestBuilder {
+    export_type: String,
+    format: String,
+    created_after: Option<String>,
+    created_before: Option<String>,
+    filters: Vec<(String, String)>,
+    columns: Vec<String>,
+}
+
+impl ExportRequestBuilder {
+    pub fn format(mut self, format: &str) -> Self {
+        self.format = format.to_string();
+        self
+    }
+
+    // Only include objects created on or after this date (YYYY-MM-DD or RFC 3339)
+    pub fn created_after(mut self, date: &str) -> Self {
+        self.created_after = Some(date.to_string());
+        self
+    }
+
+    // Only include objects created before this date (YYYY-MM-DD or RFC 3339)
+    pub fn created_before(mut self, date: &str) -> Self {
+        self.created_before = Some(date.to_string());
+        self
+    }
+
+    pub fn filter(mut self, field: &str, value: &str) -> Self {
+        self.filters.push((field.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn columns<I: IntoIterator<Item = S>, S: Into<String>>(mut self, columns: I) -> Self {
+        self.columns.extend(columns.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn build(self) -> Result<ExportRequest> {
+        if !EXPORT_TYPES.contains(&self.export_type.as_str()) {
+            return Err(anyhow::anyhow!(
+                "Unknown export type '{}' (expected one of: {})",
+                self.export_type,
+                EXPORT_TYPES.join(", ")
+            ));
+        }
+        if !EXPORT_FORMATS.contains(&self.format.as_str()) {
+            return Err(anyhow::anyhow!(
+                "Unknown export format '{}' (expected one of: {})",
+                self.format,
+                EXPORT_FORMATS.join(", ")
+            ));
+        }
+
+        let mut filter = Map::new();
+        let after = self.created_after.as_deref().map(parse_date).transpose()?;
+        let before = self.created_before.as_deref().map(parse_date).transpose()?;
+        if let (Some(after), Some(before)) = (after, before)
+            && after >= before
+        {
+            return Err(anyhow::anyhow!("--created-after must be earlier than --created-before"));
+        }
+        if let Some(after) = after {
+            filter.insert("createdAfter".to_string(), after.to_rfc3339_opts(SecondsFormat::Millis, true).into());
+        }
+        if let Some(before) = before {
+            filter.insert("createdBefore".to_string(), before.to_rfc3339_opts(SecondsFormat::Millis, true).into());
+        }
+
+        for (field, value) in self.filters {
+            if !is_field_name(&field) {
+                return Err(anyhow::anyhow!("Invalid filter field '{}'", field));
+            }
+            // The date range has its own flags; a filter must not silently replace it
+            if let Some(flag) = match field.as_str() {
+                "createdAfter" => Some("--created-after"),
+                "createdBefore" => Some("--created-before"),
+                _ => None,
+            } {
+                return Err(anyhow::anyhow!("Filter field '{}' is reserved; use {} instead", field, flag));
+            }
+            filter.insert(field, Value::String(value));
+        }
+
+        if let Some(column) = self.columns.iter().find(|c| !is_field_name(c)) {
+            return Err(anyhow::anyhow!("Invalid column '{}'", column));
+        }
+
+        Ok(ExportRequest {
+            export_type: self.export_type,
+            format: self.format,
+            filter,
+            columns: self.columns,
+        })
+    }
+}
+
+// A date as YYYY-MM-DD (midnight UTC) or a full RFC 3339 timestamp
+fn parse_date(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|_| anyhow::anyhow!("Invalid date '{}': use YYYY-MM-DD or an RFC 3339 timestamp", value))
+}
+
+// Field names as the API spells them, e.g. `email` or `metadata.plan`
+fn is_field_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+// Writable user fields for creating or updating a user; unset fields are left alone
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserFields {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallets: Option<Vec<WalletFields>>,
+}
+
+// A wallet to link when creating a user
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletFields {
+    pub public_key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain: Option<String>,
+}
+
+impl UserFields {
+    pub fn is_empty(&self) -> bool {
+        *self == UserFields::default()
+    }
+}
+
+// Writable organization fields for creating or updating an organization
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizationFields {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub website_url: Option<String>,
+}
+
+impl OrganizationFields {
+    pub fn is_empty(&self) -> bool {
+        *self == OrganizationFields::default()
+    }
+}
+
+// Roles a user can hold in an organization
+pub const ORGANIZATION_ROLES: [&str; 3] = ["owner", "admin", "member"];
+
+// A request to an endpoint the commands don't wrap, sent by `api`
+#[derive(Debug, Clone)]
+pub struct RawRequest {
+    pub method: Method,
+    // Path under the base URL. Paths without a leading slash are relative to /api/v0/, and
+    // `{env}` stands for the environment ID.
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub body: Option<Value>,
+}
+
+impl RawRequest {
+    // The path to request in `environment_id`, with the query string appended
+    pub fn path_in(&self, environment_id: &str) -> Result<String> {
+        if self.path.contains("://") {
+            return Err(anyhow::anyhow!("Pass a path under the base URL, not a full URL: {}", self.path));
+        }
+
+        let mut path = self.path.replace("{env}", environment_id);
+        if !path.starts_with('/') {
+            path = format!("/api/v0/{}", path);
+        }
+        if !self.query.is_empty() {
+            let mut url = reqwest::Url::parse("http://localhost/")?;
+            url.query_pairs_mut().extend_pairs(&self.query);
+            let separator = if path.contains('?') { '&' } else { '?' };
+            path = format!("{}{}{}", path, separator, url.query().unwrap_or_default());
+        }
+        Ok(path)
+    }
+}
+
+// Behaviour of the client that isn't tied to a particular account
+#[derive(Debug, Clone, Default)]
+pub struct ClientOptions {
+    pub retry: RetryPolicy,
+    // Log retries and other diagnostics to stderr
+    pub verbose: bool,
+    // Record responses to, or replay them from, an HTTP cassette
+    pub cassette: Option<Arc<Cassette>>,
+}
+
+// Every Admin API operation the commands use. Implemented over HTTP by `DynamicApiClient`, and
+// by decorators such as `CachingAdminApi` and test fakes wrapping or standing in for it.
+#[async_trait]
+pub trait AdminApi: Send + Sync {
+    // Organizations
+    fn list_organizations(&self, environment_id: &str, options: PageOptions) -> BoxStream<'_, Result<Organization>>;
+    async fn get_organization(&self, environment_id: &str, org_id: &str) -> Result<Organization>;
+    async fn create_organization(&self, environment_id: &str, fields: &OrganizationFields) -> Result<Organization>;
+    async fn update_organization(&self, environment_id: &str, org_id: &str, fields: &OrganizationFields) -> Result<Organization>;
+    async fn delete_organization(&self, environment_id: &str, org_id: &str) -> Result<()>;
+
+    // Organization members
+    fn list_organization_members(&self, environment_id: &str, org_id: &str, options: PageOptions) -> BoxStream<'_, Result<OrganizationMember>>;
+    async fn add_organization_member(&self, environment_id: &str, org_id: &str, user_id: &str, role: &str) -> Result<OrganizationMember>;
+    async fn set_organization_member_role(&self, environment_id: &str, org_id: &str, user_id: &str, role: &str) -> Result<OrganizationMember>;
+    async fn remove_organization_member(&self, environment_id: &str, org_id: &str, user_id: &str) -> Result<()>;
+
+    // Exports
+    fn list_exports(&self, environment_id: &str, options: PageOptions) -> BoxStream<'_, Result<Export>>;
+    async fn get_export(&self, environment_id: &str, export_id: &str) -> Result<Export>;
+    async fn create_export(&self, environment_id: &str, params: &ExportRequest, idempotency_key: Option<&str>) -> Result<Export>;
+    // Stream a file to `dest`, reporting (bytes so far, total if known) as it arrives
+    async fn download(&self, url: &str, dest: &Path, progress: &mut (dyn FnMut(u64, Option<u64>) + Send)) -> Result<Download>;
+
+    // Wallets
+    fn list_wallets(&self, environment_id: &str, options: PageOptions) -> BoxStream<'_, Result<Wallet>>;
+    fn list_user_wallets(&self, environment_id: &str, user_id: &str, options: PageOptions) -> BoxStream<'_, Result<Wallet>>;
+    async fn get_wallet(&self, environment_id: &str, wallet_id: &str) -> Result<Wallet>;
+
+    // Users
+    fn list_users(&self, environment_id: &str, options: PageOptions) -> BoxStream<'_, Result<User>>;
+    async fn get_user(&self, environment_id: &str, user_id: &str) -> Result<User>;
+    async fn create_user(&self, environment_id: &str, fields: &UserFields, idempotency_key: Option<&str>) -> Result<User>;
+    async fn update_user(&self, environment_id: &str, user_id: &str, fields: &UserFields) -> Result<User>;
+    async fn delete_user(&self, environment_id: &str, user_id: &str) -> Result<()>;
+    async fn block_user(&self, environment_id: &str, user_id: &str) -> Result<()>;
+    async fn unblock_user(&self, environment_id: &str, user_id: &str) -> Result<()>;
+
+    // Any endpoint, returning the response as JSON (null when empty)
+    async fn request(&self, environment_id: &str, request: &RawRequest) -> Result<Value>;
+    // Every item of any list endpoint
+    fn paginate_request(&self, environment_id: &str, request: &RawRequest, options: PageOptions) -> BoxStream<'_, Result<Value>>;
+}
 
 // API client for DynamicSDK
 pub struct DynamicApiClient {
     client: Client,
-    api_token: String,
     base_url: String,
+    options: ClientOptions,
 }
 
 impl DynamicApiClient {
-    pub fn new(api_token: String, base_url: String) -> Self {
+    pub fn new(api_token: String, base_url: String, options: ClientOptions) -> Result<Self> {
+        // e.g. a newline pasted along with the token
+        let authorization = header::HeaderValue::from_str(&format!("Bearer {}", api_token))
+            .map_err(|_| anyhow::anyhow!("API token contains characters not allowed in an HTTP header"))?;
         let mut headers = header::HeaderMap::new();
-        headers.insert(
-            header::AUTHORIZATION,
-            header::HeaderValue::from_str(&format!("Bearer {}", api_token)).unwrap(),
-        );
+        headers.insert(header::AUTHORIZATION, authorization);
 
-        let client = Client::builder()
-            .default_headers(headers)
-            .build()
-            .unwrap();
+        let client = Client::builder().default_headers(headers).build()?;
 
-        DynamicApiClient {
+        Ok(DynamicApiClient {
             client,
-            api_token,
             base_url,
-        }
+            options,
+        })
     }
 
     // Generic GET request
-    pub async fn get<T: for<'de> Deserialize<'de>>(&self, path: &str) -> Result<T> {
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ApiError> {
         let url = format!("{}{}", self.base_url, path);
-        let response = self.client.get(&url).send().await?;
-        
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("API error ({}): {}", status, error_text));
-        }
-        
-        let data = response.json::<T>().await?;
-        Ok(data)
+        self.send(self.client.get(&url).build()?).await
     }
 
     // Generic POST request
-    pub async fn post<T: for<'de> Deserialize<'de>, B: Serialize>(&self, path: &str, body: &B) -> Result<T> {
+    pub async fn post<T: DeserializeOwned, B: Serialize>(&self, path: &str, body: &B) -> Result<T, ApiError> {
+        let url = format!("{}{}", self.base_url, path);
+        self.send(self.client.post(&url).json(body).build()?).await
+    }
+
+    // Generic PUT request
+    pub async fn put<T: DeserializeOwned, B: Serialize>(&self, path: &str, body: &B) -> Result<T, ApiError> {
         let url = format!("{}{}", self.base_url, path);
-        let response = self.client.post(&url).json(body).send().await?;
-        
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("API error ({}): {}", status, error_text));
+        self.send(self.client.put(&url).json(body).build()?).await
+    }
+
+    // Generic PATCH request
+    pub async fn patch<T: DeserializeOwned, B: Serialize>(&self, path: &str, body: &B) -> Result<T, ApiError> {
+        let url = format!("{}{}", self.base_url, path);
+        self.send(self.client.patch(&url).json(body).build()?).await
+    }
+
+    // Generic DELETE request
+    pub async fn delete<T: DeserializeOwned>(&self, path: &str) -> Result<T, ApiError> {
+        let url = format!("{}{}", self.base_url, path);
+        self.send(self.client.delete(&url).build()?).await
+    }
+
+    // POST carrying an idempotency key, which lets it be retried safely
+    pub async fn post_idempotent<T: DeserializeOwned, B: Serialize>(
+        &self,
+        path: &str,
+        body: &B,
+        idempotency_key: &str,
+    ) -> Result<T, ApiError> {
+        let url = format!("{}{}", self.base_url, path);
+        let request = self
+            .client
+            .post(&url)
+            .header(IDEMPOTENCY_KEY, idempotency_key)
+            .json(body)
+            .build()?;
+        self.send(request).await
+    }
+
+    // Send a request, retrying transient failures according to the retry policy
+    async fn send<T: DeserializeOwned>(&self, request: Request) -> Result<T, ApiError> {
+        let policy = &self.options.retry;
+        let retryable = policy.allows(request.method(), request.headers());
+        let mut attempt = 1;
+
+        loop {
+            // JSON bodies are buffered so this always succeeds; fall back to a single attempt otherwise
+            let Some(current) = request.try_clone() else {
+                return self.send_once(request).await;
+            };
+
+            let error = match self.send_once(current).await {
+                Ok(data) => return Ok(data),
+                Err(error) => error,
+            };
+
+            let delay = if retryable && error.is_retryable() && attempt < policy.max_attempts {
+                policy.delay(attempt, error.retry_after())
+            } else {
+                None
+            };
+            let Some(delay) = delay else {
+                return Err(error);
+            };
+
+            if self.options.verbose {
+                eprintln!(
+                    "{} {} {} failed: {}. Retrying in {:.1}s (attempt {}/{})",
+                    "Retry:".yellow(),
+                    request.method(),
+                    request.url().path(),
+                    error,
+                    delay.as_secs_f64(),
+                    attempt + 1,
+                    policy.max_attempts
+                );
+            }
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    // Send a request once and decode the JSON response, mapping failures to ApiError
+    async fn send_once<T: DeserializeOwned>(&self, request: Request) -> Result<T, ApiError> {
+        let (status, retry_after, body) = match &self.options.cassette {
+            Some(cassette) if cassette.is_replay() => {
+                let recorded = cassette.request(&request, &self.base_url);
+                let response = cassette.find(&recorded).ok_or_else(|| ApiError::NotRecorded {
+                    request: format!("{} {}", recorded.method, recorded.path),
+                })?;
+                let status = StatusCode::from_u16(response.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+                let retry_after = response.retry_after.as_deref().and_then(|value| value.parse().ok());
+                (status, retry_after, response.bytes())
+            }
+            cassette => {
+                let recorded = cassette.as_ref().map(|cassette| cassette.request(&request, &self.base_url));
+                let response = self.client.execute(request).await?;
+                let status = response.status();
+                let retry_after = response.headers().get(header::RETRY_AFTER).cloned();
+                let body = response.bytes().await?.to_vec();
+
+                if let (Some(cassette), Some(recorded)) = (cassette, recorded) {
+                    let value = retry_after.as_ref().and_then(|v| v.to_str().ok()).map(str::to_string);
+                    if let Err(e) = cassette.save(recorded, cassette.response(status.as_u16(), value, &body)) {
+                        eprintln!("{} couldn't write to {}: {}", "Warning:".yellow(), cassette.path().display(), e);
+                    }
+                }
+                (status, retry_after, body)
+            }
+        };
+
+        if !status.is_success() {
+            let retry_after = retry_after.as_ref().and_then(retry::parse_retry_after);
+            return Err(ApiError::from_response(status, retry_after, &String::from_utf8_lossy(&body)));
+        }
+
+        // An empty body (e.g. 204 No Content) reads as null
+        if body.iter().all(u8::is_ascii_whitespace) {
+            return Ok(serde_json::from_value(Value::Null)?);
         }
-        
-        let data = response.json::<T>().await?;
-        Ok(data)
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    // Stream every item of a paginated list endpoint, fetching pages as they're consumed
+    pub fn paginate<T: DeserializeOwned>(&self, path: String, key: &'static str, options: PageOptions) -> impl Stream<Item = Result<T>> + Send + '_ {
+        self.pages(path, Some(key), options)
+    }
+
+    fn pages<T: DeserializeOwned>(&self, path: String, key: Option<&'static str>, options: PageOptions) -> impl Stream<Item = Result<T>> + Send + '_ {
+        let state = PageState {
+            path,
+            key,
+            options,
+            offset: 0,
+            cursor: None,
+            buffer: VecDeque::new(),
+            yielded: 0,
+            done: false,
+        };
+
+        futures::stream::try_unfold(state, move |mut state| async move {
+            loop {
+                if state.options.limit.is_some_and(|limit| state.yielded >= limit) {
+                    return Ok(None);
+                }
+
+                if let Some(item) = state.buffer.pop_front() {
+                    state.yielded += 1;
+                    return Ok(Some((serde_json::from_value(item)?, state)));
+                }
+
+                if state.done {
+                    return Ok(None);
+                }
+
+                let page: Value = self.get(&state.page_path()).await?;
+                state.absorb(page);
+            }
+        })
     }
+}
 
+#[async_trait]
+impl AdminApi for DynamicApiClient {
     // Organizations
-    //   --url https://app.dynamicauth.com/api/v0/organizations \
-    pub async fn list_organizations(&self) -> Result<Value> {
-        self.get(&format!("/api/v0/environments/organizations")).await
+    fn list_organizations(&self, environment_id: &str, options: PageOptions) -> BoxStream<'_, Result<Organization>> {
+        self.paginate(format!("/api/v0/environments/{}/organizations", environment_id), "organizations", options).boxed()
+    }
+
+    async fn get_organization(&self, environment_id: &str, org_id: &str) -> Result<Organization> {
+        Ok(self.get(&format!("/api/v0/environments/{}/organizations/{}", environment_id, org_id)).await?)
+    }
+
+    async fn create_organization(&self, environment_id: &str, fields: &OrganizationFields) -> Result<Organization> {
+        Ok(self.post(&format!("/api/v0/environments/{}/organizations", environment_id), fields).await?)
+    }
+
+    async fn update_organization(&self, environment_id: &str, org_id: &str, fields: &OrganizationFields) -> Result<Organization> {
+        Ok(self.put(&format!("/api/v0/environments/{}/organizations/{}", environment_id, org_id), fields).await?)
+    }
+
+    async fn delete_organization(&self, environment_id: &str, org_id: &str) -> Result<()> {
+        let _: IgnoredAny = self.delete(&format!("/api/v0/environments/{}/organizations/{}", environment_id, org_id)).await?;
+        Ok(())
+    }
+
+    // Organization members
+    fn list_organization_members(
+        &self,
+        environment_id: &str,
+        org_id: &str,
+        options: PageOptions,
+    ) -> BoxStream<'_, Result<OrganizationMember>> {
+        self.paginate(
+            format!("/api/v0/environments/{}/organizations/{}/members", environment_id, org_id),
+            "members",
+            options,
+        )
+        .boxed()
+    }
+
+    async fn add_organization_member(&self, environment_id: &str, org_id: &str, user_id: &str, role: &str) -> Result<OrganizationMember> {
+        let path = format!("/api/v0/environments/{}/organizations/{}/members", environment_id, org_id);
+        Ok(self.post(&path, &json!({ "userId": user_id, "role": role })).await?)
+    }
+
+    async fn set_organization_member_role(&self, environment_id: &str, org_id: &str, user_id: &str, role: &str) -> Result<OrganizationMember> {
+        let path = format!("/api/v0/environments/{}/organizations/{}/members/{}", environment_id, org_id, user_id);
+        Ok(self.put(&path, &json!({ "role": role })).await?)
     }
 
-    pub async fn get_organization(&self, environment_id: &str, org_id: &str) -> Result<Value> {
-        self.get(&format!("/api/v0/environments/{}/organizations/{}", environment_id, org_id)).await
+    async fn remove_organization_member(&self, environment_id: &str, org_id: &str, user_id: &str) -> Result<()> {
+        let path = format!("/api/v0/environments/{}/organizations/{}/members/{}", environment_id, org_id, user_id);
+        let _: IgnoredAny = self.delete(&path).await?;
+        Ok(())
     }
 
     // Exports
-    pub async fn list_exports(&self, environment_id: &str) -> Result<Value> {
-        self.get(&format!("/api/v0/environments/{}/exports", environment_id)).await
+    fn list_exports(&self, environment_id: &str, options: PageOptions) -> BoxStream<'_, Result<Export>> {
+        self.paginate(format!("/api/v0/environments/{}/exports", environment_id), "exports", options).boxed()
+    }
+
+    async fn get_export(&self, environment_id: &str, export_id: &str) -> Result<Export> {
+        Ok(self.get(&format!("/api/v0/environments/{}/exports/{}", environment_id, export_id)).await?)
+    }
+
+    async fn create_export(&self, environment_id: &str, params: &ExportRequest, idempotency_key: Option<&str>) -> Result<Export> {
+        let path = format!("/api/v0/environments/{}/exports", environment_id);
+        match idempotency_key {
+            Some(key) => Ok(self.post_idempotent(&path, params, key).await?),
+            None => Ok(self.post(&path, params).await?),
+        }
+    }
+
+    // Signed URLs on another host don't get our Authorization header
+    async fn download(&self, url: &str, dest: &Path, progress: &mut (dyn FnMut(u64, Option<u64>) + Send)) -> Result<Download> {
+        // File contents aren't recorded, so there's nothing to play back
+        if let Some(cassette) = self.options.cassette.as_ref().filter(|c| c.is_replay()) {
+            return Err(anyhow::anyhow!("Downloads aren't recorded, so {} can't replay {}", cassette.path().display(), url));
+        }
+
+        let request = if url.starts_with('/') {
+            self.client.get(format!("{}{}", self.base_url, url))
+        } else if same_origin(url, &self.base_url) {
+            self.client.get(url)
+        } else {
+            Client::new().get(url)
+        };
+
+        let mut response = request.send().await.map_err(ApiError::from)?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.map_err(ApiError::from)?;
+            return Err(ApiError::from_response(status, None, &body).into());
+        }
+
+        let total = response.content_length();
+        let mut file = tokio::fs::File::create(dest).await?;
+        let mut hasher = Sha256::new();
+        let mut bytes = 0;
+
+        while let Some(chunk) = response.chunk().await.map_err(ApiError::from)? {
+            file.write_all(&chunk).await?;
+            hasher.update(&chunk);
+            bytes += chunk.len() as u64;
+            progress(bytes, total);
+        }
+        file.flush().await?;
+
+        if let Some(total) = total.filter(|total| *total != bytes) {
+            return Err(anyhow::anyhow!("Download truncated: got {} of {} bytes", bytes, total));
+        }
+
+        let sha256 = hex(&hasher.finalize());
+        Ok(Download { bytes, sha256 })
+    }
+
+    // Wallets
+    fn list_wallets(&self, environment_id: &str, options: PageOptions) -> BoxStream<'_, Result<Wallet>> {
+        self.paginate(format!("/api/v0/environments/{}/wallets", environment_id), "wallets", options).boxed()
     }
 
-    pub async fn get_export(&self, environment_id: &str, export_id: &str) -> Result<Value> {
-        self.get(&format!("/api/v0/environments/{}/exports/{}", environment_id, export_id)).await
+    fn list_user_wallets(&self, environment_id: &str, user_id: &str, options: PageOptions) -> BoxStream<'_, Result<Wallet>> {
+        self.paginate(format!("/api/v0/environments/{}/users/{}/wallets", environment_id, user_id), "wallets", options).boxed()
     }
 
-    pub async fn create_export(&self, environment_id: &str, params: &Value) -> Result<Value> {
-        self.post(&format!("/api/v0/environments/{}/exports", environment_id), params).await
+    async fn get_wallet(&self, environment_id: &str, wallet_id: &str) -> Result<Wallet> {
+        Ok(self.get(&format!("/api/v0/environments/{}/wallets/{}", environment_id, wallet_id)).await?)
     }
 
     // Users
-    pub async fn list_users(&self, environment_id: &str) -> Result<Value> {
-        self.get(&format!("/api/v0/environments/{}/users", environment_id)).await
+    fn list_users(&self, environment_id: &str, options: PageOptions) -> BoxStream<'_, Result<User>> {
+        self.paginate(format!("/api/v0/environments/{}/users", environment_id), "users", options).boxed()
+    }
+
+    async fn get_user(&self, environment_id: &str, user_id: &str) -> Result<User> {
+        Ok(self.get(&format!("/api/v0/environments/{}/users/{}", environment_id, user_id)).await?)
     }
 
-    pub async fn get_user(&self, environment_id: &str, user_id: &str) -> Result<Value> {
-        self.get(&format!("/api/v0/environments/{}/users/{}", environment_id, user_id)).await
+    async fn create_user(&self, environment_id: &str, fields: &UserFields, idempotency_key: Option<&str>) -> Result<User> {
+        let path = format!("/api/v0/environments/{}/users", environment_id);
+        match idempotency_key {
+            Some(key) => Ok(self.post_idempotent(&path, fields, key).await?),
+            None => Ok(self.post(&path, fields).await?),
+        }
     }
-}
\ No newline at end of file
+
+    async fn update_user(&self, environment_id: &str, user_id: &str, fields: &UserFields) -> Result<User> {
+        Ok(self.put(&format!("/api/v0/environments/{}/users/{}", environment_id, user_id), fields).await?)
+    }
+
+    async fn delete_user(&self, environment_id: &str, user_id: &str) -> Result<()> {
+        let _: IgnoredAny = self.delete(&format!("/api/v0/environments/{}/users/{}", environment_id, user_id)).await?;
+        Ok(())
+    }
+
+    async fn block_user(&self, environment_id: &str, user_id: &str) -> Result<()> {
+        let path = format!("/api/v0/environments/{}/users/{}/block", environment_id, user_id);
+        let _: IgnoredAny = self.put(&path, &Map::new()).await?;
+        Ok(())
+    }
+
+    async fn unblock_user(&self, environment_id: &str, user_id: &str) -> Result<()> {
+        let path = format!("/api/v0/environments/{}/users/{}/unblock", environment_id, user_id);
+        let _: IgnoredAny = self.put(&path, &Map::new()).await?;
+        Ok(())
+    }
+
+    // Raw requests
+    async fn request(&self, environment_id: &str, request: &RawRequest) -> Result<Value> {
+        let path = request.path_in(environment_id)?;
+        let body = request.body.clone().unwrap_or_else(|| json!({}));
+        let response = match request.method {
+            Method::GET if request.body.is_none() => self.get(&path).await?,
+            Method::POST => self.post(&path, &body).await?,
+            Method::PUT => self.put(&path, &body).await?,
+            Method::PATCH => self.patch(&path, &body).await?,
+            Method::DELETE if request.body.is_none() => self.delete(&path).await?,
+            Method::GET | Method::DELETE => return Err(anyhow::anyhow!("{} requests don't take a body", request.method)),
+            _ => return Err(anyhow::anyhow!("Unsupported method {}", request.method)),
+        };
+        Ok(response)
+    }
+
+    fn paginate_request(&self, environment_id: &str, request: &RawRequest, options: PageOptions) -> BoxStream<'_, Result<Value>> {
+        match request.path_in(environment_id) {
+            Ok(path) => self.pages(path, None, options).boxed(),
+            Err(e) => futures::stream::once(async { Err(e) }).boxed(),
+        }
+    }
+}
+
+// Whether two URLs share scheme, host and port
+fn same_origin(url: &str, base_url: &str) -> bool {
+    match (reqwest::Url::parse(url), reqwest::Url::parse(base_url)) {
+        (Ok(a), Ok(b)) => a.origin() == b.origin(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ApiError;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // Serve canned (status, extra headers) responses in order, repeating the last, and count requests
+    async fn mock_server(responses: Vec<(u16, &'static str)>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let (status, headers) = responses[n.min(responses.len() - 1)];
+
+                let mut request = vec![0; 8192];
+                let _ = socket.read(&mut request).await;
+
+                let body = if status == 200 { r#"{"id":"abc"}"# } else { r#"{"error":"slow down"}"# };
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n{}\r\n{}",
+                    status,
+                    body.len(),
+                    headers,
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        (url, hits)
+    }
+
+    fn client(base_url: String, max_attempts: u32) -> DynamicApiClient {
+        let options = ClientOptions {
+            retry: RetryPolicy {
+                max_attempts,
+                base_delay_ms: 1,
+                max_delay_ms: 5_000,
+                jitter: false,
+            },
+            verbose: false,
+            cassette: None,
+        };
+        DynamicApiClient::new("dyn_test".to_string(), base_url, options).unwrap()
+    }
+
+    #[test]
+    fn tokens_must_fit_in_a_header() {
+        let error = DynamicApiClient::new("dyn_test\n".to_string(), String::new(), ClientOptions::default()).err().unwrap();
+        assert_eq!(error.to_string(), "API token contains characters not allowed in an HTTP header");
+    }
+
+    #[test]
+    fn page_paths_encode_the_cursor() {
+        let mut state = PageState {
+            path: "/api/v0/environments/env/users?filter=active".to_string(),
+            key: Some("users"),
+            options: PageOptions { page_size: 50, limit: Some(120) },
+            offset: 0,
+            cursor: None,
+            buffer: VecDeque::new(),
+            yielded: 100,
+            done: false,
+        };
+        assert_eq!(state.page_path(), "/api/v0/environments/env/users?filter=active&limit=20&offset=0");
+
+        state.cursor = Some("a+b&c=d/e f".to_string());
+        let path = state.page_path();
+        assert_eq!(path, "/api/v0/environments/env/users?filter=active&limit=20&cursor=a%2Bb%26c%3Dd%2Fe+f");
+        let url = reqwest::Url::parse(&format!("http://localhost{}", path)).unwrap();
+        let cursor = url.query_pairs().find(|(key, _)| key == "cursor").unwrap().1;
+        assert_eq!(cursor, "a+b&c=d/e f");
+    }
+
+    #[tokio::test]
+    async fn retries_rate_limited_get_until_success() {
+        let (url, hits) = mock_server(vec![(429, "retry-after: 0\r\n"), (429, ""), (200, "")]).await;
+
+        let user = client(url, 3).get_user("env", "abc").await.unwrap();
+
+        assert_eq!(user.id, "abc");
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let (url, hits) = mock_server(vec![(503, "")]).await;
+
+        let error = client(url, 2).get_user("env", "abc").await.unwrap_err();
+
+        assert!(matches!(error.downcast_ref::<ApiError>(), Some(ApiError::Server { status: 503, .. })));
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_post_without_idempotency_key() {
+        let (url, hits) = mock_server(vec![(429, "retry-after: 0\r\n"), (200, "")]).await;
+
+        let error = client(url, 3).create_export("env", &ExportRequest::builder("users").build().unwrap(), None).await.unwrap_err();
+
+        assert!(matches!(error.downcast_ref::<ApiError>(), Some(ApiError::RateLimited { .. })));
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_post_with_idempotency_key() {
+        let (url, hits) = mock_server(vec![(429, "retry-after: 0\r\n"), (200, "")]).await;
+
+        let export = client(url, 3)
+            .create_export("env", &ExportRequest::builder("users").build().unwrap(), Some("key-1"))
+            .await
+            .unwrap();
+
+        assert_eq!(export.id, "abc");
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn does_not_wait_longer_than_max_delay() {
+        let (url, hits) = mock_server(vec![(429, "retry-after: 120\r\n"), (200, "")]).await;
+
+        let error = client(url, 3).get_user("env", "abc").await.unwrap_err();
+
+        assert!(matches!(error.downcast_ref::<ApiError>(), Some(ApiError::RateLimited { .. })));
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn export_request_normalizes_dates_and_filters() {
+        let request = ExportRequest::builder("users")
+            .format("json")
+            .created_after("2024-01-01")
+            .created_before("2024-06-30T12:00:00+02:00")
+            .filter("metadata.plan", "pro")
+            .columns(["id", "email"])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "type": "users",
+                "format": "json",
+                "filter": {
+                    "createdAfter": "2024-01-01T00:00:00.000Z",
+                    "createdBefore": "2024-06-30T10:00:00.000Z",
+                    "metadata.plan": "pro"
+                },
+                "columns": ["id", "email"]
+            })
+        );
+    }
+
+    #[test]
+    fn export_request_rejects_invalid_parameters() {
+        assert!(ExportRequest::builder("payments").build().is_err());
+        assert!(ExportRequest::builder("users").format("xlsx").build().is_err());
+        assert!(ExportRequest::builder("users").created_after("01/02/2024").build().is_err());
+        assert!(ExportRequest::builder("users")
+            .created_after("2024-06-01")
+            .created_before("2024-01-01")
+            .build()
+            .is_err());
+        assert!(ExportRequest::builder("users").columns(["email address"]).build().is_err());
+    }
+
+    #[test]
+    fn export_request_rejects_date_filters() {
+        let error = ExportRequest::builder("users")
+            .created_after("2024-01-01")
+            .filter("createdAfter", "1970-01-01")
+            .build()
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Filter field 'createdAfter' is reserved; use --created-after instead");
+
+        let error = ExportRequest::builder("users").filter("createdBefore", "2030-01-01").build().unwrap_err();
+        assert_eq!(error.to_string(), "Filter field 'createdBefore' is reserved; use --created-before instead");
+    }
+}
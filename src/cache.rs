@@ -0,0 +1,350 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use colored::*;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use reqwest::Method;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::api::{AdminApi, Download, ExportRequest, OrganizationFields, PageOptions, RawRequest, UserFields};
+use crate::completion::RecentIds;
+use crate::models::{Export, Organization, OrganizationMember, User, Wallet};
+
+// An AdminApi decorator that remembers users, organizations and wallets for a short while, so
+// looking the same object up again (e.g. get after list) doesn't cost another request. Writes go
+// straight through and update or evict what they touch. Exports are never cached since their
+// status changes while they're polled. User and organization IDs can also be kept on disk for
+// shell completion.
+
+// How long a cached object is trusted
+pub const DEFAULT_TTL: Duration = Duration::from_secs(30);
+
+// Objects of one kind, keyed by environment and ID
+struct Entries<T> {
+    entries: Mutex<HashMap<(String, String), (Instant, T)>>,
+}
+
+impl<T: Clone> Entries<T> {
+    fn new() -> Self {
+        Entries {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, environment_id: &str, id: &str, ttl: Duration) -> Option<T> {
+        let entries = self.entries.lock().unwrap();
+        let (stored, value) = entries.get(&(environment_id.to_string(), id.to_string()))?;
+        (stored.elapsed() < ttl).then(|| value.clone())
+    }
+
+    fn put(&self, environment_id: &str, id: &str, value: &T) {
+        let key = (environment_id.to_string(), id.to_string());
+        self.entries.lock().unwrap().insert(key, (Instant::now(), value.clone()));
+    }
+
+    fn remove(&self, environment_id: &str, id: &str) {
+        self.entries.lock().unwrap().remove(&(environment_id.to_string(), id.to_string()));
+    }
+
+    fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+// IDs seen and deleted during this run, merged into `path` when the client is dropped
+struct RecentLog {
+    path: PathBuf,
+    seen: RecentIds,
+    deleted: RecentIds,
+}
+
+pub struct CachingAdminApi {
+    inner: Arc<dyn AdminApi>,
+    ttl: Duration,
+    users: Entries<User>,
+    organizations: Entries<Organization>,
+    wallets: Entries<Wallet>,
+    recent: Option<Mutex<RecentLog>>,
+}
+
+impl CachingAdminApi {
+    pub fn new(inner: Arc<dyn AdminApi>, ttl: Duration) -> Self {
+        CachingAdminApi {
+            inner,
+            ttl,
+            users: Entries::new(),
+            organizations: Entries::new(),
+            wallets: Entries::new(),
+            recent: None,
+        }
+    }
+
+    // Also record user and organization IDs in `path` for completion
+    pub fn remember_ids(mut self, path: PathBuf) -> Self {
+        self.recent = Some(Mutex::new(RecentLog {
+            path,
+            seen: RecentIds::default(),
+            deleted: RecentIds::default(),
+        }));
+        self
+    }
+
+    fn saw_user(&self, user: &User) {
+        let label = user.email.as_deref().or(user.phone_number.as_deref()).or(user.alias.as_deref());
+        self.log(|log| {
+            RecentIds::remove(&mut log.deleted.users, &user.id);
+            RecentIds::add(&mut log.seen.users, &user.id, label);
+        });
+    }
+
+    fn saw_organization(&self, org: &Organization) {
+        self.log(|log| {
+            RecentIds::remove(&mut log.deleted.organizations, &org.id);
+            RecentIds::add(&mut log.seen.organizations, &org.id, org.name.as_deref());
+        });
+    }
+
+    fn log(&self, update: impl FnOnce(&mut RecentLog)) {
+        if let Some(recent) = &self.recent {
+            update(&mut recent.lock().unwrap());
+        }
+    }
+}
+
+impl Drop for CachingAdminApi {
+    // Written once at the end rather than on every lookup
+    fn drop(&mut self) {
+        let Some(recent) = &self.recent else {
+            return;
+        };
+        let log = recent.lock().unwrap();
+        if log.seen.is_empty() && log.deleted.is_empty() {
+            return;
+        }
+
+        let mut ids = RecentIds::load(&log.path);
+        ids.merge(&log.seen, &log.deleted);
+        if let Err(e) = ids.save(&log.path) {
+            eprintln!("{} couldn't write to {}: {}", "Warning:".yellow(), log.path.display(), e);
+        }
+    }
+}
+
+#[async_trait]
+impl AdminApi for CachingAdminApi {
+    // Organizations
+    fn list_organizations(&self, environment_id: &str, options: PageOptions) -> BoxStream<'_, Result<Organization>> {
+        let environment_id = environment_id.to_string();
+        self.inner
+            .list_organizations(&environment_id, options)
+            .inspect(move |org| {
+                if let Ok(org) = org {
+                    self.organizations.put(&environment_id, &org.id, org);
+                    self.saw_organization(org);
+                }
+            })
+            .boxed()
+    }
+
+    async fn get_organization(&self, environment_id: &str, org_id: &str) -> Result<Organization> {
+        if let Some(org) = self.organizations.get(environment_id, org_id, self.ttl) {
+            return Ok(org);
+        }
+        let org = self.inner.get_organization(environment_id, org_id).await?;
+        self.organizations.put(environment_id, org_id, &org);
+        self.saw_organization(&org);
+        Ok(org)
+    }
+
+    async fn create_organization(&self, environment_id: &str, fields: &OrganizationFields) -> Result<Organization> {
+        let org = self.inner.create_organization(environment_id, fields).await?;
+        self.organizations.put(environment_id, &org.id, &org);
+        self.saw_organization(&org);
+        Ok(org)
+    }
+
+    async fn update_organization(&self, environment_id: &str, org_id: &str, fields: &OrganizationFields) -> Result<Organization> {
+        self.organizations.remove(environment_id, org_id);
+        let org = self.inner.update_organization(environment_id, org_id, fields).await?;
+        self.organizations.put(environment_id, org_id, &org);
+        self.saw_organization(&org);
+        Ok(org)
+    }
+
+    async fn delete_organization(&self, environment_id: &str, org_id: &str) -> Result<()> {
+        self.organizations.remove(environment_id, org_id);
+        self.inner.delete_organization(environment_id, org_id).await?;
+        self.log(|log| {
+            RecentIds::remove(&mut log.seen.organizations, org_id);
+            RecentIds::add(&mut log.deleted.organizations, org_id, None);
+        });
+        Ok(())
+    }
+
+    // Organization members
+    fn list_organization_members(&self, environment_id: &str, org_id: &str, options: PageOptions) -> BoxStream<'_, Result<OrganizationMember>> {
+        self.inner.list_organization_members(environment_id, org_id, options)
+    }
+
+    async fn add_organization_member(&self, environment_id: &str, org_id: &str, user_id: &str, role: &str) -> Result<OrganizationMember> {
+        self.inner.add_organization_member(environment_id, org_id, user_id, role).await
+    }
+
+    async fn set_organization_member_role(&self, environment_id: &str, org_id: &str, user_id: &str, role: &str) -> Result<OrganizationMember> {
+        self.inner.set_organization_member_role(environment_id, org_id, user_id, role).await
+    }
+
+    async fn remove_organization_member(&self, environment_id: &str, org_id: &str, user_id: &str) -> Result<()> {
+        self.inner.remove_organization_member(environment_id, org_id, user_id).await
+    }
+
+    // Exports
+    fn list_exports(&self, environment_id: &str, options: PageOptions) -> BoxStream<'_, Result<Export>> {
+        self.inner.list_exports(environment_id, options)
+    }
+
+    async fn get_export(&self, environment_id: &str, export_id: &str) -> Result<Export> {
+        self.inner.get_export(environment_id, export_id).await
+    }
+
+    async fn create_export(&self, environment_id: &str, params: &ExportRequest, idempotency_key: Option<&str>) -> Result<Export> {
+        self.inner.create_export(environment_id, params, idempotency_key).await
+    }
+
+    async fn download(&self, url: &str, dest: &Path, progress: &mut (dyn FnMut(u64, Option<u64>) + Send)) -> Result<Download> {
+        self.inner.download(url, dest, progress).await
+    }
+
+    // Wallets
+    fn list_wallets(&self, environment_id: &str, options: PageOptions) -> BoxStream<'_, Result<Wallet>> {
+        let environment_id = environment_id.to_string();
+        self.inner
+            .list_wallets(&environment_id, options)
+            .inspect(move |wallet| {
+                if let Ok(wallet) = wallet {
+                    self.wallets.put(&environment_id, &wallet.id, wallet);
+                }
+            })
+            .boxed()
+    }
+
+    fn list_user_wallets(&self, environment_id: &str, user_id: &str, options: PageOptions) -> BoxStream<'_, Result<Wallet>> {
+        self.inner.list_user_wallets(environment_id, user_id, options)
+    }
+
+    async fn get_wallet(&self, environment_id: &str, wallet_id: &str) -> Result<Wallet> {
+        if let Some(wallet) = self.wallets.get(environment_id, wallet_id, self.ttl) {
+            return Ok(wallet);
+        }
+        let wallet = self.inner.get_wallet(environment_id, wallet_id).await?;
+        self.wallets.put(environment_id, wallet_id, &wallet);
+        Ok(wallet)
+    }
+
+    // Users
+    fn list_users(&self, environment_id: &str, options: PageOptions) -> BoxStream<'_, Result<User>> {
+        let environment_id = environment_id.to_string();
+        self.inner
+            .list_users(&environment_id, options)
+            .inspect(move |user| {
+                if let Ok(user) = user {
+                    self.users.put(&environment_id, &user.id, user);
+                    self.saw_user(user);
+                }
+            })
+            .boxed()
+    }
+
+    async fn get_user(&self, environment_id: &str, user_id: &str) -> Result<User> {
+        if let Some(user) = self.users.get(environment_id, user_id, self.ttl) {
+            return Ok(user);
+        }
+        let user = self.inner.get_user(environment_id, user_id).await?;
+        self.users.put(environment_id, user_id, &user);
+        self.saw_user(&user);
+        Ok(user)
+    }
+
+    async fn create_user(&self, environment_id: &str, fields: &UserFields, idempotency_key: Option<&str>) -> Result<User> {
+        let user = self.inner.create_user(environment_id, fields, idempotency_key).await?;
+        self.users.put(environment_id, &user.id, &user);
+        self.saw_user(&user);
+        Ok(user)
+    }
+
+    async fn update_user(&self, environment_id: &str, user_id: &str, fields: &UserFields) -> Result<User> {
+        self.users.remove(environment_id, user_id);
+        let user = self.inner.update_user(environment_id, user_id, fields).await?;
+        self.users.put(environment_id, user_id, &user);
+        self.saw_user(&user);
+        Ok(user)
+    }
+
+    async fn delete_user(&self, environment_id: &str, user_id: &str) -> Result<()> {
+        self.users.remove(environment_id, user_id);
+        self.inner.delete_user(environment_id, user_id).await?;
+        self.log(|log| {
+            RecentIds::remove(&mut log.seen.users, user_id);
+            RecentIds::add(&mut log.deleted.users, user_id, None);
+        });
+        Ok(())
+    }
+
+    // Blocking changes the user's state, so the cached copy is dropped rather than patched
+    async fn block_user(&self, environment_id: &str, user_id: &str) -> Result<()> {
+        self.users.remove(environment_id, user_id);
+        self.inner.block_user(environment_id, user_id).await
+    }
+
+    async fn unblock_user(&self, environment_id: &str, user_id: &str) -> Result<()> {
+        self.users.remove(environment_id, user_id);
+        self.inner.unblock_user(environment_id, user_id).await
+    }
+
+    // There's no telling what a raw write touches, so it drops everything
+    async fn request(&self, environment_id: &str, request: &RawRequest) -> Result<Value> {
+        if request.method != Method::GET {
+            self.users.clear();
+            self.organizations.clear();
+            self.wallets.clear();
+        }
+        self.inner.request(environment_id, request).await
+    }
+
+    fn paginate_request(&self, environment_id: &str, request: &RawRequest, options: PageOptions) -> BoxStream<'_, Result<Value>> {
+        self.inner.paginate_request(environment_id, request, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_api::{FakeAdminApi, ENVIRONMENT_ID};
+
+    #[tokio::test]
+    async fn repeat_lookups_are_served_from_the_cache() {
+        let fake = FakeAdminApi::sample();
+        let api = CachingAdminApi::new(fake.clone(), DEFAULT_TTL);
+
+        let users: Vec<User> = api.list_users(ENVIRONMENT_ID, PageOptions { page_size: 10, limit: None }).map(Result::unwrap).collect().await;
+        assert_eq!(api.get_user(ENVIRONMENT_ID, &users[0].id).await.unwrap(), users[0]);
+        api.get_wallet(ENVIRONMENT_ID, "wallet-1").await.unwrap();
+        api.get_wallet(ENVIRONMENT_ID, "wallet-1").await.unwrap();
+        assert_eq!(fake.calls(), ["list_users", "get_wallet wallet-1"]);
+
+        // Writes evict what they touch, and exports are always fetched
+        api.block_user(ENVIRONMENT_ID, "user-1").await.unwrap();
+        assert_eq!(api.get_user(ENVIRONMENT_ID, "user-1").await.unwrap().extra["blocked"], true);
+        api.delete_user(ENVIRONMENT_ID, "user-1").await.unwrap();
+        assert!(api.get_user(ENVIRONMENT_ID, "user-1").await.is_err());
+
+        let expired = CachingAdminApi::new(fake.clone(), Duration::ZERO);
+        expired.get_user(ENVIRONMENT_ID, "user-2").await.unwrap();
+        expired.get_user(ENVIRONMENT_ID, "user-2").await.unwrap();
+        assert_eq!(fake.calls().iter().filter(|call| *call == "get_user user-2").count(), 2);
+    }
+}
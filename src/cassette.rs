@@ -0,0 +1,414 @@
+use anyhow::{Context, Result};
+use chacha20poly1305::aead::OsRng;
+use chacha20poly1305::aead::rand_core::RngCore;
+use hmac::{Hmac, Mac};
+use regex::{Captures, Regex};
+use reqwest::{Request, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+use crate::api;
+
+// HTTP cassettes: a recording of the client's request/response pairs that can be played back
+// without a network, so regression tests run against real API responses deterministically.
+// Cassettes are JSON Lines: a header holding the cassette's salt, then one interaction per line.
+// The Authorization header is never recorded, and PII in bodies and query strings is replaced
+// with pseudonyms keyed by the salt, stable within a cassette but not across them. The salt is
+// stored in the cassette, so this stops matching people across cassettes but not guessing: anyone
+// with the file can hash candidate emails with its salt and compare.
+
+// Fields holding personal data, replaced with a pseudonym derived from the value so that the
+// same input always scrubs the same way and replayed requests still match
+const PII_FIELDS: [&str; 7] = ["email", "phoneNumber", "phone", "firstName", "lastName", "alias", "username"];
+
+// Fields holding credentials, blanked out entirely
+const SECRET_FIELDS: [&str; 5] = ["token", "apiToken", "secret", "password", "authorization"];
+
+const REDACTED: &str = "[REDACTED]";
+
+// Random bytes keying each cassette's pseudonyms
+const SALT_LEN: usize = 16;
+
+// Emails and phone numbers (international with a leading +, or US style) in free text, such as
+// error messages and non-JSON bodies
+static EMAIL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}").unwrap());
+static PHONE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\+[1-9](?:[ .()-]{0,2}[0-9]){6,14}|(?:\([0-9]{3}\) ?|\b[0-9]{3}[ .-])[0-9]{3}[ .-][0-9]{4}\b").unwrap()
+});
+
+// First line of a cassette
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    // Hex key for the pseudonyms; recording again picks a new one
+    salt: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    // Path and query, relative to the base URL
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<String>,
+    // JSON bodies; anything else is kept in `text`, with emails and phone numbers scrubbed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+impl RecordedResponse {
+    pub fn new(status: u16, retry_after: Option<String>, bytes: &[u8], scrubber: &Scrubber) -> Self {
+        let (body, text) = if bytes.iter().all(u8::is_ascii_whitespace) {
+            (None, None)
+        } else {
+            match serde_json::from_slice::<Value>(bytes) {
+                Ok(mut body) => {
+                    scrubber.scrub(&mut body);
+                    (Some(body), None)
+                }
+                Err(_) => (None, Some(scrubber.scrub_text(&String::from_utf8_lossy(bytes)))),
+            }
+        };
+        RecordedResponse { status, retry_after, body, text }
+    }
+
+    // The body as it came over the wire
+    pub fn bytes(&self) -> Vec<u8> {
+        match (&self.body, &self.text) {
+            (Some(body), _) => serde_json::to_vec(body).unwrap_or_default(),
+            (None, Some(text)) => text.as_bytes().to_vec(),
+            (None, None) => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug)]
+enum Mode {
+    // Appends every interaction to the open cassette file
+    Record(Mutex<File>),
+    // Recorded interactions, each flagged once it has been played back
+    Replay(Mutex<Vec<(Interaction, bool)>>),
+}
+
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: Mode,
+    scrubber: Scrubber,
+}
+
+impl Cassette {
+    // Start a new recording at `path`, replacing any earlier one
+    pub fn record(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)
+            .with_context(|| format!("Could not create cassette {}", path.display()))?;
+
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let mut header = serde_json::to_string(&Header { salt: api::hex(&salt) })?;
+        header.push('\n');
+        file.write_all(header.as_bytes())?;
+
+        Ok(Cassette {
+            path: path.to_path_buf(),
+            mode: Mode::Record(Mutex::new(file)),
+            scrubber: Scrubber::new(&salt),
+        })
+    }
+
+    // Load a recording to play back
+    pub fn replay(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path).with_context(|| format!("Could not read cassette {}", path.display()))?;
+        let mut lines = contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+        let salt = lines
+            .next()
+            .and_then(|(_, line)| serde_json::from_str::<Header>(line).ok())
+            .and_then(|header| api::from_hex(&header.salt))
+            .with_context(|| format!("{} has no salt header; record it again with --record", path.display()))?;
+        let interactions = lines
+            .map(|(i, line)| {
+                serde_json::from_str(line)
+                    .map(|interaction| (interaction, false))
+                    .with_context(|| format!("{} line {}: invalid interaction", path.display(), i + 1))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Cassette {
+            path: path.to_path_buf(),
+            mode: Mode::Replay(Mutex::new(interactions)),
+            scrubber: Scrubber::new(&salt),
+        })
+    }
+
+    pub fn is_replay(&self) -> bool {
+        matches!(self.mode, Mode::Replay(_))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // A response scrubbed with this cassette's salt, ready to save
+    pub fn response(&self, status: u16, retry_after: Option<String>, bytes: &[u8]) -> RecordedResponse {
+        RecordedResponse::new(status, retry_after, bytes, &self.scrubber)
+    }
+
+    // The scrubbed form of a request, as it's recorded and matched
+    pub fn request(&self, request: &Request, base_url: &str) -> RecordedRequest {
+        let url = self.scrubber.scrub_query(request.url());
+        let path = url
+            .as_str()
+            .strip_prefix(base_url.trim_end_matches('/'))
+            .unwrap_or(url.as_str())
+            .to_string();
+
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .filter(|bytes| !bytes.is_empty())
+            .map(|bytes| match serde_json::from_slice(bytes) {
+                Ok(mut body) => {
+                    self.scrubber.scrub(&mut body);
+                    body
+                }
+                Err(_) => Value::String(self.scrubber.scrub_text(&String::from_utf8_lossy(bytes))),
+            });
+
+        RecordedRequest {
+            method: request.method().to_string(),
+            path,
+            body,
+        }
+    }
+
+    // Append an interaction to a recording
+    pub fn save(&self, request: RecordedRequest, response: RecordedResponse) -> Result<()> {
+        let Mode::Record(file) = &self.mode else {
+            return Ok(());
+        };
+        let mut line = serde_json::to_string(&Interaction { request, response })?;
+        line.push('\n');
+        let mut file = file.lock().unwrap();
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    // The first response recorded for this request that hasn't been played back yet. Requests
+    // are matched on method, path and body rather than position, so concurrent requests replay
+    // correctly, while repeats of the same request (retries, polling) come back in order.
+    pub fn find(&self, request: &RecordedRequest) -> Option<RecordedResponse> {
+        let Mode::Replay(interactions) = &self.mode else {
+            return None;
+        };
+        let mut interactions = interactions.lock().unwrap();
+        let (interaction, used) = interactions
+            .iter_mut()
+            .find(|(interaction, used)| !*used && interaction.request == *request)?;
+        *used = true;
+        Some(interaction.response.clone())
+    }
+}
+
+// Replaces PII and credentials, deriving pseudonyms with HMAC-SHA256 keyed by a cassette's salt
+#[derive(Debug, Clone)]
+pub struct Scrubber {
+    salt: Vec<u8>,
+}
+
+impl Scrubber {
+    pub fn new(salt: &[u8]) -> Self {
+        Scrubber { salt: salt.to_vec() }
+    }
+
+    // Replace PII and credentials throughout a JSON value
+    pub fn scrub(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    match value {
+                        Value::String(text) if PII_FIELDS.contains(&key.as_str()) => *text = self.pseudonym(key, text),
+                        Value::String(text) if SECRET_FIELDS.contains(&key.as_str()) => *text = REDACTED.to_string(),
+                        _ => self.scrub(value),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.scrub(item)),
+            Value::String(text) => *text = self.scrub_text(text),
+            _ => {}
+        }
+    }
+
+    // Replace emails and phone numbers wherever they appear in free text
+    pub fn scrub_text(&self, text: &str) -> String {
+        let text = EMAIL.replace_all(text, |found: &Captures| self.pseudonym("email", &found[0]));
+        PHONE
+            .replace_all(&text, |found: &Captures| self.pseudonym("phone", &found[0]))
+            .into_owned()
+    }
+
+    fn scrub_query(&self, url: &Url) -> Url {
+        let mut url = url.clone();
+        if url.query().is_none() {
+            return url;
+        }
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(key, value)| {
+                let value = if PII_FIELDS.contains(&key.as_ref()) {
+                    self.pseudonym(&key, &value)
+                } else if SECRET_FIELDS.contains(&key.as_ref()) {
+                    REDACTED.to_string()
+                } else {
+                    self.scrub_text(&value)
+                };
+                (key.into_owned(), value)
+            })
+            .collect();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+        url
+    }
+
+    // A stand-in that keeps the field's shape, so scrubbed emails still look like emails
+    fn pseudonym(&self, field: &str, value: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.salt).expect("HMAC takes keys of any length");
+        mac.update(value.as_bytes());
+        let hash = mac.finalize().into_bytes();
+        let hex = api::hex(&hash[..8]);
+        match field {
+            "email" => format!("user-{}@example.com", hex),
+            "phoneNumber" | "phone" => format!("+1555{:07}", u64::from_be_bytes(hash[..8].try_into().unwrap()) % 10_000_000),
+            _ => format!("{}-{}", field, hex),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use serde_json::json;
+
+    fn temp_cassette(name: &str) -> PathBuf {
+        test_support::temp_dir(&format!("cassette-{}", name)).join("cassette.jsonl")
+    }
+
+    #[test]
+    fn scrubs_pii_consistently() {
+        let scrubber = Scrubber::new(b"salt");
+        let mut body = json!({
+            "user": {"id": "u1", "email": "ada@example.com", "firstName": "Ada", "phoneNumber": "+442071234567"},
+            "users": [{"email": "ada@example.com"}],
+            "message": "User ada@example.com already exists",
+            "apiToken": "dyn_secret",
+        });
+        scrubber.scrub(&mut body);
+
+        let email = body["user"]["email"].as_str().unwrap();
+        assert!(email.starts_with("user-") && email.ends_with("@example.com"));
+        assert_ne!(email, "ada@example.com");
+        assert_eq!(body["users"][0]["email"], email);
+        assert_eq!(body["message"], format!("User {} already exists", email));
+        assert_eq!(body["user"]["id"], "u1");
+        assert!(body["user"]["phoneNumber"].as_str().unwrap().starts_with("+1555"));
+        assert_eq!(body["apiToken"], REDACTED);
+
+        // Another cassette's salt gives other pseudonyms
+        let mut other = json!({"email": "ada@example.com"});
+        Scrubber::new(b"other salt").scrub(&mut other);
+        assert_ne!(other["email"], email);
+    }
+
+    #[test]
+    fn scrubs_emails_and_phone_numbers_in_text() {
+        let scrubber = Scrubber::new(b"salt");
+        let text = "<p>ada@example.com called +44 20 7123 4567 and (555) 123-4567 on 2024-01-02 \
+                    about 00000000-0000-4000-8000-000000000000, code 1234567</p>";
+        let response = RecordedResponse::new(502, None, text.as_bytes(), &scrubber);
+        let scrubbed = response.text.unwrap();
+
+        for pii in ["ada@example.com", "7123 4567", "123-4567"] {
+            assert!(!scrubbed.contains(pii), "{} left in {}", pii, scrubbed);
+        }
+        for kept in ["<p>", "2024-01-02", "00000000-0000-4000-8000-000000000000", "code 1234567"] {
+            assert!(scrubbed.contains(kept), "{} lost from {}", kept, scrubbed);
+        }
+        assert!(scrubbed.contains(&scrubber.pseudonym("email", "ada@example.com")));
+        assert!(scrubbed.contains(&scrubber.pseudonym("phone", "+44 20 7123 4567")));
+    }
+
+    #[test]
+    fn requests_are_recorded_relative_and_scrubbed() {
+        let path = temp_cassette("requests");
+        let client = reqwest::Client::new();
+        let request = client
+            .post("https://api.example.com/api/v0/users?email=ada%40example.com&q=ada%40example.com&limit=5")
+            .header(reqwest::header::AUTHORIZATION, "Bearer dyn_secret")
+            .json(&json!({"email": "ada@example.com"}))
+            .build()
+            .unwrap();
+
+        let recording = Cassette::record(&path).unwrap();
+        let recorded = recording.request(&request, "https://api.example.com");
+        assert_eq!(recorded.method, "POST");
+        assert!(recorded.path.starts_with("/api/v0/users?email=user-"));
+        assert!(recorded.path.ends_with("&limit=5"));
+        assert!(!serde_json::to_string(&recorded).unwrap().contains("ada"));
+        drop(recording);
+
+        // Replays scrub with the salt saved in the header, so the request still matches
+        let replay = Cassette::replay(&path).unwrap();
+        assert_eq!(replay.request(&request, "https://api.example.com"), recorded);
+        assert_ne!(Cassette::record(&path).unwrap().request(&request, "https://api.example.com"), recorded);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn replays_matching_interactions_in_order() {
+        let path = temp_cassette("replay");
+        let request = RecordedRequest { method: "GET".into(), path: "/users/1".into(), body: None };
+
+        let recording = Cassette::record(&path).unwrap();
+        recording.save(request.clone(), recording.response(429, Some("0".into()), b"")).unwrap();
+        recording.save(request.clone(), recording.response(200, None, br#"{"id":"1"}"#)).unwrap();
+        drop(recording);
+
+        let cassette = Cassette::replay(&path).unwrap();
+        assert_eq!(cassette.find(&request).unwrap().status, 429);
+        assert_eq!(cassette.find(&request).unwrap().bytes(), br#"{"id":"1"}"#);
+        assert!(cassette.find(&request).is_none());
+
+        // Cassettes need the header their pseudonyms were made with
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::write(&path, contents.split_once('\n').unwrap().1).unwrap();
+        let error = Cassette::replay(&path).unwrap_err();
+        assert!(error.to_string().contains("has no salt header"), "{}", error);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::{Arg, ArgAction};
+use reqwest::Method;
+use serde_json::{Map, Value};
+use std::fs;
+use std::io::{self, Read};
+use std::sync::Arc;
+
+use crate::api::{AdminApi, RawRequest};
+use crate::command::args;
+use crate::commands::Command;
+use crate::context::{CommandOutput, ExecutionContext};
+
+// Methods `api` can send
+const METHODS: [&str; 5] = ["GET", "POST", "PUT", "PATCH", "DELETE"];
+
+// Command to call any Admin API endpoint, for what the other commands don't cover yet
+pub struct ApiRequestCommand {
+    api_client: Arc<dyn AdminApi>,
+}
+
+impl ApiRequestCommand {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
+        Self { api_client }
+    }
+}
+
+#[async_trait]
+impl Command for ApiRequestCommand {
+    fn name(&self) -> &str {
+        "api"
+    }
+
+    fn description(&self) -> &str {
+        "Send a request to any Admin API endpoint"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::new("method")
+                .value_name("METHOD")
+                .help("HTTP method")
+                .value_parser(METHODS)
+                .ignore_case(true),
+            Arg::new("path")
+                .value_name("PATH")
+                .help("Endpoint path, e.g. environments/{env}/users; {env} is the environment ID"),
+            args::environment_arg(),
+            Arg::new("field")
+                .long("field")
+                .short('f')
+                .value_name("KEY=VALUE")
+                .help("Body field, or query parameter for GET and DELETE; true, false, null and numbers are sent as JSON")
+                .value_parser(parse_field)
+                .action(ArgAction::Append),
+            Arg::new("input")
+                .long("input")
+                .value_name("FILE")
+                .help("Read the JSON body from FILE (- for stdin); --field values become query parameters"),
+            // Read by args::page_options as --all
+            Arg::new("all")
+                .long("paginate")
+                .help("Follow every page of a GET list endpoint, printing the items")
+                .action(ArgAction::SetTrue),
+            Arg::new("page-size")
+                .long("page-size")
+                .value_name("N")
+                .help("Number of items requested per page with --paginate")
+                .value_parser(clap::value_parser!(u32).range(1..))
+                .requires("all"),
+        ]
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let method = ctx.value_or_select("method", "Select method", &METHODS)?.to_uppercase();
+        let method = Method::from_bytes(method.as_bytes())?;
+        let path = ctx.value_or_prompt("path", "Enter endpoint path")?;
+        // Only paths naming {env} need one, so account-wide endpoints work without an environment
+        let environment_id = if path.contains("{env}") { ctx.environment_id()? } else { String::new() };
+        let fields: Vec<(String, String)> = ctx
+            .args
+            .get_many::<(String, String)>("field")
+            .map(|fields| fields.cloned().collect())
+            .unwrap_or_default();
+        let input = args::get_value(ctx.args, "input");
+
+        // Like `gh api`, fields make the body unless it comes from --input or the method has none
+        let mut request = RawRequest {
+            method: method.clone(),
+            path,
+            query: Vec::new(),
+            body: None,
+        };
+        if let Some(input) = input {
+            request.body = Some(read_body(&input)?);
+            request.query = fields;
+        } else if method == Method::GET || method == Method::DELETE {
+            request.query = fields;
+        } else {
+            let body: Map<String, Value> = fields.into_iter().map(|(key, value)| (key, field_value(&value))).collect();
+            request.body = Some(Value::Object(body));
+        }
+
+        if ctx.args.get_flag("all") {
+            if method != Method::GET {
+                return Err(anyhow::anyhow!("--paginate only works with GET"));
+            }
+            let items = self.api_client.paginate_request(&environment_id, &request, args::page_options(ctx.args));
+            return Ok(CommandOutput::list(items, &[]));
+        }
+
+        if method != Method::GET && ctx.skip_in_dry_run(&format!("send {} {}", method, request.path_in(&environment_id)?))? {
+            return Ok(request.body.map_or(CommandOutput::Empty, CommandOutput::Item));
+        }
+
+        match self.api_client.request(&environment_id, &request).await? {
+            Value::Null => Ok(CommandOutput::Empty),
+            response => Ok(CommandOutput::Item(response)),
+        }
+    }
+}
+
+// Parse a --field value of the form KEY=VALUE
+fn parse_field(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{}'", value)),
+    }
+}
+
+// A body field's JSON value: literals and numbers as themselves, anything else as a string
+fn field_value(value: &str) -> Value {
+    match serde_json::from_str::<Value>(value) {
+        Ok(literal @ (Value::Bool(_) | Value::Null | Value::Number(_))) => literal,
+        _ => Value::String(value.to_string()),
+    }
+}
+
+// A JSON body from a file, or stdin for -
+fn read_body(input: &str) -> Result<Value> {
+    let contents = if input == "-" {
+        let mut contents = String::new();
+        io::stdin().read_to_string(&mut contents)?;
+        contents
+    } else {
+        fs::read_to_string(input).map_err(|e| anyhow::anyhow!("Could not read {}: {}", input, e))?
+    };
+    serde_json::from_str(&contents).map_err(|e| anyhow::anyhow!("{} isn't valid JSON: {}", input, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_api::{execute, FakeAdminApi, ENVIRONMENT_ID};
+    use crate::test_support;
+
+    async fn run(command: &ApiRequestCommand, args: &[&str]) -> Value {
+        serde_json::from_str(&execute(command, args).await.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn api_sends_fields_as_body_or_query() {
+        let api = FakeAdminApi::sample();
+        let command = ApiRequestCommand::new(api.clone());
+
+        let response = run(&command, &["api", "get", "environments/{env}/users", "-f", "q=ada lovelace"]).await;
+        assert_eq!(response["path"], format!("/api/v0/environments/{}/users?q=ada+lovelace", ENVIRONMENT_ID));
+
+        let response = run(&command, &["api", "PATCH", "/api/v0/users/user-1", "-f", "alias=ada", "-f", "age=36", "-f", "admin=true"]).await;
+        assert_eq!(response["body"], serde_json::json!({"alias": "ada", "age": 36, "admin": true}));
+
+        let dir = test_support::temp_dir("api");
+        let input = dir.join("body.json");
+        fs::write(&input, r#"{"name": "Initech"}"#).unwrap();
+        let response = run(&command, &["api", "POST", "organizations", "--input", input.to_str().unwrap(), "-f", "dry=1"]).await;
+        assert_eq!((response["path"].as_str(), &response["body"]), (Some("/api/v0/organizations?dry=1"), &serde_json::json!({"name": "Initech"})));
+
+        // A dry run sends nothing
+        execute(&command, &["api", "DELETE", "users/user-1", "--dry-run"]).await.unwrap();
+        assert_eq!(api.calls().len(), 3);
+
+        let users = run(&command, &["api", "GET", "environments/{env}/users", "--paginate"]).await;
+        assert_eq!(users.as_array().unwrap().len(), 2);
+        assert!(execute(&command, &["api", "POST", "users", "--paginate"]).await.is_err());
+        assert!(execute(&command, &["api", "GET", "https://example.com/steal"]).await.is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn api_only_resolves_the_environment_for_env_paths() {
+        let command = ApiRequestCommand::new(FakeAdminApi::sample());
+
+        let response = run(&command, &["api", "GET", "/api/v0/sdk/health", "--env", "nowhere"]).await;
+        assert_eq!(response["path"], "/api/v0/sdk/health");
+
+        let error = execute(&command, &["api", "GET", "environments/{env}/users", "--env", "nowhere"]).await.unwrap_err();
+        assert!(error.to_string().contains("Unknown environment alias 'nowhere'"), "{}", error);
+    }
+}
@@ -0,0 +1,231 @@
+use anyhow::Result;
+use clap::{Arg, ArgAction, ArgMatches};
+
+use crate::api::PageOptions;
+use crate::completion;
+use crate::environment::{EnvironmentSource, Environments};
+use crate::output::OutputFormat;
+use crate::prompt::{Prompter, Question};
+
+// Flags accepted by every command, declared once on the root command
+pub fn global_args() -> Vec<Arg> {
+    vec![
+        Arg::new("config")
+            .long("config")
+            .value_name("PATH")
+            .help("Read configuration from PATH instead of the default config.toml")
+            .global(true),
+        Arg::new("token")
+            .long("token")
+            .value_name("TOKEN")
+            .help("API token, overriding the profile and DYNAMIC_API_TOKEN")
+            .global(true),
+        Arg::new("base-url")
+            .long("base-url")
+            .value_name("URL")
+            .help("API base URL, overriding the profile and DYNAMIC_BASE_URL")
+            .global(true),
+        Arg::new("profile")
+            .long("profile")
+            .value_name("NAME")
+            .help("Config profile to use (overrides DYNAMIC_ADMIN_PROFILE and the default profile)")
+            .global(true),
+        Arg::new("env")
+            .long("env")
+            .value_name("ID|ALIAS")
+            .help("Environment ID or alias to use instead of the active environment")
+            .add(completion::environment_candidates())
+            .global(true),
+        Arg::new("output")
+            .long("output")
+            .short('o')
+            .value_name("FORMAT")
+            .help("Output format (defaults to table on a terminal, json when piped)")
+            .value_parser(clap::value_parser!(OutputFormat))
+            .global(true),
+        Arg::new("columns")
+            .long("columns")
+            .value_name("COLUMNS")
+            .help("Comma-separated fields to include, e.g. id,email,createdAt")
+            .value_delimiter(',')
+            .global(true),
+        Arg::new("verbose")
+            .long("verbose")
+            .short('v')
+            .help("Print retries and other diagnostics to stderr")
+            .action(ArgAction::SetTrue)
+            .global(true),
+        Arg::new("dry-run")
+            .long("dry-run")
+            .help("Show what would change without changing anything")
+            .action(ArgAction::SetTrue)
+            .global(true),
+        Arg::new("record")
+            .long("record")
+            .value_name("CASSETTE")
+            .help("Record API requests and responses to CASSETTE, with credentials and PII scrubbed")
+            .conflicts_with("replay")
+            .global(true),
+        Arg::new("replay")
+            .long("replay")
+            .value_name("CASSETTE")
+            .help("Answer API requests from a recorded CASSETTE instead of the network")
+            .global(true),
+    ]
+}
+
+// --environment-id flag shared by every environment-scoped command
+pub fn environment_arg() -> Arg {
+    Arg::new("environment-id")
+        .long("environment-id")
+        .value_name("ENVIRONMENT_ID")
+        .help("Environment ID or alias (defaults to the active environment)")
+        .add(completion::environment_candidates())
+}
+
+// Default number of items requested per page
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+// --limit, --page-size and --all for list commands
+pub fn pagination_args() -> Vec<Arg> {
+    vec![
+        Arg::new("limit")
+            .long("limit")
+            .value_name("N")
+            .help("Maximum number of items to fetch (defaults to one page)")
+            .value_parser(clap::value_parser!(usize)),
+        Arg::new("page-size")
+            .long("page-size")
+            .value_name("N")
+            .help("Number of items requested per page")
+            .value_parser(clap::value_parser!(u32).range(1..)),
+        Arg::new("all")
+            .long("all")
+            .help("Follow every page until the listing is exhausted")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("limit"),
+    ]
+}
+
+// Pagination settings from --limit/--page-size/--all; without either, fetch a single page
+pub fn page_options(matches: &ArgMatches) -> PageOptions {
+    let page_size = matches
+        .try_get_one::<u32>("page-size")
+        .ok()
+        .flatten()
+        .map_or(DEFAULT_PAGE_SIZE, |size| *size as usize);
+    let limit = matches.try_get_one::<usize>("limit").ok().flatten().copied();
+    let all = matches.try_get_one::<bool>("all").ok().flatten().copied().unwrap_or(false);
+
+    PageOptions {
+        page_size,
+        limit: if all { None } else { Some(limit.unwrap_or(page_size)) },
+    }
+}
+
+// Object ID accepted either positionally or as a --<id> flag
+pub fn id_args(id: &'static str, value_name: &'static str, help: &'static str) -> Vec<Arg> {
+    vec![
+        complete_ids(Arg::new(id), id)
+            .value_name(value_name)
+            .help(help),
+        complete_ids(Arg::new(flag_id(id)), id)
+            .long(id)
+            .value_name(value_name)
+            .help(help)
+            .conflicts_with(id),
+    ]
+}
+
+// Offer recently seen IDs of this kind when completing the argument in the shell
+pub fn complete_ids(arg: Arg, id: &str) -> Arg {
+    match completion::id_candidates(id) {
+        Some(candidates) => arg.add(candidates),
+        None => arg,
+    }
+}
+
+fn flag_id(id: &str) -> String {
+    format!("{}-flag", id)
+}
+
+// Look up an argument value, whether given positionally or as a flag
+pub fn get_value(matches: &ArgMatches, id: &str) -> Option<String> {
+    [id.to_string(), flag_id(id)]
+        .iter()
+        .find_map(|id| matches.try_get_one::<String>(id).ok().flatten().cloned())
+}
+
+// Resolve the environment: --environment-id, then the global --env, then the active environment
+pub fn resolve_environment(matches: &ArgMatches, environments: &Environments) -> Result<(String, EnvironmentSource)> {
+    for id in ["environment-id", "env"] {
+        if let Some(value) = get_value(matches, id) {
+            return Ok((environments.resolve(&value)?, EnvironmentSource::Flag));
+        }
+    }
+
+    environments.current()
+}
+
+// Read an argument value, asking for it only when missing
+pub fn value_or_prompt(matches: &ArgMatches, definitions: &[Arg], prompter: &dyn Prompter, id: &str, prompt: &str) -> Result<String> {
+    if let Some(value) = get_value(matches, id) {
+        return Ok(value);
+    }
+    prompter.text(&question_for(definitions, id, prompt))
+}
+
+// Read a value that must be one of `items`, asking with a selector when missing
+pub fn value_or_select(
+    matches: &ArgMatches,
+    definitions: &[Arg],
+    prompter: &dyn Prompter,
+    id: &str,
+    prompt: &str,
+    items: &[&str],
+) -> Result<String> {
+    if let Some(value) = get_value(matches, id) {
+        return Ok(value);
+    }
+    let selection = prompter.select(&question_for(definitions, id, prompt), items)?;
+    Ok(items[selection].to_string())
+}
+
+// A question for a missing argument, naming how it's passed: as a flag when it has one, else by
+// its positional value name
+pub fn question_for(definitions: &[Arg], id: &str, prompt: &str) -> Question {
+    let question = Question::new(prompt);
+    let flag_id = flag_id(id);
+    let definition = [flag_id.as_str(), id]
+        .iter()
+        .find_map(|id| definitions.iter().find(|arg| arg.get_id() == *id));
+
+    match definition {
+        Some(arg) => match arg.get_long() {
+            Some(long) => question.flag(long),
+            None => {
+                let value_name = arg.get_value_names().and_then(|names| names.first());
+                question.positional(&value_name.map_or_else(|| id.to_uppercase(), |name| name.to_string()))
+            }
+        },
+        // Not one of the command's own arguments
+        None => question.flag(id),
+    }
+}
+
+// --yes flag for skipping confirmation of destructive commands
+pub fn yes_arg() -> Arg {
+    Arg::new("yes")
+        .long("yes")
+        .short('y')
+        .help("Don't ask for confirmation")
+        .action(ArgAction::SetTrue)
+}
+
+// Ask before doing something destructive, unless --yes was given
+pub fn confirm(matches: &ArgMatches, prompter: &dyn Prompter, prompt: &str) -> Result<bool> {
+    if matches.try_get_one::<bool>("yes").ok().flatten().copied().unwrap_or(false) {
+        return Ok(true);
+    }
+    prompter.confirm(&Question::new(prompt).flag("yes"))
+}
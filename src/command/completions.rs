@@ -0,0 +1,148 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::{Arg, ArgAction};
+use clap_complete::env::Shells;
+use clap_complete::Shell;
+use colored::*;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::commands::{self, Command};
+use crate::completion::COMPLETE_ENV;
+use crate::context::{CommandOutput, ExecutionContext};
+
+// Shells `completions` writes scripts for
+const SHELLS: [&str; 4] = ["bash", "zsh", "fish", "elvish"];
+
+// Command to print a shell completion script
+pub struct CompletionsCommand;
+
+impl CompletionsCommand {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Command for CompletionsCommand {
+    fn name(&self) -> &str {
+        "completions"
+    }
+
+    fn description(&self) -> &str {
+        "Print a shell completion script for bash, zsh, fish or elvish"
+    }
+
+    fn requires_config(&self) -> bool {
+        false
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::new("shell").value_name("SHELL").help("Shell to complete in").value_parser(SHELLS),
+            Arg::new("static")
+                .long("static")
+                .help("Write a standalone script that doesn't complete environment aliases or IDs")
+                .action(ArgAction::SetTrue),
+        ]
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let shell = ctx.value_or_select("shell", "Select shell", &SHELLS)?;
+
+        if ctx.args.get_flag("static") {
+            let shell: Shell = shell.parse().map_err(|e| anyhow::anyhow!("{}", e))?;
+            // Buffered, since generate panics if the write fails, e.g. when piped into head
+            let mut script = Vec::new();
+            clap_complete::generate(shell, &mut commands::default_cli(), commands::BIN_NAME, &mut script);
+            ctx.stdout.write_all(&script)?;
+        } else {
+            // The script calls back into this binary, which completes from the live command tree
+            let completer = std::env::current_exe()?;
+            Shells::builtins()
+                .completer(&shell)
+                .ok_or_else(|| anyhow::anyhow!("Unsupported shell {}", shell))?
+                .write_registration(
+                    COMPLETE_ENV,
+                    commands::BIN_NAME,
+                    commands::BIN_NAME,
+                    &completer.to_string_lossy(),
+                    &mut ctx.stdout,
+                )?;
+        }
+
+        Ok(CommandOutput::Empty)
+    }
+}
+
+// Command to write man pages for every command
+pub struct ManpagesCommand;
+
+impl ManpagesCommand {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Command for ManpagesCommand {
+    fn name(&self) -> &str {
+        "manpages"
+    }
+
+    fn description(&self) -> &str {
+        "Write a man page for each command to a directory"
+    }
+
+    fn requires_config(&self) -> bool {
+        false
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![Arg::new("dir").value_name("DIR").help("Directory to write the pages to, created if missing")]
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let dir = PathBuf::from(ctx.value_or_prompt("dir", "Enter directory for man pages")?);
+        fs::create_dir_all(&dir)?;
+
+        let cli = commands::default_cli();
+        let count = page_count(&cli);
+        clap_mangen::generate_to(cli, &dir)?;
+
+        writeln!(ctx.stderr, "{} {} man pages to {}", "Wrote".green(), count, dir.display())?;
+        Ok(CommandOutput::Empty)
+    }
+}
+
+// One page per visible command, as clap_mangen writes them; the directory may hold others already
+fn page_count(cli: &clap::Command) -> usize {
+    1 + cli.get_subcommands().filter(|sub| !sub.is_hide_set()).map(page_count).sum::<usize>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_api::execute;
+    use crate::test_support;
+
+    #[tokio::test]
+    async fn completions_and_manpages_cover_the_registry() {
+        let script = execute(&CompletionsCommand::new(), &["completions", "bash", "--static"]).await.unwrap();
+        assert!(script.contains("organizations") && script.contains("set-role"));
+        let script = execute(&CompletionsCommand::new(), &["completions", "fish"]).await.unwrap();
+        assert!(script.contains("DYNAMIC_ADMIN_COMPLETE=fish"));
+        assert!(execute(&CompletionsCommand::new(), &["completions"]).await.is_err());
+
+        let dir = test_support::temp_dir("manpages");
+        fs::write(dir.join("other-tool.1"), "").unwrap();
+        execute(&ManpagesCommand::new(), &["manpages", dir.to_str().unwrap()]).await.unwrap();
+        let page = fs::read_to_string(dir.join("dynamic-admin-users-block.1")).unwrap();
+        assert!(page.contains("Block a user"));
+
+        // Only the pages just written are counted, not others already there
+        assert_eq!(page_count(&commands::default_cli()), fs::read_dir(&dir).unwrap().count() - 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
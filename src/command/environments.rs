@@ -0,0 +1,170 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Arg;
+use colored::*;
+use serde_json::json;
+use std::io::Write;
+
+use crate::command::args;
+use crate::commands::Command;
+use crate::completion;
+use crate::config;
+use crate::context::{CommandOutput, ExecutionContext};
+use crate::environment::EnvironmentSource;
+
+// Default table columns for the alias listing
+const ALIAS_COLUMNS: [&str; 3] = ["active", "alias", "id"];
+
+// Command to switch the active environment
+pub struct UseEnvironmentCommand;
+
+impl UseEnvironmentCommand {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Command for UseEnvironmentCommand {
+    fn name(&self) -> &str {
+        "use"
+    }
+
+    fn description(&self) -> &str {
+        "Set the active environment by ID or alias"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::new("environment")
+                .value_name("ID|ALIAS")
+                .help("Environment ID or a previously defined alias")
+                .add(completion::environment_candidates()),
+            Arg::new("alias")
+                .long("alias")
+                .value_name("NAME")
+                .help("Save the environment ID under this alias (e.g. sandbox, live)"),
+        ]
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let target = ctx.value_or_prompt("environment", "Enter environment ID or alias")?;
+        let mut state = ctx.environments.load_state()?;
+
+        let active = match args::get_value(ctx.args, "alias") {
+            Some(alias) => {
+                if !config::is_uuid(&target) {
+                    return Err(anyhow::anyhow!("--alias requires an environment ID, got '{}'", target));
+                }
+                if config::is_uuid(&alias) {
+                    return Err(anyhow::anyhow!("Alias '{}' looks like an environment ID", alias));
+                }
+                state.aliases.insert(alias.clone(), target.clone());
+                alias
+            }
+            None => {
+                // Validates that the alias exists or the value is an ID
+                ctx.environments.resolve(&target)?;
+                target
+            }
+        };
+
+        if ctx.skip_in_dry_run(&format!("switch to environment {}", active))? {
+            return Ok(CommandOutput::Empty);
+        }
+        state.active = Some(active.clone());
+        ctx.environments.save_state(&state)?;
+
+        let id = ctx.environments.resolve(&active)?;
+        if id == active {
+            writeln!(ctx.stderr, "{} {}", "Active environment:".green(), id.yellow())?;
+        } else {
+            writeln!(ctx.stderr, "{} {} ({})", "Active environment:".green(), active.yellow(), id)?;
+        }
+        if let (session, EnvironmentSource::Session) = ctx.environments.current()? {
+            writeln!(
+                ctx.stderr,
+                "{}",
+                format!("This shell keeps using {} from --env; leave it to use the active environment", session).yellow()
+            )?;
+        }
+
+        Ok(CommandOutput::Empty)
+    }
+}
+
+// Command to show the environment commands will run against
+pub struct CurrentEnvironmentCommand;
+
+impl CurrentEnvironmentCommand {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Command for CurrentEnvironmentCommand {
+    fn name(&self) -> &str {
+        "current"
+    }
+
+    fn description(&self) -> &str {
+        "Show the environment commands will use"
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let (id, source) = ctx.resolve_environment()?;
+        let state = ctx.environments.load_state()?;
+
+        let aliases: Vec<&str> = state
+            .aliases
+            .iter()
+            .filter(|(_, alias_id)| **alias_id == id)
+            .map(|(alias, _)| alias.as_str())
+            .collect();
+
+        Ok(CommandOutput::Item(json!({
+            "id": id,
+            "aliases": aliases,
+            "source": source.describe(),
+        })))
+    }
+}
+
+// Command to list saved environment aliases
+pub struct ListEnvironmentsCommand;
+
+impl ListEnvironmentsCommand {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Command for ListEnvironmentsCommand {
+    fn name(&self) -> &str {
+        "list"
+    }
+
+    fn description(&self) -> &str {
+        "List environment aliases"
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let state = ctx.environments.load_state()?;
+        let (current, _) = ctx.environments.current()?;
+
+        if state.aliases.is_empty() {
+            writeln!(ctx.stderr, "{}", "No environment aliases defined. Use `env use <id> --alias <name>`".yellow())?;
+        }
+
+        let aliases = state.aliases.into_iter().map(move |(alias, id)| {
+            Ok(json!({
+                "active": id == current,
+                "alias": alias,
+                "id": id,
+            }))
+        });
+        Ok(CommandOutput::list(futures::stream::iter(aliases), &ALIAS_COLUMNS))
+    }
+}
@@ -1,20 +1,52 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use colored::*;
-use dialoguer::{theme::ColorfulTheme, Input, Select};
+use clap::{Arg, ArgAction, ArgMatches};
 use serde_json::json;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
 
-use crate::api::DynamicApiClient;
+use crate::api::{AdminApi, Download, ExportRequest, EXPORT_FORMATS, EXPORT_TYPES};
+use crate::command::args;
 use crate::commands::Command;
+use crate::config::{self, ExportTemplate};
+use crate::context::{CommandOutput, ExecutionContext};
+use crate::error::Cancelled;
+use crate::models::Export;
+use crate::output;
+use crate::prompt::{Prompter, Question};
+
+// Default table columns for export listings
+const EXPORT_COLUMNS: [&str; 5] = ["id", "status", "format", "createdAt", "completedAt"];
+
+// Default columns for the template listing
+const TEMPLATE_COLUMNS: [&str; 5] = ["name", "type", "format", "created_after", "created_before"];
+
+// Fields offered when choosing what an export includes, by export type
+fn export_fields(export_type: &str) -> &'static [&'static str] {
+    match export_type {
+        "users" => &["id", "email", "phoneNumber", "firstName", "lastName", "createdAt"],
+        "organizations" => &["id", "name", "createdAt"],
+        "wallets" => &["id", "userId", "chain", "publicKey", "name", "provider"],
+        _ => &[],
+    }
+}
+
+// Delay between status checks while waiting for an export, growing from the first to the second
+const POLL_DELAYS: (Duration, Duration) = (Duration::from_secs(1), Duration::from_secs(15));
+// How long --wait waits by default before giving up
+const DEFAULT_WAIT_SECS: u64 = 1800;
 
 // Command to list exports
 pub struct ListExportsCommand {
-    api_client: Arc<DynamicApiClient>,
+    api_client: Arc<dyn AdminApi>,
 }
 
 impl ListExportsCommand {
-    pub fn new(api_client: Arc<DynamicApiClient>) -> Self {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
         Self { api_client }
     }
 }
@@ -29,29 +61,29 @@ impl Command for ListExportsCommand {
         "List all exports"
     }
 
-    async fn execute(&self) -> Result<()> {
-        let environment_id: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("Enter environment ID")
-            .interact()?;
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(args::pagination_args());
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+
+        writeln!(ctx.stderr, "{}", "Fetching exports...".blue())?;
+        let exports = self.api_client.list_exports(&environment_id, args::page_options(ctx.args));
 
-        println!("{}", "Fetching exports...".blue());
-        let result = self.api_client.list_exports(&environment_id).await?;
-        
-        // Pretty print the JSON result
-        let formatted = serde_json::to_string_pretty(&result)?;
-        println!("{}", formatted);
-        
-        Ok(())
+        Ok(CommandOutput::list(exports, &EXPORT_COLUMNS))
     }
 }
 
 // Command to get a specific export
 pub struct GetExportCommand {
-    api_client: Arc<DynamicApiClient>,
+    api_client: Arc<dyn AdminApi>,
 }
 
 impl GetExportCommand {
-    pub fn new(api_client: Arc<DynamicApiClient>) -> Self {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
         Self { api_client }
     }
 }
@@ -66,34 +98,33 @@ impl Command for GetExportCommand {
         "Get a specific export"
     }
 
-    async fn execute(&self) -> Result<()> {
-        let environment_id: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("Enter environment ID")
-            .interact()?;
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(args::id_args("export-id", "EXPORT_ID", "Export ID"));
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
 
-        let export_id: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("Enter export ID")
-            .interact()?;
+        let export_id = ctx.value_or_prompt("export-id", "Enter export ID")?;
 
-        println!("{}", "Fetching export...".blue());
+        writeln!(ctx.stderr, "{}", "Fetching export...".blue())?;
         let result = self.api_client.get_export(&environment_id, &export_id).await?;
-        
-        // Pretty print the JSON result
-        let formatted = serde_json::to_string_pretty(&result)?;
-        println!("{}", formatted);
-        
-        Ok(())
+
+        CommandOutput::item(&result)
     }
 }
 
 // Command to create a new export
 pub struct CreateExportCommand {
-    api_client: Arc<DynamicApiClient>,
+    api_client: Arc<dyn AdminApi>,
+    config_path: PathBuf,
 }
 
 impl CreateExportCommand {
-    pub fn new(api_client: Arc<DynamicApiClient>) -> Self {
-        Self { api_client }
+    pub fn new(api_client: Arc<dyn AdminApi>, config_path: PathBuf) -> Self {
+        Self { api_client, config_path }
     }
 }
 
@@ -107,32 +138,575 @@ impl Command for CreateExportCommand {
         "Create a new export"
     }
 
-    async fn execute(&self) -> Result<()> {
-        let environment_id: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("Enter environment ID")
-            .interact()?;
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            args::environment_arg(),
+            Arg::new("type")
+                .long("type")
+                .value_name("TYPE")
+                .help("Export type")
+                .value_parser(EXPORT_TYPES),
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("File format of the export")
+                .value_parser(EXPORT_FORMATS),
+            Arg::new("created-after")
+                .long("created-after")
+                .value_name("DATE")
+                .help("Only include objects created on or after DATE (YYYY-MM-DD or RFC 3339)"),
+            Arg::new("created-before")
+                .long("created-before")
+                .value_name("DATE")
+                .help("Only include objects created before DATE (YYYY-MM-DD or RFC 3339)"),
+            Arg::new("filter")
+                .long("filter")
+                .value_name("FIELD=VALUE")
+                .help("Only include objects whose FIELD equals VALUE; repeatable")
+                .value_parser(parse_filter)
+                .action(ArgAction::Append),
+            Arg::new("fields")
+                .long("fields")
+                .value_name("FIELDS")
+                .help("Comma-separated fields to include in the exported file")
+                .value_delimiter(','),
+            Arg::new("template")
+                .long("template")
+                .value_name("NAME")
+                .help("Start from a saved export template; other flags override it"),
+            Arg::new("save-template")
+                .long("save-template")
+                .value_name("NAME")
+                .help("Save these export parameters as a template"),
+            Arg::new("idempotency-key")
+                .long("idempotency-key")
+                .value_name("KEY")
+                .help("Idempotency key, which also lets the request be retried safely"),
+            wait_arg().help("Wait for the export to finish and print its final state"),
+            Arg::new("download")
+                .long("download")
+                .value_name("PATH")
+                .help("Wait for the export to finish, then download the file to PATH (implies --wait)"),
+            timeout_arg(),
+        ]
+    }
 
-        // Select export type
-        let export_types = vec!["users", "organizations", "wallets"];
-        let selected_type = Select::with_theme(&ColorfulTheme::default())
-            .with_prompt("Select export type")
-            .default(0)
-            .items(&export_types)
-            .interact()?;
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+
+        let path = &self.config_path;
+        let mut file = config::read_config_file(path)?;
+
+        let mut template = match args::get_value(ctx.args, "template") {
+            Some(name) => file
+                .export_templates
+                .get(&name)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Export template '{}' not found", name))?,
+            None => ExportTemplate::default(),
+        };
+        apply_flags(&mut template, ctx.args);
+
+        if template.export_type.is_empty() {
+            prompt_template(ctx.prompter.as_ref(), &mut template)?;
+        }
 
         // Create export request body
-        let export_params = json!({
-            "type": export_types[selected_type],
-            "format": "csv"
-        });
-
-        println!("{}", "Creating export...".blue());
-        let result = self.api_client.create_export(&environment_id, &export_params).await?;
-        
-        // Pretty print the JSON result
-        let formatted = serde_json::to_string_pretty(&result)?;
-        println!("{}", formatted);
-        
-        Ok(())
-    }
-}
\ No newline at end of file
+        let export_params = export_request(&template)?;
+        if ctx.skip_in_dry_run(&format!("create a {} export", export_params.export_type))? {
+            return CommandOutput::item(&export_params);
+        }
+
+        writeln!(ctx.stderr, "{}", "Creating export...".blue())?;
+        let mut result = self.api_client
+            .create_export(&environment_id, &export_params, args::get_value(ctx.args, "idempotency-key").as_deref())
+            .await?;
+
+        // Only parameters the API accepted are worth saving
+        if let Some(name) = args::get_value(ctx.args, "save-template") {
+            file.export_templates.insert(name.clone(), template);
+            config::write_config_file(path, &file)?;
+            writeln!(ctx.stderr, "{} {}", "Saved export template".green(), name.yellow())?;
+        }
+
+        let download = args::get_value(ctx.args, "download");
+        if download.is_some() || ctx.args.get_flag("wait") {
+            let timeout = wait_timeout(ctx.args);
+            result = wait_for_export(self.api_client.as_ref(), &mut *ctx.stderr, &ctx.cancel, &environment_id, result, timeout).await?;
+        }
+        if let Some(path) = download {
+            download_export(self.api_client.as_ref(), &mut *ctx.stderr, &result, Some(path)).await?;
+        }
+
+        CommandOutput::item(&result)
+    }
+}
+
+// Command to list saved export templates
+pub struct ListExportTemplatesCommand {
+    config_path: PathBuf,
+}
+
+impl ListExportTemplatesCommand {
+    pub fn new(config_path: PathBuf) -> Self {
+        Self { config_path }
+    }
+}
+
+#[async_trait]
+impl Command for ListExportTemplatesCommand {
+    fn name(&self) -> &str {
+        "templates"
+    }
+
+    fn description(&self) -> &str {
+        "List saved export templates"
+    }
+
+    async fn execute<'a>(&'a self, _ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let file = config::read_config_file(&self.config_path)?;
+
+        let mut templates = Vec::new();
+        for (name, template) in file.export_templates {
+            let mut item = serde_json::Map::new();
+            item.insert("name".to_string(), name.into());
+            if let serde_json::Value::Object(fields) = serde_json::to_value(template)? {
+                item.extend(fields);
+            }
+            templates.push(Ok(serde_json::Value::Object(item)));
+        }
+        Ok(CommandOutput::list(futures::stream::iter(templates), &TEMPLATE_COLUMNS))
+    }
+}
+
+// Command to download the file of an existing export
+pub struct DownloadExportCommand {
+    api_client: Arc<dyn AdminApi>,
+}
+
+impl DownloadExportCommand {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
+        Self { api_client }
+    }
+}
+
+#[async_trait]
+impl Command for DownloadExportCommand {
+    fn name(&self) -> &str {
+        "download"
+    }
+
+    fn description(&self) -> &str {
+        "Download the file of a finished export"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(args::id_args("export-id", "EXPORT_ID", "Export ID"));
+        command_args.extend([
+            Arg::new("file")
+                .long("file")
+                .value_name("PATH")
+                .help("Where to save the file (defaults to the export's file name)"),
+            wait_arg().help("Wait for the export to finish if it's still running"),
+            timeout_arg(),
+        ]);
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+
+        let export_id = ctx.value_or_prompt("export-id", "Enter export ID")?;
+
+        writeln!(ctx.stderr, "{}", "Fetching export...".blue())?;
+        let mut export = self.api_client.get_export(&environment_id, &export_id).await?;
+        if ctx.args.get_flag("wait") {
+            let timeout = wait_timeout(ctx.args);
+            export = wait_for_export(self.api_client.as_ref(), &mut *ctx.stderr, &ctx.cancel, &environment_id, export, timeout).await?;
+        }
+
+        let file = args::get_value(ctx.args, "file");
+        let (path, download) = download_export(self.api_client.as_ref(), &mut *ctx.stderr, &export, file).await?;
+
+        CommandOutput::item(&json!({
+            "id": export.id,
+            "path": path,
+            "bytes": download.bytes,
+            "sha256": download.sha256,
+        }))
+    }
+}
+
+// Parse a --filter value of the form FIELD=VALUE
+fn parse_filter(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((field, value)) if !field.is_empty() => Ok((field.to_string(), value.to_string())),
+        _ => Err(format!("expected FIELD=VALUE, got '{}'", value)),
+    }
+}
+
+// Layer the command-line flags over a template
+fn apply_flags(template: &mut ExportTemplate, matches: &ArgMatches) {
+    if let Some(export_type) = args::get_value(matches, "type") {
+        template.export_type = export_type;
+    }
+    for (id, field) in [
+        ("format", &mut template.format),
+        ("created-after", &mut template.created_after),
+        ("created-before", &mut template.created_before),
+    ] {
+        if let Some(value) = args::get_value(matches, id) {
+            *field = Some(value);
+        }
+    }
+
+    if let Ok(Some(filters)) = matches.try_get_many::<(String, String)>("filter") {
+        template.filters.extend(filters.cloned());
+    }
+    if let Ok(Some(fields)) = matches.try_get_many::<String>("fields") {
+        template.columns = fields.cloned().collect();
+    }
+}
+
+// Ask for the export parameters interactively
+fn prompt_template(prompter: &dyn Prompter, template: &mut ExportTemplate) -> Result<()> {
+    let selection = prompter.select(&Question::new("Select export type").flag("type"), &EXPORT_TYPES)?;
+    template.export_type = EXPORT_TYPES[selection].to_string();
+
+    if template.format.is_none() {
+        let selection = prompter.select(&Question::new("Select format").flag("format"), &EXPORT_FORMATS)?;
+        template.format = Some(EXPORT_FORMATS[selection].to_string());
+    }
+
+    let optional = |prompt: &str, flag: &str| -> Result<Option<String>> {
+        let value = prompter.text(&Question::new(prompt).flag(flag).allow_empty())?;
+        Ok(Some(value.trim().to_string()).filter(|v| !v.is_empty()))
+    };
+
+    if template.created_after.is_none() {
+        template.created_after = optional("Created on or after (YYYY-MM-DD, empty for no limit)", "created-after")?;
+    }
+    if template.created_before.is_none() {
+        template.created_before = optional("Created before (YYYY-MM-DD, empty for no limit)", "created-before")?;
+    }
+    if template.filters.is_empty() {
+        for filter in optional("Filters as FIELD=VALUE, comma-separated (empty for none)", "filter")?.iter().flat_map(|f| f.split(',')) {
+            let (field, value) = parse_filter(filter.trim()).map_err(|e| anyhow::anyhow!(e))?;
+            template.filters.insert(field, value);
+        }
+    }
+    if template.columns.is_empty() {
+        let fields = export_fields(&template.export_type);
+        let selection = prompter.multi_select(&Question::new("Fields to include (none for all)").flag("fields"), fields)?;
+        template.columns = selection.into_iter().map(|i| fields[i].to_string()).collect();
+    }
+
+    Ok(())
+}
+
+// Validate a template into the request body
+fn export_request(template: &ExportTemplate) -> Result<ExportRequest> {
+    let mut builder = ExportRequest::builder(&template.export_type).columns(template.columns.iter().cloned());
+    if let Some(format) = &template.format {
+        builder = builder.format(format);
+    }
+    if let Some(date) = &template.created_after {
+        builder = builder.created_after(date);
+    }
+    if let Some(date) = &template.created_before {
+        builder = builder.created_before(date);
+    }
+    for (field, value) in &template.filters {
+        builder = builder.filter(field, value);
+    }
+    builder.build()
+}
+
+fn wait_arg() -> Arg {
+    Arg::new("wait").long("wait").action(ArgAction::SetTrue)
+}
+
+fn timeout_arg() -> Arg {
+    Arg::new("timeout")
+        .long("timeout")
+        .value_name("SECONDS")
+        .help("Give up waiting after this many seconds")
+        .value_parser(clap::value_parser!(u64))
+}
+
+fn wait_timeout(matches: &ArgMatches) -> Duration {
+    let seconds = matches.try_get_one::<u64>("timeout").ok().flatten().copied();
+    Duration::from_secs(seconds.unwrap_or(DEFAULT_WAIT_SECS))
+}
+
+// Poll an export with backoff until it finishes, or the user stops waiting
+async fn wait_for_export(
+    api_client: &dyn AdminApi,
+    stderr: &mut (dyn Write + Send),
+    cancel: &CancellationToken,
+    environment_id: &str,
+    mut export: Export,
+    timeout: Duration,
+) -> Result<Export> {
+    let started = Instant::now();
+    let (mut delay, max_delay) = POLL_DELAYS;
+    let spinner = output::spinner("Waiting for export...");
+
+    loop {
+        let status = export.status.as_deref().unwrap_or("pending");
+        spinner.set_message(format!("Export {} is {} ({}s)", export.id, status, started.elapsed().as_secs()));
+
+        if export.is_ready() {
+            spinner.finish_and_clear();
+            writeln!(stderr, "{} {}", "Export finished:".green(), export.id)?;
+            return Ok(export);
+        }
+
+        if export.is_failed() {
+            spinner.finish_and_clear();
+            return Err(anyhow::anyhow!(
+                "Export {} did not complete ({}): {}",
+                export.id,
+                status,
+                export.error.as_deref().unwrap_or("no details given")
+            ));
+        }
+
+        if started.elapsed() + delay > timeout {
+            spinner.finish_and_clear();
+            return Err(anyhow::anyhow!(
+                "Export {} still {} after {}s. Run `exports download {} --wait` to keep waiting",
+                export.id,
+                status,
+                started.elapsed().as_secs(),
+                export.id
+            ));
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = cancel.cancelled() => {
+                spinner.finish_and_clear();
+                return Err(Cancelled(format!(
+                    "Stopped waiting for export {}, which is still {}. Run `exports download {} --wait` to keep waiting",
+                    export.id, status, export.id
+                ))
+                .into());
+            }
+        }
+        delay = (delay * 3 / 2).min(max_delay);
+        export = api_client.get_export(environment_id, &export.id).await?;
+    }
+}
+
+// Download a finished export, checking the file against the size and checksum the API reports
+async fn download_export(
+    api_client: &dyn AdminApi,
+    stderr: &mut (dyn Write + Send),
+    export: &Export,
+    path: Option<String>,
+) -> Result<(PathBuf, Download)> {
+    if !export.is_ready() {
+        return Err(anyhow::anyhow!(
+            "Export {} is {}; pass --wait to wait for it to finish",
+            export.id,
+            export.status.as_deref().unwrap_or("not finished")
+        ));
+    }
+
+    let url = export
+        .download_url
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("Export {} has no download URL", export.id))?;
+
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => default_file_name(export)?,
+    };
+    // Download next to the destination and only move it into place once it checks out
+    let partial = PathBuf::from(format!("{}.part", path.display()));
+
+    let bar = output::byte_progress(export.file_size);
+    let result = api_client
+        .download(url, &partial, &mut |bytes, total| {
+            if let Some(total) = total {
+                bar.set_length(total);
+            }
+            bar.set_position(bytes);
+        })
+        .await;
+    bar.finish_and_clear();
+
+    let checked = result.and_then(|download| verify_download(export, &download).map(|_| download));
+    let download = match checked {
+        Ok(download) => download,
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e);
+        }
+    };
+    std::fs::rename(&partial, &path)?;
+
+    writeln!(
+        stderr,
+        "{} {:?} ({} bytes, sha256 {})",
+        "Saved".green(),
+        path,
+        download.bytes,
+        download.sha256
+    )?;
+    Ok((path, download))
+}
+
+fn verify_download(export: &Export, download: &Download) -> Result<()> {
+    if let Some(expected) = export.file_size.filter(|size| *size != download.bytes) {
+        return Err(anyhow::anyhow!(
+            "Downloaded {} bytes but the export reports {}",
+            download.bytes,
+            expected
+        ));
+    }
+
+    if let Some(checksum) = &export.checksum {
+        let expected = checksum.trim_start_matches("sha256:").to_lowercase();
+        if expected != download.sha256 {
+            return Err(anyhow::anyhow!(
+                "Checksum mismatch: expected sha256 {} but got {}",
+                expected,
+                download.sha256
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Where to save an export without --file. The name comes from the server, so only its last
+// component is used, keeping the file in the current directory.
+fn default_file_name(export: &Export) -> Result<PathBuf> {
+    // The id and format come from the API too, so the fallback gets the same check
+    let name = match export.file_name.as_deref() {
+        Some(name) => name.to_string(),
+        None => format!("{}.{}", export.id, export.format.as_deref().unwrap_or("csv")),
+    };
+    // None for empty names and those ending in . or ..
+    match Path::new(&name).file_name() {
+        Some(file_name) => Ok(PathBuf::from(file_name)),
+        None => Err(anyhow::anyhow!(
+            "Export {} has no usable file name ({:?}); pass --file to choose where to save it",
+            export.id,
+            name
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_api::{self, column, execute, execute_with, json, FakeAdminApi, EXPORT_CONTENTS};
+    use crate::test_support;
+    use std::fs;
+
+    #[tokio::test]
+    async fn create_downloads_and_saves_the_template() {
+        let api = FakeAdminApi::sample();
+        let dir = test_support::temp_dir("exports");
+        let file = dir.join("users.csv");
+
+        let create = CreateExportCommand::new(api.clone(), dir.join("config.toml"));
+        assert!(execute(&create, &["create"]).await.is_err());
+        let args = ["create", "--type", "users", "--filter", "plan=pro", "--save-template", "pro", "--download", file.to_str().unwrap()];
+        execute(&create, &args).await.unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), EXPORT_CONTENTS);
+
+        let templates = config::read_config_file(&dir.join("config.toml")).unwrap().export_templates;
+        assert_eq!(templates["pro"].export_type, "users");
+        assert_eq!(templates["pro"].filters["plan"], "pro");
+        assert_eq!(api.calls().iter().filter(|call| call.starts_with("download")).count(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn create_asks_for_missing_parameters() {
+        let api = FakeAdminApi::sample();
+        let dir = test_support::temp_dir("exports-asked");
+        let create = CreateExportCommand::new(api.clone(), dir.join("config.toml"));
+
+        // Type, format, both dates skipped, a filter and the columns
+        let answers = fake_api::answers(&["wallets", "json", "", "", "chain=evm", "id,chain"]);
+        execute_with(&create, &["create"], answers).await.unwrap();
+        let asked = api.state().exports.last().unwrap().clone();
+        assert_eq!((asked.model.as_deref(), asked.format.as_deref()), (Some("wallets"), Some("json")));
+        assert_eq!(asked.filter, Some(json!({"chain": "evm"})));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn listings_and_lookups_render_exports() {
+        let api = FakeAdminApi::sample();
+        let dir = test_support::temp_dir("exports-listed");
+        let create = CreateExportCommand::new(api.clone(), dir.join("config.toml"));
+        let first = json(&create, &["create", "--type", "users"]).await;
+        let second = json(&create, &["create", "--type", "wallets", "--format", "json"]).await;
+        let ids = [first["id"].as_str().unwrap(), second["id"].as_str().unwrap()];
+
+        let list = ListExportsCommand::new(api.clone());
+        assert_eq!(column(&json(&list, &["list"]).await, "id"), ids);
+        assert_eq!(column(&json(&list, &["list", "--limit", "1"]).await, "id"), ids[..1]);
+        let export = json(&GetExportCommand::new(api.clone()), &["get", ids[1]]).await;
+        assert_eq!((&export["model"], &export["format"]), (&json!("wallets"), &json!("json")));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn mismatched_checksums_leave_nothing_behind() {
+        let api = FakeAdminApi::sample();
+        let dir = test_support::temp_dir("exports-checksum");
+        let create = CreateExportCommand::new(api.clone(), dir.join("config.toml"));
+        let id = json(&create, &["create", "--type", "users"]).await["id"].as_str().unwrap().to_string();
+        api.state().exports[0].checksum = Some("sha256:0000".to_string());
+
+        let file = dir.join("users.csv");
+        let download = DownloadExportCommand::new(api.clone());
+        let error = execute(&download, &["download", &id, "--file", file.to_str().unwrap()]).await.unwrap_err();
+        assert!(error.to_string().starts_with("Checksum mismatch"), "{}", error);
+        assert!(!file.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejected_exports_save_no_template() {
+        let api = FakeAdminApi::sample();
+        api.state().reject_exports = Some("Unknown filter field 'plan'".to_string());
+        let dir = test_support::temp_dir("exports-rejected");
+        let config_path = dir.join("config.toml");
+
+        let create = CreateExportCommand::new(api.clone(), config_path.clone());
+        let error = execute(&create, &["create", "--type", "users", "--filter", "plan=pro", "--save-template", "pro"]).await.unwrap_err();
+        assert_eq!(error.to_string(), "Request rejected (400): Unknown filter field 'plan'");
+        assert!(config::read_config_file(&config_path).unwrap().export_templates.is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn saved_file_names_stay_in_the_current_directory() {
+        let export = |id: &str, format: &str, file_name: Option<&str>| -> Export {
+            serde_json::from_value(json!({"id": id, "format": format, "fileName": file_name})).unwrap()
+        };
+        let name = |file_name| default_file_name(&export("export-1", "csv", file_name)).map(|path| path.display().to_string());
+
+        assert_eq!(name(Some("users.csv")).unwrap(), "users.csv");
+        assert_eq!(name(Some("../../.ssh/authorized_keys")).unwrap(), "authorized_keys");
+        assert_eq!(name(Some("/etc/passwd")).unwrap(), "passwd");
+        assert_eq!(name(None).unwrap(), "export-1.csv");
+        for unusable in ["", ".", "..", "exports/.."] {
+            assert!(name(Some(unusable)).is_err(), "{:?}", unusable);
+        }
+
+        let fallback = |id, format| default_file_name(&export(id, format, None)).map(|path| path.display().to_string());
+        assert_eq!(fallback("../../.bashrc", "csv").unwrap(), ".bashrc.csv");
+        assert_eq!(fallback("export-1", "csv/../../../etc/cron.d/x").unwrap(), "x");
+        assert!(fallback("export-1", "csv/..").is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use colored::*;
+use clap::Arg;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+use crate::command::args;
+use crate::commands::Command;
+use crate::context::{CommandOutput, ExecutionContext};
+use crate::mock_server::{self, Fixtures, MockOptions};
+
+// Port the mock server listens on unless --port says otherwise
+const DEFAULT_MOCK_PORT: u16 = 4010;
+
+// Command to run a local in-memory Admin API for tests and demos
+pub struct MockServerCommand;
+
+impl MockServerCommand {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Command for MockServerCommand {
+    fn name(&self) -> &str {
+        "mock-server"
+    }
+
+    fn description(&self) -> &str {
+        "Run a local in-memory Admin API to point base_url at for tests and demos"
+    }
+
+    fn requires_config(&self) -> bool {
+        false
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::new("port")
+                .long("port")
+                .value_name("PORT")
+                .help("Port to listen on (0 picks a free one)")
+                .value_parser(clap::value_parser!(u16)),
+            Arg::new("seed")
+                .long("seed")
+                .value_name("FILE")
+                .help("JSON file with users, organizations, members, wallets and exports to start with"),
+            Arg::new("users")
+                .long("users")
+                .value_name("N")
+                .help("Generate N extra users, e.g. to try pagination")
+                .value_parser(clap::value_parser!(usize)),
+            Arg::new("latency")
+                .long("latency")
+                .value_name("MS")
+                .help("Delay every response by this many milliseconds")
+                .value_parser(clap::value_parser!(u64)),
+            Arg::new("error-rate")
+                .long("error-rate")
+                .value_name("RATE")
+                .help("Fraction of requests (0 to 1) that fail with --error-status")
+                .value_parser(parse_rate),
+            Arg::new("error-status")
+                .long("error-status")
+                .value_name("STATUS")
+                .help("Status returned for injected failures (default 500)")
+                .value_parser(["429", "500", "502", "503"]),
+            Arg::new("retry-after")
+                .long("retry-after")
+                .value_name("SECS")
+                .help("Retry-After sent with injected 429s (default 1)")
+                .value_parser(clap::value_parser!(u64)),
+            Arg::new("accept-token")
+                .long("accept-token")
+                .value_name("TOKEN")
+                .help("Only accept this API token (any dyn_ token by default)"),
+        ]
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let matches = ctx.args;
+        let number = |id: &str| matches.try_get_one::<u64>(id).ok().flatten().copied();
+
+        let fixtures = match args::get_value(matches, "seed") {
+            Some(path) => Fixtures::load(Path::new(&path))?,
+            None => Fixtures::sample(),
+        };
+        let extra_users = matches.try_get_one::<usize>("users").ok().flatten().copied().unwrap_or(0);
+
+        let defaults = MockOptions::default();
+        let options = MockOptions {
+            latency: number("latency").map_or(defaults.latency, Duration::from_millis),
+            error_rate: matches.try_get_one::<f64>("error-rate").ok().flatten().copied().unwrap_or(defaults.error_rate),
+            error_status: args::get_value(matches, "error-status")
+                .and_then(|status| status.parse().ok())
+                .unwrap_or(defaults.error_status),
+            retry_after_secs: number("retry-after").unwrap_or(defaults.retry_after_secs),
+            token: args::get_value(matches, "accept-token"),
+        };
+
+        let port = matches.try_get_one::<u16>("port").ok().flatten().copied().unwrap_or(DEFAULT_MOCK_PORT);
+        let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+        let url = format!("http://{}", listener.local_addr()?);
+
+        writeln!(ctx.stderr, "{} {}", "Mock Dynamic API listening on".green(), url.yellow())?;
+        writeln!(
+            ctx.stderr,
+            "Point the CLI at it with --base-url {} --token dyn_mock_token --env 00000000-0000-4000-8000-000000000000",
+            url
+        )?;
+        writeln!(ctx.stderr, "Press Ctrl-C to stop")?;
+        ctx.stderr.flush()?;
+
+        tokio::select! {
+            result = mock_server::serve(listener, fixtures.with_generated_users(extra_users), options) => result?,
+            _ = ctx.cancel.cancelled() => {}
+        }
+        Ok(CommandOutput::Empty)
+    }
+}
+
+fn parse_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if (0.0..=1.0).contains(&rate) => Ok(rate),
+        _ => Err("expected a number between 0 and 1".to_string()),
+    }
+}
@@ -1,3 +1,10 @@
+pub mod args;
 pub mod organizations;
 pub mod exports;
-pub mod users; 
\ No newline at end of file
+pub mod users;
+pub mod environments;
+pub mod profiles;
+pub mod wallets;
+pub mod mock_server;
+pub mod api;
+pub mod completions;
@@ -1,19 +1,27 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use colored::*;
-use dialoguer::{theme::ColorfulTheme, Input};
+use clap::{Arg, ArgMatches};
+use serde_json::json;
 use std::sync::Arc;
 
-use crate::api::DynamicApiClient;
+use crate::api::{AdminApi, OrganizationFields, ORGANIZATION_ROLES};
+use crate::command::args;
 use crate::commands::Command;
+use crate::context::{CommandOutput, ExecutionContext};
+
+// Default table columns for organization listings
+const ORGANIZATION_COLUMNS: [&str; 3] = ["id", "name", "createdAt"];
+// Default table columns for member listings
+const MEMBER_COLUMNS: [&str; 4] = ["userId", "email", "role", "createdAt"];
 
 // Command to list organizations
 pub struct ListOrganizationsCommand {
-    api_client: Arc<DynamicApiClient>,
+    api_client: Arc<dyn AdminApi>,
 }
 
 impl ListOrganizationsCommand {
-    pub fn new(api_client: Arc<DynamicApiClient>) -> Self {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
         Self { api_client }
     }
 }
@@ -28,26 +36,29 @@ impl Command for ListOrganizationsCommand {
         "List all organizations"
     }
 
-    async fn execute(&self) -> Result<()> {
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(args::pagination_args());
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
 
-        println!("{}", "Fetching organizations...".blue());
-        let result = self.api_client.list_organizations().await?;
-        
-        // Pretty print the JSON result
-        let formatted = serde_json::to_string_pretty(&result)?;
-        println!("{}", formatted);
-        
-        Ok(())
+        writeln!(ctx.stderr, "{}", "Fetching organizations...".blue())?;
+        let organizations = self.api_client.list_organizations(&environment_id, args::page_options(ctx.args));
+
+        Ok(CommandOutput::list(organizations, &ORGANIZATION_COLUMNS))
     }
 }
 
 // Command to get a specific organization
 pub struct GetOrganizationCommand {
-    api_client: Arc<DynamicApiClient>,
+    api_client: Arc<dyn AdminApi>,
 }
 
 impl GetOrganizationCommand {
-    pub fn new(api_client: Arc<DynamicApiClient>) -> Self {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
         Self { api_client }
     }
 }
@@ -62,22 +73,447 @@ impl Command for GetOrganizationCommand {
         "Get a specific organization"
     }
 
-    async fn execute(&self) -> Result<()> {
-        let environment_id: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("Enter environment ID")
-            .interact()?;
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(args::id_args("organization-id", "ORGANIZATION_ID", "Organization ID"));
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
 
-        let org_id: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("Enter organization ID")
-            .interact()?;
+        let org_id = ctx.value_or_prompt("organization-id", "Enter organization ID")?;
 
-        println!("{}", "Fetching organization...".blue());
+        writeln!(ctx.stderr, "{}", "Fetching organization...".blue())?;
         let result = self.api_client.get_organization(&environment_id, &org_id).await?;
-        
-        // Pretty print the JSON result
-        let formatted = serde_json::to_string_pretty(&result)?;
-        println!("{}", formatted);
-        
-        Ok(())
-    }
-}
\ No newline at end of file
+
+        CommandOutput::item(&result)
+    }
+}
+
+// Command to create an organization
+pub struct CreateOrganizationCommand {
+    api_client: Arc<dyn AdminApi>,
+}
+
+impl CreateOrganizationCommand {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
+        Self { api_client }
+    }
+}
+
+#[async_trait]
+impl Command for CreateOrganizationCommand {
+    fn name(&self) -> &str {
+        "create"
+    }
+
+    fn description(&self) -> &str {
+        "Create an organization"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(organization_field_args());
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+
+        let mut fields = organization_fields(ctx.args)?;
+        fields.name = Some(ctx.value_or_prompt("name", "Enter organization name")?);
+        if ctx.skip_in_dry_run("create an organization")? {
+            return CommandOutput::item(&fields);
+        }
+
+        writeln!(ctx.stderr, "{}", "Creating organization...".blue())?;
+        let result = self.api_client.create_organization(&environment_id, &fields).await?;
+
+        CommandOutput::item(&result)
+    }
+}
+
+// Command to update an organization's name, description or website
+pub struct UpdateOrganizationCommand {
+    api_client: Arc<dyn AdminApi>,
+}
+
+impl UpdateOrganizationCommand {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
+        Self { api_client }
+    }
+}
+
+#[async_trait]
+impl Command for UpdateOrganizationCommand {
+    fn name(&self) -> &str {
+        "update"
+    }
+
+    fn description(&self) -> &str {
+        "Update an organization's name, description or website"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(args::id_args("organization-id", "ORGANIZATION_ID", "Organization ID"));
+        command_args.extend(organization_field_args());
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+
+        let org_id = ctx.value_or_prompt("organization-id", "Enter organization ID")?;
+        let fields = organization_fields(ctx.args)?;
+        if fields.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Nothing to update; pass at least one of --name, --description or --website-url"
+            ));
+        }
+        if ctx.skip_in_dry_run(&format!("update organization {}", org_id))? {
+            return CommandOutput::item(&fields);
+        }
+
+        writeln!(ctx.stderr, "{}", "Updating organization...".blue())?;
+        let result = self.api_client.update_organization(&environment_id, &org_id, &fields).await?;
+
+        CommandOutput::item(&result)
+    }
+}
+
+// Command to delete an organization
+pub struct DeleteOrganizationCommand {
+    api_client: Arc<dyn AdminApi>,
+}
+
+impl DeleteOrganizationCommand {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
+        Self { api_client }
+    }
+}
+
+#[async_trait]
+impl Command for DeleteOrganizationCommand {
+    fn name(&self) -> &str {
+        "delete"
+    }
+
+    fn description(&self) -> &str {
+        "Delete an organization"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(args::id_args("organization-id", "ORGANIZATION_ID", "Organization ID"));
+        command_args.push(args::yes_arg());
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+
+        let org_id = ctx.value_or_prompt("organization-id", "Enter organization ID")?;
+        if ctx.skip_in_dry_run(&format!("delete organization {}", org_id))?
+            || !ctx.confirm(&format!("Delete organization {}? This can't be undone", org_id))?
+        {
+            return Ok(CommandOutput::Empty);
+        }
+
+        writeln!(ctx.stderr, "{}", "Deleting organization...".blue())?;
+        self.api_client.delete_organization(&environment_id, &org_id).await?;
+
+        writeln!(ctx.stderr, "{} {}", "Deleted organization".green(), org_id.yellow())?;
+        Ok(CommandOutput::Empty)
+    }
+}
+
+// Command to list an organization's members and their roles
+pub struct ListMembersCommand {
+    api_client: Arc<dyn AdminApi>,
+}
+
+impl ListMembersCommand {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
+        Self { api_client }
+    }
+}
+
+#[async_trait]
+impl Command for ListMembersCommand {
+    fn name(&self) -> &str {
+        "list"
+    }
+
+    fn description(&self) -> &str {
+        "List an organization's members"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(args::id_args("organization-id", "ORGANIZATION_ID", "Organization ID"));
+        command_args.extend(args::pagination_args());
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+
+        let org_id = ctx.value_or_prompt("organization-id", "Enter organization ID")?;
+
+        writeln!(ctx.stderr, "{}", "Fetching members...".blue())?;
+        let members = self
+            .api_client
+            .list_organization_members(&environment_id, &org_id, args::page_options(ctx.args));
+
+        Ok(CommandOutput::list(members, &MEMBER_COLUMNS))
+    }
+}
+
+// Command to add a user to an organization
+pub struct AddMemberCommand {
+    api_client: Arc<dyn AdminApi>,
+}
+
+impl AddMemberCommand {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
+        Self { api_client }
+    }
+}
+
+#[async_trait]
+impl Command for AddMemberCommand {
+    fn name(&self) -> &str {
+        "add"
+    }
+
+    fn description(&self) -> &str {
+        "Add a user to an organization"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(member_id_args());
+        command_args.push(role_arg().help("Role in the organization (defaults to member)"));
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+
+        let org_id = ctx.value_or_prompt("organization-id", "Enter organization ID")?;
+        let user_id = ctx.value_or_prompt("user-id", "Enter user ID")?;
+        let role = args::get_value(ctx.args, "role").unwrap_or_else(|| "member".to_string());
+        if ctx.skip_in_dry_run(&format!("add user {} to organization {}", user_id, org_id))? {
+            return Ok(CommandOutput::Item(json!({ "userId": user_id, "role": role })));
+        }
+
+        writeln!(ctx.stderr, "{}", "Adding member...".blue())?;
+        let result = self
+            .api_client
+            .add_organization_member(&environment_id, &org_id, &user_id, &role)
+            .await?;
+
+        CommandOutput::item(&result)
+    }
+}
+
+// Command to change a member's role
+pub struct SetMemberRoleCommand {
+    api_client: Arc<dyn AdminApi>,
+}
+
+impl SetMemberRoleCommand {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
+        Self { api_client }
+    }
+}
+
+#[async_trait]
+impl Command for SetMemberRoleCommand {
+    fn name(&self) -> &str {
+        "set-role"
+    }
+
+    fn description(&self) -> &str {
+        "Change a member's role in an organization"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(member_id_args());
+        command_args.push(role_arg().help("New role"));
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+
+        let org_id = ctx.value_or_prompt("organization-id", "Enter organization ID")?;
+        let user_id = ctx.value_or_prompt("user-id", "Enter user ID")?;
+        let role = ctx.value_or_select("role", "Select role", &ORGANIZATION_ROLES)?;
+        if ctx.skip_in_dry_run(&format!("make user {} {} of organization {}", user_id, role, org_id))? {
+            return Ok(CommandOutput::Item(json!({ "userId": user_id, "role": role })));
+        }
+
+        writeln!(ctx.stderr, "{}", "Updating member...".blue())?;
+        let result = self
+            .api_client
+            .set_organization_member_role(&environment_id, &org_id, &user_id, &role)
+            .await?;
+
+        CommandOutput::item(&result)
+    }
+}
+
+// Command to remove a user from an organization
+pub struct RemoveMemberCommand {
+    api_client: Arc<dyn AdminApi>,
+}
+
+impl RemoveMemberCommand {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
+        Self { api_client }
+    }
+}
+
+#[async_trait]
+impl Command for RemoveMemberCommand {
+    fn name(&self) -> &str {
+        "remove"
+    }
+
+    fn description(&self) -> &str {
+        "Remove a user from an organization"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(member_id_args());
+        command_args.push(args::yes_arg());
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+
+        let org_id = ctx.value_or_prompt("organization-id", "Enter organization ID")?;
+        let user_id = ctx.value_or_prompt("user-id", "Enter user ID")?;
+        if ctx.skip_in_dry_run(&format!("remove user {} from organization {}", user_id, org_id))?
+            || !ctx.confirm(&format!("Remove user {} from organization {}?", user_id, org_id))?
+        {
+            return Ok(CommandOutput::Empty);
+        }
+
+        writeln!(ctx.stderr, "{}", "Removing member...".blue())?;
+        self.api_client
+            .remove_organization_member(&environment_id, &org_id, &user_id)
+            .await?;
+
+        writeln!(ctx.stderr, "{} {}", "Removed member".green(), user_id.yellow())?;
+        Ok(CommandOutput::Empty)
+    }
+}
+
+fn organization_field_args() -> Vec<Arg> {
+    vec![
+        Arg::new("name").long("name").value_name("NAME").help("Organization name"),
+        Arg::new("description")
+            .long("description")
+            .value_name("TEXT")
+            .help("Description"),
+        Arg::new("website-url")
+            .long("website-url")
+            .value_name("URL")
+            .help("Website, e.g. https://acme.example.com"),
+    ]
+}
+
+// Read and validate the organization field flags
+fn organization_fields(matches: &ArgMatches) -> Result<OrganizationFields> {
+    let website_url = args::get_value(matches, "website-url");
+    if let Some(url) = &website_url
+        && !matches!(reqwest::Url::parse(url), Ok(parsed) if matches!(parsed.scheme(), "http" | "https"))
+    {
+        return Err(anyhow::anyhow!("Invalid website URL '{}': include the scheme, e.g. https://", url));
+    }
+
+    Ok(OrganizationFields {
+        name: args::get_value(matches, "name"),
+        description: args::get_value(matches, "description"),
+        website_url,
+    })
+}
+
+// Organization and user IDs, positionally in that order or as flags
+fn member_id_args() -> Vec<Arg> {
+    let mut member_args = args::id_args("organization-id", "ORGANIZATION_ID", "Organization ID");
+    member_args.extend(args::id_args("user-id", "USER_ID", "User ID"));
+    member_args
+}
+
+fn role_arg() -> Arg {
+    Arg::new("role")
+        .long("role")
+        .value_name("ROLE")
+        .value_parser(ORGANIZATION_ROLES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_api::{column, execute, json, FakeAdminApi};
+
+    #[tokio::test]
+    async fn listings_and_lookups_render_organizations() {
+        let api = FakeAdminApi::sample();
+        let list = ListOrganizationsCommand::new(api.clone());
+        assert_eq!(column(&json(&list, &["list"]).await, "name"), ["Acme Labs"]);
+        let org = json(&GetOrganizationCommand::new(api.clone()), &["get", "org-1"]).await;
+        assert_eq!((&org["id"], &org["name"]), (&json!("org-1"), &json!("Acme Labs")));
+
+        let created = json(&CreateOrganizationCommand::new(api.clone()), &["create", "--name", "Initech"]).await;
+        let id = created["id"].as_str().unwrap();
+        assert_eq!(column(&json(&list, &["list"]).await, "id"), ["org-1", id]);
+        assert_eq!(column(&json(&list, &["list", "--limit", "1"]).await, "id"), ["org-1"]);
+    }
+
+    #[tokio::test]
+    async fn update_changes_only_the_given_fields() {
+        let api = FakeAdminApi::sample();
+        let create = CreateOrganizationCommand::new(api.clone());
+        assert!(execute(&create, &["create", "--name", "Initech", "--website-url", "initech.example"]).await.is_err());
+        let created = json(&create, &["create", "--name", "Initech", "--website-url", "https://initech.example"]).await;
+        assert_eq!((&created["name"], &created["websiteUrl"]), (&json!("Initech"), &json!("https://initech.example")));
+        let id = created["id"].as_str().unwrap();
+
+        let updated = json(&UpdateOrganizationCommand::new(api.clone()), &["update", id, "--description", "TPS reports"]).await;
+        assert_eq!((&updated["name"], &updated["description"]), (&json!("Initech"), &json!("TPS reports")));
+        assert_eq!(updated["websiteUrl"], "https://initech.example");
+
+        execute(&DeleteOrganizationCommand::new(api.clone()), &["delete", id, "--yes"]).await.unwrap();
+        assert_eq!(api.state().organizations.len(), 1);
+        assert_eq!(api.calls().last().unwrap(), &format!("delete_organization {}", id));
+    }
+
+    #[tokio::test]
+    async fn members_keep_their_roles() {
+        let api = FakeAdminApi::sample();
+
+        let add = AddMemberCommand::new(api.clone());
+        assert!(execute(&add, &["add", "org-1", "user-2", "--role", "boss"]).await.is_err());
+        execute(&add, &["add", "org-1", "user-2"]).await.unwrap();
+        let list = ListMembersCommand::new(api.clone());
+        assert_eq!(column(&json(&list, &["list", "org-1"]).await, "role"), ["owner", "member"]);
+
+        let set_role = SetMemberRoleCommand::new(api.clone());
+        execute(&set_role, &["set-role", "org-1", "user-2", "--role", "admin"]).await.unwrap();
+        let members = json(&list, &["list", "org-1"]).await;
+        assert_eq!(column(&members, "userId"), ["user-1", "user-2"]);
+        assert_eq!(column(&members, "role"), ["owner", "admin"]);
+
+        execute(&RemoveMemberCommand::new(api.clone()), &["remove", "org-1", "user-2", "--yes"]).await.unwrap();
+        assert_eq!(column(&json(&list, &["list", "org-1"]).await, "userId"), ["user-1"]);
+    }
+}
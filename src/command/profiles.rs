@@ -0,0 +1,412 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::{Arg, ArgAction, ArgMatches};
+use colored::*;
+use serde_json::{json, Map, Value};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::command::args;
+use crate::commands::Command;
+use crate::config::{self, Profile};
+use crate::context::{CommandOutput, ExecutionContext};
+use crate::credentials::{self, TokenStorage};
+use crate::prompt::Prompter;
+
+// Default table columns for the profile listing
+const PROFILE_COLUMNS: [&str; 5] = ["active", "name", "default", "base_url", "default_environment_id"];
+
+// Command to list the profiles in config.toml
+pub struct ListProfilesCommand {
+    config_path: PathBuf,
+}
+
+impl ListProfilesCommand {
+    pub fn new(config_path: PathBuf) -> Self {
+        Self { config_path }
+    }
+}
+
+#[async_trait]
+impl Command for ListProfilesCommand {
+    fn name(&self) -> &str {
+        "list"
+    }
+
+    fn description(&self) -> &str {
+        "List configured profiles"
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let file = config::read_config_file(&self.config_path)?;
+        let default = file.default_profile_name();
+
+        let profiles: Vec<Result<Value>> = file
+            .profiles
+            .iter()
+            .map(|(name, profile)| {
+                Ok(json!({
+                    "active": *name == ctx.profile,
+                    "name": name,
+                    "default": *name == default,
+                    "base_url": profile.base_url,
+                    "default_environment_id": profile.default_environment_id,
+                }))
+            })
+            .collect();
+        Ok(CommandOutput::list(futures::stream::iter(profiles), &PROFILE_COLUMNS))
+    }
+}
+
+// Command to add a profile. Its token and base URL come from the global --token and --base-url.
+pub struct AddProfileCommand {
+    config_path: PathBuf,
+}
+
+impl AddProfileCommand {
+    pub fn new(config_path: PathBuf) -> Self {
+        Self { config_path }
+    }
+}
+
+#[async_trait]
+impl Command for AddProfileCommand {
+    fn name(&self) -> &str {
+        "add"
+    }
+
+    fn description(&self) -> &str {
+        "Add a profile for another project or account"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::new("name")
+                .value_name("NAME")
+                .help("Profile name, e.g. staging or prod"),
+            args::environment_arg().help("Default environment ID for the profile"),
+            token_storage_arg(),
+            token_command_arg(),
+            Arg::new("default")
+                .long("default")
+                .help("Make this the default profile")
+                .action(ArgAction::SetTrue),
+        ]
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let name = ctx.value_or_prompt("name", "Enter profile name")?;
+        let path = &self.config_path;
+        let mut file = config::read_config_file(path)?;
+
+        if file.profiles.contains_key(&name) {
+            return Err(anyhow::anyhow!("Profile '{}' already exists", name));
+        }
+        if ctx.skip_in_dry_run(&format!("add profile {}", name))? {
+            return Ok(CommandOutput::Empty);
+        }
+
+        let token = args::get_value(ctx.args, "token");
+        let token_command = args::get_value(ctx.args, "token-command");
+        let environment_id = args::get_value(ctx.args, "environment-id");
+        let profile = match environment_id {
+            Some(default_environment_id) if token.is_some() || token_command.is_some() => {
+                if !config::is_uuid(&default_environment_id) {
+                    return Err(anyhow::anyhow!("Invalid environment ID '{}'", default_environment_id));
+                }
+                let mut profile = Profile {
+                    token_command,
+                    base_url: args::get_value(ctx.args, "base-url")
+                        .unwrap_or_else(config::default_base_url),
+                    default_environment_id,
+                    ..Profile::default()
+                };
+                if let Some(token) = token {
+                    save_token(ctx.prompter.as_ref(), path, &name, &mut profile, token_storage(ctx.args), token)?;
+                }
+                profile
+            }
+            _ if ctx.prompter.is_interactive() => config::prompt_profile(ctx.prompter.as_ref(), path, &name)?,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Missing required arguments --environment-id and --token (or --token-command)"
+                ))
+            }
+        };
+
+        if ctx.args.get_flag("default") || file.profiles.is_empty() {
+            file.default_profile = Some(name.clone());
+        }
+        file.profiles.insert(name.clone(), profile);
+        config::write_config_file(path, &file)?;
+
+        writeln!(ctx.stderr, "{} {}", "Added profile".green(), name.yellow())?;
+        Ok(CommandOutput::Empty)
+    }
+}
+
+// Command to delete a profile
+pub struct RemoveProfileCommand {
+    config_path: PathBuf,
+}
+
+impl RemoveProfileCommand {
+    pub fn new(config_path: PathBuf) -> Self {
+        Self { config_path }
+    }
+}
+
+#[async_trait]
+impl Command for RemoveProfileCommand {
+    fn name(&self) -> &str {
+        "remove"
+    }
+
+    fn description(&self) -> &str {
+        "Remove a profile"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::new("name")
+                .value_name("NAME")
+                .help("Profile to remove"),
+            args::yes_arg(),
+        ]
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let name = ctx.value_or_prompt("name", "Enter profile name")?;
+        let path = &self.config_path;
+        let mut file = config::read_config_file(path)?;
+
+        if !file.profiles.contains_key(&name) {
+            return Err(anyhow::anyhow!("Profile '{}' not found", name));
+        }
+
+        if ctx.skip_in_dry_run(&format!("remove profile {}", name))? || !ctx.confirm(&format!("Remove profile '{}'?", name))? {
+            return Ok(CommandOutput::Empty);
+        }
+
+        if let Some(profile) = file.profiles.remove(&name) {
+            credentials::delete_token(path, &name, profile.token_storage)?;
+        }
+        if file.default_profile.as_deref() == Some(name.as_str()) {
+            file.default_profile = None;
+        }
+        config::write_config_file(path, &file)?;
+
+        writeln!(ctx.stderr, "{} {}", "Removed profile".green(), name.yellow())?;
+        Ok(CommandOutput::Empty)
+    }
+}
+
+// Command to show a profile's settings
+pub struct ShowProfileCommand {
+    config_path: PathBuf,
+}
+
+impl ShowProfileCommand {
+    pub fn new(config_path: PathBuf) -> Self {
+        Self { config_path }
+    }
+}
+
+#[async_trait]
+impl Command for ShowProfileCommand {
+    fn name(&self) -> &str {
+        "show"
+    }
+
+    fn description(&self) -> &str {
+        "Show a profile's settings (defaults to the active profile)"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![Arg::new("name").value_name("NAME").help("Profile to show")]
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let name = args::get_value(ctx.args, "name").unwrap_or_else(|| ctx.profile.clone());
+        let file = config::read_config_file(&self.config_path)?;
+        let profile = file
+            .profiles
+            .get(&name)
+            .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))?;
+
+        let mut item = Map::new();
+        item.insert("name".to_string(), json!(name));
+        match &profile.token_command {
+            Some(command) => item.insert("token_command".to_string(), json!(command)),
+            None if profile.token_storage.is_plaintext() => {
+                item.insert("api_token".to_string(), json!(config::mask_token(&profile.api_token)))
+            }
+            None => item.insert("token_storage".to_string(), json!(profile.token_storage.name())),
+        };
+        item.insert("base_url".to_string(), json!(profile.base_url));
+        item.insert("default_environment_id".to_string(), json!(profile.default_environment_id));
+
+        Ok(CommandOutput::Item(Value::Object(item)))
+    }
+}
+
+// Command to change the default profile
+pub struct UseProfileCommand {
+    config_path: PathBuf,
+}
+
+impl UseProfileCommand {
+    pub fn new(config_path: PathBuf) -> Self {
+        Self { config_path }
+    }
+}
+
+#[async_trait]
+impl Command for UseProfileCommand {
+    fn name(&self) -> &str {
+        "use"
+    }
+
+    fn description(&self) -> &str {
+        "Set the default profile"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![Arg::new("name").value_name("NAME").help("Profile to make the default")]
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let path = &self.config_path;
+        let mut file = config::read_config_file(path)?;
+
+        let names: Vec<&str> = file.profiles.keys().map(String::as_str).collect();
+        let name = ctx.value_or_select("name", "Select default profile", &names)?;
+        if !file.profiles.contains_key(&name) {
+            return Err(anyhow::anyhow!("Profile '{}' not found", name));
+        }
+
+        if ctx.skip_in_dry_run(&format!("make {} the default profile", name))? {
+            return Ok(CommandOutput::Empty);
+        }
+        file.default_profile = Some(name.clone());
+        config::write_config_file(path, &file)?;
+
+        writeln!(ctx.stderr, "{} {}", "Default profile:".green(), name.yellow())?;
+        Ok(CommandOutput::Empty)
+    }
+}
+
+// Command to replace a profile's token or move it to another storage backend
+pub struct SetTokenCommand {
+    config_path: PathBuf,
+}
+
+impl SetTokenCommand {
+    pub fn new(config_path: PathBuf) -> Self {
+        Self { config_path }
+    }
+}
+
+#[async_trait]
+impl Command for SetTokenCommand {
+    fn name(&self) -> &str {
+        "set-token"
+    }
+
+    fn description(&self) -> &str {
+        "Store a profile's API token in the keyring, an encrypted file or config.toml"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::new("name").value_name("NAME").help("Profile to update (defaults to the active profile)"),
+            token_storage_arg(),
+            token_command_arg().conflicts_with("token-storage"),
+        ]
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let name = args::get_value(ctx.args, "name").unwrap_or_else(|| ctx.profile.clone());
+        let path = &self.config_path;
+        let mut file = config::read_config_file(path)?;
+        let profile = file
+            .profiles
+            .get_mut(&name)
+            .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))?;
+        if ctx.skip_in_dry_run(&format!("update the token for profile {}", name))? {
+            return Ok(CommandOutput::Empty);
+        }
+
+        let previous = profile.token_storage;
+        match args::get_value(ctx.args, "token-command") {
+            Some(command) => {
+                profile.token_command = Some(command);
+                profile.api_token.clear();
+                profile.token_storage = TokenStorage::Plaintext;
+            }
+            None => {
+                // Keep the current token when only the storage changes
+                let token = match args::get_value(ctx.args, "token") {
+                    Some(token) => token,
+                    None if profile.token_command.is_none() => credentials::load_token(path, &name, profile, ctx.prompter.as_ref())?,
+                    None => return Err(anyhow::anyhow!("Missing required argument --token")),
+                };
+                profile.token_command = None;
+                save_token(ctx.prompter.as_ref(), path, &name, profile, token_storage(ctx.args), token)?;
+            }
+        }
+
+        // Don't leave a copy behind in the old backend
+        if previous != profile.token_storage {
+            credentials::delete_token(path, &name, previous)?;
+        }
+        let storage = profile.token_storage;
+        config::write_config_file(path, &file)?;
+
+        writeln!(ctx.stderr, "{} {} ({})", "Updated token for profile".green(), name.yellow(), storage.name())?;
+        Ok(CommandOutput::Empty)
+    }
+}
+
+fn token_storage_arg() -> Arg {
+    Arg::new("token-storage")
+        .long("token-storage")
+        .value_name("STORAGE")
+        .help("Where to keep the token given with --token")
+        .value_parser(clap::value_parser!(TokenStorage))
+}
+
+fn token_command_arg() -> Arg {
+    Arg::new("token-command")
+        .long("token-command")
+        .value_name("COMMAND")
+        .help("Shell command that prints the token, e.g. `op read op://vault/dynamic/token`")
+}
+
+fn token_storage(matches: &ArgMatches) -> TokenStorage {
+    matches
+        .try_get_one::<TokenStorage>("token-storage")
+        .ok()
+        .flatten()
+        .copied()
+        .unwrap_or_default()
+}
+
+// Validate a token and put it in the chosen backend, keeping it in the profile only for plaintext
+fn save_token(
+    prompter: &dyn Prompter,
+    config_path: &Path,
+    name: &str,
+    profile: &mut Profile,
+    storage: TokenStorage,
+    token: String,
+) -> Result<()> {
+    if !config::is_api_token(&token) {
+        return Err(anyhow::anyhow!("Invalid API token format. It should start with 'dyn_'"));
+    }
+
+    credentials::store_token(config_path, name, storage, &token, prompter)?;
+    profile.token_storage = storage;
+    profile.api_token = if storage.is_plaintext() { token } else { String::new() };
+    Ok(())
+}
@@ -1,19 +1,41 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use colored::*;
-use dialoguer::{theme::ColorfulTheme, Input};
+use clap::{Arg, ArgAction, ArgMatches};
+use futures::StreamExt;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
 use std::sync::Arc;
 
-use crate::api::DynamicApiClient;
+use crate::address;
+use crate::api::{self, AdminApi, UserFields, WalletFields};
+use crate::command::args;
 use crate::commands::Command;
+use crate::context::{CommandOutput, ExecutionContext};
+use crate::error::Cancelled;
+use crate::output;
+use crate::validation;
+
+// Default table columns for user listings
+const USER_COLUMNS: [&str; 4] = ["id", "email", "phoneNumber", "createdAt"];
+
+// Users created at once by `users import` unless --concurrency says otherwise
+const DEFAULT_IMPORT_CONCURRENCY: u32 = 4;
+// Default table columns for import results
+const IMPORT_RESULT_COLUMNS: [&str; 5] = ["line", "key", "status", "userId", "error"];
 
 // Command to list users
 pub struct ListUsersCommand {
-    api_client: Arc<DynamicApiClient>,
+    api_client: Arc<dyn AdminApi>,
 }
 
 impl ListUsersCommand {
-    pub fn new(api_client: Arc<DynamicApiClient>) -> Self {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
         Self { api_client }
     }
 }
@@ -28,29 +50,29 @@ impl Command for ListUsersCommand {
         "List all users"
     }
 
-    async fn execute(&self) -> Result<()> {
-        let environment_id: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("Enter environment ID")
-            .interact()?;
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(args::pagination_args());
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
 
-        println!("{}", "Fetching users...".blue());
-        let result = self.api_client.list_users(&environment_id).await?;
-        
-        // Pretty print the JSON result
-        let formatted = serde_json::to_string_pretty(&result)?;
-        println!("{}", formatted);
-        
-        Ok(())
+        writeln!(ctx.stderr, "{}", "Fetching users...".blue())?;
+        let users = self.api_client.list_users(&environment_id, args::page_options(ctx.args));
+
+        Ok(CommandOutput::list(users, &USER_COLUMNS))
     }
 }
 
 // Command to get a specific user
 pub struct GetUserCommand {
-    api_client: Arc<DynamicApiClient>,
+    api_client: Arc<dyn AdminApi>,
 }
 
 impl GetUserCommand {
-    pub fn new(api_client: Arc<DynamicApiClient>) -> Self {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
         Self { api_client }
     }
 }
@@ -65,22 +87,816 @@ impl Command for GetUserCommand {
         "Get a specific user"
     }
 
-    async fn execute(&self) -> Result<()> {
-        let environment_id: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("Enter environment ID")
-            .interact()?;
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(args::id_args("user-id", "USER_ID", "User ID"));
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
 
-        let user_id: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("Enter user ID")
-            .interact()?;
+        let user_id = ctx.value_or_prompt("user-id", "Enter user ID")?;
 
-        println!("{}", "Fetching user...".blue());
+        writeln!(ctx.stderr, "{}", "Fetching user...".blue())?;
         let result = self.api_client.get_user(&environment_id, &user_id).await?;
-        
-        // Pretty print the JSON result
-        let formatted = serde_json::to_string_pretty(&result)?;
-        println!("{}", formatted);
-        
-        Ok(())
-    }
-}
\ No newline at end of file
+
+        CommandOutput::item(&result)
+    }
+}
+
+// Command to create a user
+pub struct CreateUserCommand {
+    api_client: Arc<dyn AdminApi>,
+}
+
+impl CreateUserCommand {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
+        Self { api_client }
+    }
+}
+
+#[async_trait]
+impl Command for CreateUserCommand {
+    fn name(&self) -> &str {
+        "create"
+    }
+
+    fn description(&self) -> &str {
+        "Create a user"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(user_field_args());
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+
+        let mut fields = user_fields(ctx.args)?;
+        if fields.email.is_none() && fields.phone_number.is_none() {
+            let email = ctx.value_or_prompt("email", "Enter email")?;
+            fields.email = Some(checked_email(email)?);
+        }
+        if ctx.skip_in_dry_run("create a user")? {
+            return CommandOutput::item(&fields);
+        }
+
+        writeln!(ctx.stderr, "{}", "Creating user...".blue())?;
+        let result = self.api_client.create_user(&environment_id, &fields, None).await?;
+
+        CommandOutput::item(&result)
+    }
+}
+
+// Command to update a user's details
+pub struct UpdateUserCommand {
+    api_client: Arc<dyn AdminApi>,
+}
+
+impl UpdateUserCommand {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
+        Self { api_client }
+    }
+}
+
+#[async_trait]
+impl Command for UpdateUserCommand {
+    fn name(&self) -> &str {
+        "update"
+    }
+
+    fn description(&self) -> &str {
+        "Update a user's email, phone number, alias, name or metadata"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(args::id_args("user-id", "USER_ID", "User ID"));
+        command_args.extend(user_field_args());
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+
+        let user_id = ctx.value_or_prompt("user-id", "Enter user ID")?;
+        let fields = user_fields(ctx.args)?;
+        if fields.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Nothing to update; pass at least one of --email, --phone, --alias, --first-name, --last-name or --metadata"
+            ));
+        }
+        if ctx.skip_in_dry_run(&format!("update user {}", user_id))? {
+            return CommandOutput::item(&fields);
+        }
+
+        writeln!(ctx.stderr, "{}", "Updating user...".blue())?;
+        let result = self.api_client.update_user(&environment_id, &user_id, &fields).await?;
+
+        CommandOutput::item(&result)
+    }
+}
+
+// Command to delete a user
+pub struct DeleteUserCommand {
+    api_client: Arc<dyn AdminApi>,
+}
+
+impl DeleteUserCommand {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
+        Self { api_client }
+    }
+}
+
+#[async_trait]
+impl Command for DeleteUserCommand {
+    fn name(&self) -> &str {
+        "delete"
+    }
+
+    fn description(&self) -> &str {
+        "Delete a user"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(args::id_args("user-id", "USER_ID", "User ID"));
+        command_args.push(args::yes_arg());
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+
+        let user_id = ctx.value_or_prompt("user-id", "Enter user ID")?;
+        if ctx.skip_in_dry_run(&format!("delete user {}", user_id))?
+            || !ctx.confirm(&format!("Delete user {}? This can't be undone", user_id))?
+        {
+            return Ok(CommandOutput::Empty);
+        }
+
+        writeln!(ctx.stderr, "{}", "Deleting user...".blue())?;
+        self.api_client.delete_user(&environment_id, &user_id).await?;
+
+        writeln!(ctx.stderr, "{} {}", "Deleted user".green(), user_id.yellow())?;
+        Ok(CommandOutput::Empty)
+    }
+}
+
+// Command to block a user from signing in
+pub struct BlockUserCommand {
+    api_client: Arc<dyn AdminApi>,
+}
+
+impl BlockUserCommand {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
+        Self { api_client }
+    }
+}
+
+#[async_trait]
+impl Command for BlockUserCommand {
+    fn name(&self) -> &str {
+        "block"
+    }
+
+    fn description(&self) -> &str {
+        "Block a user from signing in"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(args::id_args("user-id", "USER_ID", "User ID"));
+        command_args.push(args::yes_arg());
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+
+        let user_id = ctx.value_or_prompt("user-id", "Enter user ID")?;
+        if ctx.skip_in_dry_run(&format!("block user {}", user_id))?
+            || !ctx.confirm(&format!("Block user {}? Their sessions will stop working", user_id))?
+        {
+            return Ok(CommandOutput::Empty);
+        }
+
+        writeln!(ctx.stderr, "{}", "Blocking user...".blue())?;
+        self.api_client.block_user(&environment_id, &user_id).await?;
+
+        writeln!(ctx.stderr, "{} {}", "Blocked user".green(), user_id.yellow())?;
+        Ok(CommandOutput::Empty)
+    }
+}
+
+// Command to let a blocked user sign in again
+pub struct UnblockUserCommand {
+    api_client: Arc<dyn AdminApi>,
+}
+
+impl UnblockUserCommand {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
+        Self { api_client }
+    }
+}
+
+#[async_trait]
+impl Command for UnblockUserCommand {
+    fn name(&self) -> &str {
+        "unblock"
+    }
+
+    fn description(&self) -> &str {
+        "Unblock a previously blocked user"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(args::id_args("user-id", "USER_ID", "User ID"));
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+
+        let user_id = ctx.value_or_prompt("user-id", "Enter user ID")?;
+        if ctx.skip_in_dry_run(&format!("unblock user {}", user_id))? {
+            return Ok(CommandOutput::Empty);
+        }
+
+        writeln!(ctx.stderr, "{}", "Unblocking user...".blue())?;
+        self.api_client.unblock_user(&environment_id, &user_id).await?;
+
+        writeln!(ctx.stderr, "{} {}", "Unblocked user".green(), user_id.yellow())?;
+        Ok(CommandOutput::Empty)
+    }
+}
+
+// Flags for the writable user fields, shared by create and update
+fn user_field_args() -> Vec<Arg> {
+    vec![
+        Arg::new("email").long("email").value_name("EMAIL").help("Email address"),
+        Arg::new("phone")
+            .long("phone")
+            .value_name("PHONE")
+            .help("Phone number in E.164 format, e.g. +14155550123"),
+        Arg::new("alias").long("alias").value_name("ALIAS").help("Alias"),
+        Arg::new("first-name").long("first-name").value_name("NAME").help("First name"),
+        Arg::new("last-name").long("last-name").value_name("NAME").help("Last name"),
+        Arg::new("metadata")
+            .long("metadata")
+            .value_name("JSON")
+            .help("Metadata as a JSON object, replacing any existing metadata"),
+    ]
+}
+
+// Read and validate the user field flags
+fn user_fields(matches: &ArgMatches) -> Result<UserFields> {
+    let metadata = match args::get_value(matches, "metadata") {
+        Some(json) => match serde_json::from_str::<Value>(&json) {
+            Ok(value @ Value::Object(_)) => Some(value),
+            _ => return Err(anyhow::anyhow!("--metadata must be a JSON object, e.g. '{{\"plan\":\"pro\"}}'")),
+        },
+        None => None,
+    };
+
+    let phone_number = args::get_value(matches, "phone");
+    if let Some(phone) = phone_number.as_deref().filter(|p| !validation::is_phone_number(p)) {
+        return Err(anyhow::anyhow!("Invalid phone number '{}': use E.164 format, e.g. +14155550123", phone));
+    }
+
+    Ok(UserFields {
+        email: args::get_value(matches, "email").map(checked_email).transpose()?,
+        phone_number,
+        alias: args::get_value(matches, "alias"),
+        first_name: args::get_value(matches, "first-name"),
+        last_name: args::get_value(matches, "last-name"),
+        metadata,
+        wallets: None,
+    })
+}
+
+// Command to create users in bulk from a CSV or JSONL file
+pub struct ImportUsersCommand {
+    api_client: Arc<dyn AdminApi>,
+}
+
+impl ImportUsersCommand {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
+        Self { api_client }
+    }
+}
+
+#[async_trait]
+impl Command for ImportUsersCommand {
+    fn name(&self) -> &str {
+        "import"
+    }
+
+    fn description(&self) -> &str {
+        "Create users in bulk from a CSV or JSONL file"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            args::environment_arg(),
+            Arg::new("file")
+                .value_name("FILE")
+                .help("CSV with a header row, or JSONL with one user object per line"),
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Input format (defaults to the file extension)")
+                .value_parser(["csv", "jsonl"]),
+            Arg::new("concurrency")
+                .long("concurrency")
+                .value_name("N")
+                .help("Number of users to create at once")
+                .value_parser(clap::value_parser!(u32).range(1..=32)),
+            Arg::new("results")
+                .long("results")
+                .value_name("PATH")
+                .help("Where to write the outcome of each row (defaults to FILE.results.jsonl)"),
+            Arg::new("resume")
+                .long("resume")
+                .help("Continue an interrupted import, skipping rows that were already created")
+                .action(ArgAction::SetTrue),
+        ]
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+
+        let file = ctx.value_or_prompt("file", "Enter path to CSV or JSONL file")?;
+        let format = match args::get_value(ctx.args, "format") {
+            Some(format) => format,
+            None => import_format(&file)?,
+        };
+        let concurrency = ctx.args
+            .try_get_one::<u32>("concurrency")
+            .ok()
+            .flatten()
+            .copied()
+            .unwrap_or(DEFAULT_IMPORT_CONCURRENCY) as usize;
+        let results_path = args::get_value(ctx.args, "results").unwrap_or_else(|| format!("{}.results.jsonl", file));
+        let checkpoint_path = format!("{}.checkpoint", file);
+        let resume = ctx.args.get_flag("resume");
+
+        // Rows created by an earlier run, by line, with the idempotency key of what was sent
+        let done: HashMap<usize, String> = if resume {
+            read_checkpoint(Path::new(&checkpoint_path))?
+        } else if Path::new(&checkpoint_path).exists() && !ctx.dry_run {
+            return Err(anyhow::anyhow!(
+                "An interrupted import of {} left {}; pass --resume to continue it, or delete the file to start over",
+                file,
+                checkpoint_path
+            ));
+        } else {
+            HashMap::new()
+        };
+
+        // Validate every row before creating anything
+        let mut pending = Vec::new();
+        let mut invalid = Vec::new();
+        let mut skipped = 0;
+        for (line, row) in read_import_rows(Path::new(&file), &format)? {
+            let fields = row.and_then(|row| import_fields(&row).map_err(|e| (row_key(&row), e)));
+
+            // A created row must still be there unchanged, or the checkpoint is for a different file
+            if let Some(created) = done.get(&line) {
+                match &fields {
+                    Ok(fields) if idempotency_key(&environment_id, line, fields) == *created => {
+                        skipped += 1;
+                        continue;
+                    }
+                    _ => return Err(changed_since_checkpoint(&file, &checkpoint_path)),
+                }
+            }
+
+            match fields {
+                Ok(fields) => pending.push((line, fields)),
+                Err((key, error)) => invalid.push(ImportResult::invalid(line, key, error)),
+            }
+        }
+        if skipped < done.len() {
+            return Err(changed_since_checkpoint(&file, &checkpoint_path));
+        }
+
+        // A dry run only validates the file and reports the invalid rows
+        if ctx.dry_run {
+            writeln!(
+                ctx.stderr,
+                "{} {} valid, {} invalid",
+                "Checked rows:".blue(),
+                pending.len(),
+                invalid.len()
+            )?;
+            let failure = (!invalid.is_empty()).then(|| anyhow::anyhow!("{} invalid rows", invalid.len()));
+            let rows = futures::stream::iter(invalid.into_iter().map(Ok));
+            return Ok(CommandOutput::list(rows, &IMPORT_RESULT_COLUMNS).report(failure));
+        }
+
+        let mut results = BufWriter::new(
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(resume)
+                .truncate(!resume)
+                .open(&results_path)?,
+        );
+        let mut checkpoint = OpenOptions::new().create(true).append(true).open(&checkpoint_path)?;
+
+        for result in &invalid {
+            writeln!(results, "{}", serde_json::to_string(result)?)?;
+        }
+
+        if !done.is_empty() {
+            writeln!(ctx.stderr, "{} skipping {} rows created by the previous run", "Resuming:".blue(), done.len())?;
+        }
+        writeln!(ctx.stderr, "{} {} users with {} workers", "Importing".blue(), pending.len(), concurrency)?;
+
+        let bar = output::progress_bar(pending.len() as u64);
+        let api_client = &self.api_client;
+        let environment_id = environment_id.as_str();
+        // Ctrl-C stops handing out rows; those already sent finish and are recorded
+        let mut outcomes = futures::stream::iter(pending)
+            .take_until(Box::pin(ctx.cancel.clone().cancelled_owned()))
+            .map(|(line, fields)| async move {
+                // Keyed on the row, so a retried or resumed row isn't created twice
+                let idempotency_key = idempotency_key(environment_id, line, &fields);
+                let result = api_client.create_user(environment_id, &fields, Some(&idempotency_key)).await;
+                (line, fields, idempotency_key, result)
+            })
+            .buffer_unordered(concurrency);
+
+        let (mut created, mut failed) = (0, 0);
+        while let Some((line, fields, idempotency_key, result)) = outcomes.next().await {
+            let key = fields.email.clone().or(fields.phone_number.clone()).unwrap_or_default();
+            let outcome = match result {
+                Ok(user) => {
+                    created += 1;
+                    writeln!(checkpoint, "{} {}", line, idempotency_key)?;
+                    ImportResult {
+                        line,
+                        key,
+                        status: "created",
+                        user_id: Some(user.id),
+                        error: None,
+                    }
+                }
+                Err(e) => {
+                    failed += 1;
+                    ImportResult {
+                        line,
+                        key,
+                        status: "failed",
+                        user_id: None,
+                        error: Some(e.to_string()),
+                    }
+                }
+            };
+            writeln!(results, "{}", serde_json::to_string(&outcome)?)?;
+            results.flush()?;
+            bar.set_message(format!("{} failed", failed));
+            bar.inc(1);
+        }
+        bar.finish_and_clear();
+
+        writeln!(
+            ctx.stderr,
+            "{} {} created, {} failed, {} invalid. Results written to {}",
+            "Import finished:".green(),
+            created,
+            failed,
+            invalid.len(),
+            results_path
+        )?;
+
+        if ctx.cancel.is_cancelled() {
+            return Err(Cancelled("Import stopped; rerun with --resume to import the remaining rows".to_string()).into());
+        }
+        if failed == 0 && invalid.is_empty() {
+            fs::remove_file(&checkpoint_path)?;
+            return Ok(CommandOutput::Empty);
+        }
+        Err(anyhow::anyhow!(
+            "{} rows were not imported; fix them and rerun with --resume to retry only those",
+            failed + invalid.len()
+        ))
+    }
+}
+
+// Outcome of one row of an import, written to the results file
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportResult {
+    line: usize,
+    key: String,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl ImportResult {
+    fn invalid(line: usize, key: String, error: String) -> Self {
+        ImportResult {
+            line,
+            key,
+            status: "invalid",
+            user_id: None,
+            error: Some(error),
+        }
+    }
+}
+
+// Columns accepted in an import file, with the alternative spellings for each
+const IMPORT_COLUMNS: [&[&str]; 8] = [
+    &["email"],
+    &["phoneNumber", "phone", "phone_number"],
+    &["alias"],
+    &["firstName", "first_name"],
+    &["lastName", "last_name"],
+    &["metadata"],
+    &["walletAddress", "wallet_address", "wallet"],
+    &["chain"],
+];
+
+fn import_format(file: &str) -> Result<String> {
+    let extension = Path::new(file)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    match extension.as_str() {
+        "csv" => Ok("csv".to_string()),
+        "jsonl" | "ndjson" => Ok("jsonl".to_string()),
+        _ => Err(anyhow::anyhow!("Can't tell the format of {}; pass --format csv or --format jsonl", file)),
+    }
+}
+
+// Rows of the file by line number; rows that can't be parsed carry the reason
+type ImportRow = std::result::Result<Map<String, Value>, (String, String)>;
+
+fn read_import_rows(path: &Path, format: &str) -> Result<Vec<(usize, ImportRow)>> {
+    let mut rows = Vec::new();
+
+    if format == "csv" {
+        let mut reader = csv::Reader::from_path(path)?;
+        let headers = reader.headers()?.clone();
+        for (i, record) in reader.records().enumerate() {
+            // The header is line 1
+            let line = i + 2;
+            let row = record.map_err(|e| (String::new(), e.to_string())).map(|record| {
+                headers
+                    .iter()
+                    .zip(record.iter())
+                    .filter(|(_, value)| !value.trim().is_empty())
+                    .map(|(header, value)| (header.trim().to_string(), Value::String(value.trim().to_string())))
+                    .collect()
+            });
+            rows.push((line, row));
+        }
+    } else {
+        for (i, text) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let text = text?;
+            if text.trim().is_empty() {
+                continue;
+            }
+            let row = match serde_json::from_str(&text) {
+                Ok(Value::Object(map)) => Ok(map),
+                Ok(_) => Err((String::new(), "expected a JSON object".to_string())),
+                Err(e) => Err((String::new(), format!("invalid JSON: {}", e))),
+            };
+            rows.push((i + 1, row));
+        }
+    }
+
+    Ok(rows)
+}
+
+// A row's value for a column under any of its spellings
+fn column<'a>(row: &'a Map<String, Value>, names: &[&str]) -> Option<&'a Value> {
+    names.iter().find_map(|name| row.get(*name)).filter(|value| !value.is_null())
+}
+
+fn column_str(row: &Map<String, Value>, names: &[&str]) -> Option<String> {
+    column(row, names).map(|value| match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    })
+}
+
+// What identifies a row in the results: its email or phone number
+fn row_key(row: &Map<String, Value>) -> String {
+    column_str(row, IMPORT_COLUMNS[0])
+        .or_else(|| column_str(row, IMPORT_COLUMNS[1]))
+        .unwrap_or_default()
+}
+
+// Validate a row and turn it into the fields of a new user
+fn import_fields(row: &Map<String, Value>) -> std::result::Result<UserFields, String> {
+    if let Some(unknown) = row.keys().find(|key| !IMPORT_COLUMNS.iter().any(|names| names.contains(&key.as_str()))) {
+        return Err(format!("unknown column '{}'", unknown));
+    }
+
+    let [email, phone, alias, first_name, last_name, metadata, wallet, chain] = IMPORT_COLUMNS;
+    let email = column_str(row, email);
+    let phone_number = column_str(row, phone);
+
+    if email.is_none() && phone_number.is_none() {
+        return Err("needs an email or phone number".to_string());
+    }
+    if let Some(email) = email.as_deref().filter(|e| !validation::is_email(e)) {
+        return Err(format!("invalid email '{}'", email));
+    }
+    if let Some(phone) = phone_number.as_deref().filter(|p| !validation::is_phone_number(p)) {
+        return Err(format!("invalid phone number '{}' (use E.164, e.g. +14155550123)", phone));
+    }
+
+    // CSV cells hold metadata as a JSON string
+    let metadata = match column(row, metadata) {
+        None => None,
+        Some(Value::String(json)) => match serde_json::from_str(json) {
+            Ok(value @ Value::Object(_)) => Some(value),
+            _ => return Err("metadata must be a JSON object".to_string()),
+        },
+        Some(value @ Value::Object(_)) => Some(value.clone()),
+        Some(_) => return Err("metadata must be a JSON object".to_string()),
+    };
+
+    // Addresses on chains we can check are validated and normalized; others are sent as given
+    let chain = column_str(row, chain);
+    let wallets = match column_str(row, wallet) {
+        Some(public_key) => {
+            let public_key = match chain.as_deref().map(address::chain_family) {
+                Some(None) => public_key,
+                family => address::validate(&public_key, family.flatten())
+                    .map_err(|e| format!("invalid wallet address '{}': {}", public_key, e))?
+                    .normalized,
+            };
+            Some(vec![WalletFields { public_key, chain }])
+        }
+        None => None,
+    };
+
+    Ok(UserFields {
+        email,
+        phone_number,
+        alias: column_str(row, alias),
+        first_name: column_str(row, first_name),
+        last_name: column_str(row, last_name),
+        metadata,
+        wallets,
+    })
+}
+
+// Lines created by an earlier run, each with the idempotency key of the row that was sent
+fn read_checkpoint(path: &Path) -> Result<HashMap<usize, String>> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    fs::read_to_string(path)?
+        .lines()
+        .filter(|entry| !entry.trim().is_empty())
+        .map(|entry| {
+            entry
+                .split_once(' ')
+                .and_then(|(line, key)| Some((line.parse().ok()?, key.trim().to_string())))
+                .ok_or_else(|| anyhow::anyhow!("{:?} isn't an import checkpoint; delete it to start over", path))
+        })
+        .collect()
+}
+
+fn changed_since_checkpoint(file: &str, checkpoint_path: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "{} no longer has the rows {} recorded as created; restore the file, or delete the checkpoint to start over",
+        file,
+        checkpoint_path
+    )
+}
+
+fn idempotency_key(environment_id: &str, line: usize, fields: &UserFields) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(environment_id.as_bytes());
+    hasher.update(line.to_le_bytes());
+    hasher.update(serde_json::to_vec(fields).unwrap_or_default());
+    format!("dynamic-admin-import-{}", api::hex(&hasher.finalize()[..16]))
+}
+
+fn checked_email(email: String) -> Result<String> {
+    if !validation::is_email(&email) {
+        return Err(anyhow::anyhow!("Invalid email address '{}'", email));
+    }
+    Ok(email)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_api::{self, column, execute, execute_with, json, FakeAdminApi};
+    use crate::test_support;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn listings_and_lookups_render_users() {
+        let api = FakeAdminApi::sample();
+        let list = ListUsersCommand::new(api.clone());
+        assert_eq!(column(&json(&list, &["list"]).await, "email"), ["ada@example.com", "grace@example.com"]);
+        assert_eq!(column(&json(&list, &["list", "--limit", "1"]).await, "id"), ["user-1"]);
+
+        let get = GetUserCommand::new(api.clone());
+        let user = json(&get, &["get", "user-1"]).await;
+        assert_eq!((&user["id"], &user["firstName"]), (&json!("user-1"), &json!("Ada")));
+        let missing = execute(&get, &["get", "user-9"]).await.unwrap_err();
+        assert!(missing.to_string().contains("not found"), "{}", missing);
+    }
+
+    #[tokio::test]
+    async fn create_and_update_send_only_the_given_fields() {
+        let api = FakeAdminApi::sample();
+        let create = CreateUserCommand::new(api.clone());
+        assert!(execute(&create, &["create", "--email", "not an email"]).await.is_err());
+        let created = json(&create, &["create", "--email", "new@example.com", "--first-name", "New"]).await;
+        assert_eq!((&created["email"], &created["firstName"]), (&json!("new@example.com"), &json!("New")));
+        let id = created["id"].as_str().unwrap();
+
+        let updated = json(&UpdateUserCommand::new(api.clone()), &["update", id, "--alias", "newbie"]).await;
+        assert_eq!((&updated["alias"], &updated["firstName"]), (&json!("newbie"), &json!("New")));
+        assert_eq!(api.calls(), ["create_user new@example.com", &format!("update_user {}", id)]);
+    }
+
+    #[tokio::test]
+    async fn destructive_commands_need_confirmation() {
+        let api = FakeAdminApi::sample();
+        execute(&BlockUserCommand::new(api.clone()), &["block", "user-2", "--yes"]).await.unwrap();
+        assert_eq!(api.user("user-2").unwrap().extra["blocked"], true);
+        execute(&UnblockUserCommand::new(api.clone()), &["unblock", "user-2"]).await.unwrap();
+        assert_eq!(api.user("user-2").unwrap().extra["blocked"], false);
+
+        let delete = DeleteUserCommand::new(api.clone());
+        assert!(execute(&delete, &["delete", "user-2"]).await.is_err());
+        // Declining the confirmation, or a dry run, leaves the user alone
+        execute_with(&delete, &["delete", "user-2"], fake_api::answers(&["no"])).await.unwrap();
+        execute(&delete, &["delete", "user-2", "--yes", "--dry-run"]).await.unwrap();
+        assert!(api.user("user-2").is_some());
+        execute(&delete, &["delete", "user-2", "--yes"]).await.unwrap();
+        assert!(api.user("user-2").is_none());
+        assert_eq!(api.calls(), ["block_user user-2", "unblock_user user-2", "delete_user user-2"]);
+    }
+
+    #[tokio::test]
+    async fn import_creates_rows_and_reports_failures() {
+        let api = FakeAdminApi::sample();
+        let dir = test_support::temp_dir("import");
+        let file = dir.join("users.csv");
+        fs::write(&file, "email,first_name\nnew@example.com,New\nada@example.com,Ada\nbad,Bad\n").unwrap();
+        let file = file.to_str().unwrap();
+
+        let command = ImportUsersCommand::new(api.clone());
+        // A dry run reports the invalid rows and fails without creating anything
+        let (invalid, error) = fake_api::report(&command, &["import", file, "--dry-run"]).await;
+        assert_eq!(invalid, json!([{"line": 4, "key": "bad", "status": "invalid", "error": invalid[0]["error"]}]));
+        assert_eq!(error.to_string(), "1 invalid rows");
+        assert!(api.calls().is_empty());
+
+        // The invalid row is never sent and the duplicate is rejected, but the rest are created
+        assert!(execute(&command, &["import", file]).await.is_err());
+        assert_eq!(api.state().users.len(), 3);
+        assert_eq!(api.calls().iter().filter(|call| call.starts_with("create_user")).count(), 2);
+
+        let results = fs::read_to_string(dir.join("users.csv.results.jsonl")).unwrap();
+        assert_eq!(results.lines().count(), 3);
+        assert!(results.contains("\"status\":\"created\""));
+
+        // The checkpoint only fits the file it was written for: moving or editing a created row is refused
+        let calls = api.calls().len();
+        for changed in [
+            "email,first_name\nada2@example.com,Ada\nnew@example.com,New\nok@example.com,Bad\n",
+            "email,first_name\nnew@example.com,Newer\nada2@example.com,Ada\nok@example.com,Bad\n",
+            "email,first_name\n",
+        ] {
+            fs::write(file, changed).unwrap();
+            let error = execute(&command, &["import", file, "--resume"]).await.unwrap_err();
+            assert!(error.to_string().contains("no longer has the rows"), "{}", error);
+        }
+        assert_eq!(api.calls().len(), calls);
+
+        // Fixing only the rows that failed resumes the rest
+        fs::write(file, "email,first_name\nnew@example.com,New\nada2@example.com,Ada\nok@example.com,Bad\n").unwrap();
+        execute(&command, &["import", file, "--resume"]).await.unwrap();
+        assert_eq!(api.state().users.len(), 5);
+        assert!(!dir.join("users.csv.checkpoint").exists());
+        fs::remove_dir_all(dir).unwrap();
+
+        // FILE is positional only, so there's no flag to suggest
+        let missing = execute(&command, &["import"]).await.unwrap_err();
+        assert_eq!(missing.to_string(), "Missing required argument <FILE>");
+        let missing = execute(&GetUserCommand::new(api.clone()), &["get"]).await.unwrap_err();
+        assert_eq!(missing.to_string(), "Missing required argument --user-id");
+    }
+}
@@ -0,0 +1,514 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use colored::*;
+use clap::{Arg, ArgMatches};
+use futures::{future, StreamExt, TryStreamExt};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::address::{self, CHAIN_FAMILIES};
+use crate::api::{AdminApi, PageOptions};
+use crate::command::args;
+use crate::commands::Command;
+use crate::context::{CommandOutput, ExecutionContext};
+use crate::models::Wallet;
+use crate::output::OutputFormat;
+
+// Default table columns for wallet listings
+const WALLET_COLUMNS: [&str; 5] = ["id", "chain", "publicKey", "name", "provider"];
+// Columns of the flat address export
+const ADDRESS_COLUMNS: [&str; 2] = ["chain", "address"];
+// Default table columns for address validation results
+const VALIDATION_COLUMNS: [&str; 5] = ["line", "status", "chain", "normalized", "error"];
+
+// Command to list wallets in an environment or of a single user
+pub struct ListWalletsCommand {
+    api_client: Arc<dyn AdminApi>,
+}
+
+impl ListWalletsCommand {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
+        Self { api_client }
+    }
+}
+
+#[async_trait]
+impl Command for ListWalletsCommand {
+    fn name(&self) -> &str {
+        "list"
+    }
+
+    fn description(&self) -> &str {
+        "List wallets in the environment, or of one user with --user-id"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![
+            args::environment_arg(),
+            args::complete_ids(Arg::new("user-id"), "user-id")
+                .long("user-id")
+                .value_name("USER_ID")
+                .help("Only list this user's wallets"),
+            Arg::new("address")
+                .long("address")
+                .value_name("ADDRESS")
+                .help("Only wallets with this address, in any letter case"),
+        ];
+        command_args.extend(filter_args());
+        command_args.extend(args::pagination_args());
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+        let filter = WalletFilter::from_matches(ctx.args)?;
+
+        // --limit counts wallets that pass the filters, so keep fetching pages until it's reached
+        let options = args::page_options(ctx.args);
+        let limit = options.limit.unwrap_or(usize::MAX);
+        let fetch = if filter.is_empty() {
+            options
+        } else {
+            PageOptions { limit: None, ..options }
+        };
+
+        writeln!(ctx.stderr, "{}", "Fetching wallets...".blue())?;
+        let wallets = match args::get_value(ctx.args, "user-id") {
+            Some(user_id) => self.api_client.list_user_wallets(&environment_id, &user_id, fetch),
+            None => self.api_client.list_wallets(&environment_id, fetch),
+        };
+        let wallets = wallets
+            .try_filter(move |wallet| future::ready(filter.matches(wallet)))
+            .take(limit);
+
+        Ok(CommandOutput::list(wallets, &WALLET_COLUMNS))
+    }
+}
+
+// Command to get a specific wallet
+pub struct GetWalletCommand {
+    api_client: Arc<dyn AdminApi>,
+}
+
+impl GetWalletCommand {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
+        Self { api_client }
+    }
+}
+
+#[async_trait]
+impl Command for GetWalletCommand {
+    fn name(&self) -> &str {
+        "get"
+    }
+
+    fn description(&self) -> &str {
+        "Get a specific wallet"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(args::id_args("wallet-id", "WALLET_ID", "Wallet ID"));
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+
+        let wallet_id = ctx.value_or_prompt("wallet-id", "Enter wallet ID")?;
+
+        writeln!(ctx.stderr, "{}", "Fetching wallet...".blue())?;
+        let result = self.api_client.get_wallet(&environment_id, &wallet_id).await?;
+
+        CommandOutput::item(&result)
+    }
+}
+
+// Command to export every wallet address in the environment, grouped by chain
+pub struct ExportAddressesCommand {
+    api_client: Arc<dyn AdminApi>,
+}
+
+impl ExportAddressesCommand {
+    pub fn new(api_client: Arc<dyn AdminApi>) -> Self {
+        Self { api_client }
+    }
+}
+
+#[async_trait]
+impl Command for ExportAddressesCommand {
+    fn name(&self) -> &str {
+        "addresses"
+    }
+
+    fn description(&self) -> &str {
+        "Export all wallet addresses grouped by chain"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        let mut command_args = vec![args::environment_arg()];
+        command_args.extend(filter_args());
+        command_args
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let environment_id = ctx.environment_id()?;
+        let filter = WalletFilter::from_matches(ctx.args)?;
+
+        let spinner = crate::output::spinner("Collecting wallet addresses...");
+        let options = PageOptions {
+            limit: None,
+            ..args::page_options(ctx.args)
+        };
+        let mut wallets = std::pin::pin!(self.api_client.list_wallets(&environment_id, options));
+
+        // Normalized addresses by chain, so the same address in different letter case is listed once
+        let mut groups: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        let (mut count, mut invalid) = (0, 0);
+        while let Some(wallet) = wallets.try_next().await? {
+            count += 1;
+            spinner.set_message(format!("Collecting wallet addresses... {} wallets", count));
+            let Some(public_key) = wallet.public_key.as_deref().filter(|_| filter.matches(&wallet)) else {
+                continue;
+            };
+
+            // Addresses that fail validation are still exported as the API reported them
+            let address = match wallet.chain_family() {
+                Some(family) => address::validate(public_key, Some(family))
+                    .map(|address| address.normalized)
+                    .unwrap_or_else(|_| {
+                        invalid += 1;
+                        public_key.to_string()
+                    }),
+                None => public_key.to_string(),
+            };
+            groups.entry(address_group(&wallet)).or_default().insert(address);
+        }
+        spinner.finish_and_clear();
+
+        let total: usize = groups.values().map(BTreeSet::len).sum();
+        writeln!(ctx.stderr, "{} {} addresses on {} chains", "Exported".green(), total, groups.len())?;
+        if invalid > 0 {
+            writeln!(
+                ctx.stderr,
+                "{} {} addresses failed validation; check them with `wallets validate`",
+                "Warning:".yellow(),
+                invalid
+            )?;
+        }
+
+        match ctx.renderer.format() {
+            // Nested formats get one array of addresses per chain
+            OutputFormat::Json | OutputFormat::Yaml => CommandOutput::item(&groups),
+            _ => {
+                let rows = groups.into_iter().flat_map(|(chain, addresses)| {
+                    addresses
+                        .into_iter()
+                        .map(move |address| Ok(json!({ "chain": chain, "address": address })))
+                });
+                Ok(CommandOutput::list(futures::stream::iter(rows), &ADDRESS_COLUMNS))
+            }
+        }
+    }
+}
+
+// Command to check wallet addresses offline before importing or searching for them
+pub struct ValidateAddressesCommand;
+
+impl ValidateAddressesCommand {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Command for ValidateAddressesCommand {
+    fn name(&self) -> &str {
+        "validate"
+    }
+
+    fn description(&self) -> &str {
+        "Check wallet addresses offline and print them normalized"
+    }
+
+    fn requires_config(&self) -> bool {
+        false
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::new("input")
+                .value_name("FILE|ADDRESS")
+                .help("An address, or a file with one address or chain,address pair per line"),
+            Arg::new("chain")
+                .long("chain")
+                .value_name("CHAIN")
+                .help("Chain the addresses belong to (detected from each address by default)")
+                .value_parser(CHAIN_FAMILIES),
+        ]
+    }
+
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+        let input = ctx.value_or_prompt("input", "Enter an address or file path")?;
+        let chain = args::get_value(ctx.args, "chain");
+
+        let entries = if Path::new(&input).is_file() {
+            address_entries(&fs::read_to_string(&input)?)
+        } else {
+            vec![(1, None, input)]
+        };
+
+        let results: Vec<AddressCheck> = entries
+            .into_iter()
+            .map(|(line, line_chain, input)| AddressCheck::new(line, chain.as_deref().or(line_chain.as_deref()), input))
+            .collect();
+
+        let invalid = results.iter().filter(|r| r.status == "invalid").count();
+        let total = results.len();
+        let failure = if invalid > 0 {
+            Some(anyhow::anyhow!("{} of {} addresses are invalid", invalid, total))
+        } else {
+            writeln!(ctx.stderr, "{} {} addresses checked", "All valid:".green(), total)?;
+            None
+        };
+
+        let results = futures::stream::iter(results.into_iter().map(Ok));
+        Ok(CommandOutput::list(results, &VALIDATION_COLUMNS).report(failure))
+    }
+}
+
+// Outcome of validating one address
+#[derive(Debug, Serialize)]
+struct AddressCheck {
+    line: usize,
+    input: String,
+    // valid, invalid, or skipped for chains that can't be checked offline
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chain: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    normalized: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl AddressCheck {
+    fn new(line: usize, chain: Option<&str>, input: String) -> Self {
+        let check = AddressCheck {
+            line,
+            input,
+            status: "valid",
+            chain: None,
+            normalized: None,
+            error: None,
+        };
+
+        if let Some(chain) = chain.filter(|chain| address::chain_family(chain).is_none()) {
+            return AddressCheck {
+                status: "skipped",
+                chain: Some(chain.to_lowercase()),
+                ..check
+            };
+        }
+
+        match address::validate(&check.input, chain) {
+            Ok(address) => AddressCheck {
+                chain: Some(address.chain.to_string()),
+                normalized: Some(address.normalized),
+                ..check
+            },
+            Err(error) => AddressCheck {
+                status: "invalid",
+                chain: chain.and_then(address::chain_family).map(str::to_string),
+                error: Some(error),
+                ..check
+            },
+        }
+    }
+}
+
+// Addresses in a validation file by line number, with the chain when given as chain,address
+// (the CSV written by `wallets addresses -o csv`). Blank lines, # comments and the header are skipped.
+fn address_entries(content: &str) -> Vec<(usize, Option<String>, String)> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, text)| {
+            let text = text.trim();
+            if text.is_empty() || text.starts_with('#') || text == "chain,address" {
+                return None;
+            }
+            Some(match text.split_once(',') {
+                Some((chain, address)) => (i + 1, Some(chain.trim().to_string()), address.trim().to_string()),
+                None => (i + 1, None, text.to_string()),
+            })
+        })
+        .collect()
+}
+
+// Chain, provider and address filters shared by the wallet commands
+struct WalletFilter {
+    chain: Option<String>,
+    provider: Option<String>,
+    address: Option<String>,
+}
+
+impl WalletFilter {
+    fn from_matches(matches: &ArgMatches) -> Result<Self> {
+        // Catch typos up front instead of silently matching nothing
+        let address = match args::get_value(matches, "address") {
+            Some(address) => Some(
+                address::validate(&address, None)
+                    .map_err(|e| anyhow::anyhow!("Invalid --address '{}': {}", address, e))?
+                    .normalized,
+            ),
+            None => None,
+        };
+
+        Ok(WalletFilter {
+            chain: args::get_value(matches, "chain"),
+            provider: args::get_value(matches, "provider"),
+            address,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.chain.is_none() && self.provider.is_none() && self.address.is_none()
+    }
+
+    fn matches(&self, wallet: &Wallet) -> bool {
+        let chain = self.chain.as_deref().is_none_or(|chain| wallet.chain_family() == Some(chain));
+        let provider = self.provider.as_deref().is_none_or(|provider| wallet.has_provider(provider));
+        let address = self.address.as_deref().is_none_or(|address| {
+            wallet
+                .public_key
+                .as_deref()
+                .is_some_and(|public_key| address::same_address(public_key, address))
+        });
+        chain && provider && address
+    }
+}
+
+fn filter_args() -> Vec<Arg> {
+    vec![
+        Arg::new("chain")
+            .long("chain")
+            .value_name("CHAIN")
+            .help("Only wallets on this chain")
+            .value_parser(CHAIN_FAMILIES),
+        Arg::new("provider")
+            .long("provider")
+            .value_name("PROVIDER")
+            .help("Only wallets from this provider or wallet, e.g. embeddedWallet or metamask"),
+    ]
+}
+
+// Chain family for known chains, otherwise the chain code as reported
+fn address_group(wallet: &Wallet) -> String {
+    match wallet.chain_family() {
+        Some(family) => family.to_string(),
+        None => wallet.chain.as_deref().unwrap_or("unknown").to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_api::{self, column, execute, json, FakeAdminApi};
+    use crate::test_support;
+    use serde_json::from_value;
+
+    // The sample wallets plus a lower-case copy of wallet-1's address, a Flow wallet and a
+    // browser extension wallet
+    fn api() -> Arc<FakeAdminApi> {
+        let api = FakeAdminApi::sample();
+        let wallets = [
+            json!({
+                "id": "wallet-3", "userId": "user-1", "chain": "EVM", "name": "turnkeyhd",
+                "provider": "embeddedWallet", "publicKey": "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"
+            }),
+            json!({
+                "id": "wallet-4", "userId": "user-1", "chain": "FLOW", "name": "blocto",
+                "provider": "embeddedWallet", "publicKey": "0xf10w"
+            }),
+            json!({
+                "id": "wallet-5", "userId": "user-2", "chain": "EVM", "name": "coinbase",
+                "provider": "browserExtension", "publicKey": "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359"
+            }),
+        ];
+        api.state().wallets.extend(wallets.map(|wallet| from_value(wallet).unwrap()));
+        api
+    }
+
+    #[tokio::test]
+    async fn list_applies_every_filter_before_the_limit() {
+        let api = api();
+        let list = ListWalletsCommand::new(api.clone());
+        let cases: [(&[&str], &[&str]); 8] = [
+            (&["list"], &["wallet-1", "wallet-2", "wallet-3", "wallet-4", "wallet-5"]),
+            (&["list", "--user-id", "user-2"], &["wallet-2", "wallet-5"]),
+            (&["list", "--chain", "evm"], &["wallet-1", "wallet-3", "wallet-5"]),
+            // --limit counts wallets that pass the filters
+            (&["list", "--chain", "evm", "--limit", "2"], &["wallet-1", "wallet-3"]),
+            (&["list", "--provider", "embeddedwallet"], &["wallet-2", "wallet-3", "wallet-4"]),
+            // Wallet names match --provider too
+            (&["list", "--provider", "MetaMask"], &["wallet-1"]),
+            (&["list", "--chain", "evm", "--provider", "embeddedWallet", "--user-id", "user-1"], &["wallet-3"]),
+            (&["list", "--address", "0x5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED"], &["wallet-1", "wallet-3"]),
+        ];
+        for (args, expected) in cases {
+            assert_eq!(column(&json(&list, args).await, "id"), expected, "{:?}", args);
+        }
+        assert!(execute(&list, &["list", "--address", "0xnope"]).await.is_err());
+
+        // A user's wallets come from their own endpoint; other filters page through every wallet
+        assert_eq!(api.calls()[..3], ["list_wallets", "list_user_wallets user-2", "list_wallets"]);
+    }
+
+    #[tokio::test]
+    async fn addresses_are_grouped_by_chain_family() {
+        let api = api();
+        let wallet = json(&GetWalletCommand::new(api.clone()), &["get", "wallet-2"]).await;
+        assert_eq!((&wallet["chain"], &wallet["provider"]), (&json!("SOL"), &json!("embeddedWallet")));
+
+        // Each address is listed once whatever its letter case
+        let addresses = ExportAddressesCommand::new(api.clone());
+        assert_eq!(
+            json(&addresses, &["addresses"]).await,
+            json!({
+                "evm": ["0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed", "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359"],
+                "flow": ["0xf10w"],
+                "solana": ["TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"],
+            })
+        );
+        assert_eq!(
+            json(&addresses, &["addresses", "--provider", "embeddedWallet", "--chain", "evm"]).await,
+            json!({"evm": ["0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"]})
+        );
+    }
+
+    #[tokio::test]
+    async fn validate_runs_without_the_api() {
+        let command = ValidateAddressesCommand;
+        let checked = json(&command, &["validate", "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"]).await;
+        assert_eq!(column(&checked, "normalized"), ["0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"]);
+
+        // Invalid addresses are still reported before the command fails
+        let (checked, error) = fake_api::report(&command, &["validate", "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"]).await;
+        assert_eq!(column(&checked, "status"), ["invalid"]);
+        assert!(checked[0]["error"].is_string());
+        assert_eq!(error.to_string(), "1 of 1 addresses are invalid");
+
+        let file = test_support::temp_dir("validate").join("addresses.csv");
+        fs::write(&file, "chain,address\n# treasury\nsol,TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA\n").unwrap();
+        let checked = json(&command, &["validate", file.to_str().unwrap()]).await;
+        assert_eq!(column(&checked, "status"), ["valid"]);
+        assert_eq!(checked[0]["line"], 3);
+        fs::remove_dir_all(file.parent().unwrap()).unwrap();
+    }
+}
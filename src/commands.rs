@@ -1,15 +1,53 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use clap::{Arg, ArgMatches};
 use std::sync::Arc;
 use crate::config::Config;
 use crate::api;
+use crate::cache::{self, CachingAdminApi};
+use crate::command::args;
+use crate::completion::RecentIds;
+use crate::context::{self, CommandOutput, ExecutionContext};
+use crate::shell;
+
+// Name the CLI is installed under, used in completions and man pages
+pub const BIN_NAME: &str = "dynamic-admin";
 
 // Command trait - the core of our Command pattern
 #[async_trait]
 pub trait Command: Send + Sync {
     fn name(&self) -> &str;
     fn description(&self) -> &str;
-    async fn execute(&self) -> Result<()>;
+    // Arguments the command accepts on the command line
+    fn args(&self) -> Vec<Arg> {
+        Vec::new()
+    }
+    // Local tools that run without an API token or environment set up
+    fn requires_config(&self) -> bool {
+        true
+    }
+    // Do the work, returning the result for the caller to render
+    async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>>;
+}
+
+// Run a command and render its result
+pub async fn run(command: &dyn Command, ctx: &mut ExecutionContext<'_>) -> Result<()> {
+    ctx.definitions = command.args();
+    let _interrupts = context::cancel_on_interrupt(ctx.cancel.clone());
+    let output = command.execute(ctx).await?;
+    ctx.render(output).await
+}
+
+// Build the clap definition for a single command
+pub fn command_cli(command: &dyn Command) -> clap::Command {
+    clap::Command::new(command.name().to_string())
+        .about(command.description().to_string())
+        .args(command.args())
+}
+
+// Matches with no arguments supplied, used when running a command from the menu
+pub fn empty_matches(command: &dyn Command) -> ArgMatches {
+    command_cli(command).get_matches_from([command.name()])
 }
 
 // Command Registry - stores commands and categories
@@ -40,11 +78,71 @@ impl CommandRegistry {
     pub fn add_category(&mut self, category: CommandCategory) {
         self.categories.push(category);
     }
+
+    // Build the clap command tree for this registry and everything below it
+    pub fn cli(&self, name: &str, about: &str) -> clap::Command {
+        let mut cli = clap::Command::new(name.to_string()).about(about.to_string());
+
+        for category in &self.categories {
+            cli = cli.subcommand(
+                category
+                    .registry
+                    .cli(&category.name, &category.description)
+                    .subcommand_required(true)
+                    .arg_required_else_help(true),
+            );
+        }
+
+        for command in &self.commands {
+            cli = cli.subcommand(command_cli(command.as_ref()));
+        }
+
+        cli
+    }
+
+    // The whole command line: every command plus the global flags
+    pub fn root_cli(&self) -> clap::Command {
+        self.cli(BIN_NAME, "DynamicSDK Admin Operations CLI")
+            .args(args::global_args())
+            .subcommand_required(true)
+    }
+
+    // Resolve parsed matches to the leaf command and its own matches
+    pub fn find_command<'a>(&'a self, matches: &'a ArgMatches) -> Option<(&'a dyn Command, &'a ArgMatches)> {
+        let (name, sub_matches) = matches.subcommand()?;
+
+        if let Some(category) = self.categories.iter().find(|c| c.name == name) {
+            return category.registry.find_command(sub_matches);
+        }
+
+        self.commands
+            .iter()
+            .find(|c| c.name() == name)
+            .map(|c| (c.as_ref(), sub_matches))
+    }
+}
+
+// The command line built from default settings, with the shell that runs its commands. The
+// command tree doesn't depend on configuration, so this serves for completions and man pages.
+pub fn default_cli() -> clap::Command {
+    default_registry().root_cli().subcommand(shell::cli())
+}
+
+// Commands built from default settings, for parsing and help
+pub fn default_registry() -> CommandRegistry {
+    // The default token is empty, which always makes a valid header
+    create_command_registry(Config::default(), api::ClientOptions::default()).expect("default settings are valid")
 }
 
 // Create and populate command registry with all commands
-pub fn create_command_registry(config: Config) -> CommandRegistry {
-    let api_client = Arc::new(api::DynamicApiClient::new(config.api_token, config.base_url));
+pub fn create_command_registry(config: Config, options: api::ClientOptions) -> Result<CommandRegistry> {
+    let recent_ids = config.state_dir().map(RecentIds::path);
+    let client: Arc<dyn api::AdminApi> = Arc::new(api::DynamicApiClient::new(config.api_token, config.base_url, options)?);
+    let mut cache = CachingAdminApi::new(client, cache::DEFAULT_TTL);
+    if let Some(path) = recent_ids {
+        cache = cache.remember_ids(path);
+    }
+    let api_client: Arc<dyn api::AdminApi> = Arc::new(cache);
     
     let mut registry = CommandRegistry::new();
     
@@ -52,6 +150,21 @@ pub fn create_command_registry(config: Config) -> CommandRegistry {
     let mut org_registry = CommandRegistry::new();
     org_registry.add_command(Box::new(crate::command::organizations::ListOrganizationsCommand::new(api_client.clone())));
     org_registry.add_command(Box::new(crate::command::organizations::GetOrganizationCommand::new(api_client.clone())));
+    org_registry.add_command(Box::new(crate::command::organizations::CreateOrganizationCommand::new(api_client.clone())));
+    org_registry.add_command(Box::new(crate::command::organizations::UpdateOrganizationCommand::new(api_client.clone())));
+    org_registry.add_command(Box::new(crate::command::organizations::DeleteOrganizationCommand::new(api_client.clone())));
+
+    let mut members_registry = CommandRegistry::new();
+    members_registry.add_command(Box::new(crate::command::organizations::ListMembersCommand::new(api_client.clone())));
+    members_registry.add_command(Box::new(crate::command::organizations::AddMemberCommand::new(api_client.clone())));
+    members_registry.add_command(Box::new(crate::command::organizations::SetMemberRoleCommand::new(api_client.clone())));
+    members_registry.add_command(Box::new(crate::command::organizations::RemoveMemberCommand::new(api_client.clone())));
+
+    org_registry.add_category(CommandCategory {
+        name: "members".to_string(),
+        description: "Organization members and their roles".to_string(),
+        registry: members_registry,
+    });
     
     registry.add_category(CommandCategory {
         name: "organizations".to_string(),
@@ -63,7 +176,9 @@ pub fn create_command_registry(config: Config) -> CommandRegistry {
     let mut exports_registry = CommandRegistry::new();
     exports_registry.add_command(Box::new(crate::command::exports::ListExportsCommand::new(api_client.clone())));
     exports_registry.add_command(Box::new(crate::command::exports::GetExportCommand::new(api_client.clone())));
-    exports_registry.add_command(Box::new(crate::command::exports::CreateExportCommand::new(api_client.clone())));
+    exports_registry.add_command(Box::new(crate::command::exports::CreateExportCommand::new(api_client.clone(), config.path.clone())));
+    exports_registry.add_command(Box::new(crate::command::exports::DownloadExportCommand::new(api_client.clone())));
+    exports_registry.add_command(Box::new(crate::command::exports::ListExportTemplatesCommand::new(config.path.clone())));
     
     registry.add_category(CommandCategory {
         name: "exports".to_string(),
@@ -75,6 +190,12 @@ pub fn create_command_registry(config: Config) -> CommandRegistry {
     let mut users_registry = CommandRegistry::new();
     users_registry.add_command(Box::new(crate::command::users::ListUsersCommand::new(api_client.clone())));
     users_registry.add_command(Box::new(crate::command::users::GetUserCommand::new(api_client.clone())));
+    users_registry.add_command(Box::new(crate::command::users::CreateUserCommand::new(api_client.clone())));
+    users_registry.add_command(Box::new(crate::command::users::UpdateUserCommand::new(api_client.clone())));
+    users_registry.add_command(Box::new(crate::command::users::DeleteUserCommand::new(api_client.clone())));
+    users_registry.add_command(Box::new(crate::command::users::BlockUserCommand::new(api_client.clone())));
+    users_registry.add_command(Box::new(crate::command::users::UnblockUserCommand::new(api_client.clone())));
+    users_registry.add_command(Box::new(crate::command::users::ImportUsersCommand::new(api_client.clone())));
     
     registry.add_category(CommandCategory {
         name: "users".to_string(),
@@ -82,6 +203,54 @@ pub fn create_command_registry(config: Config) -> CommandRegistry {
         registry: users_registry,
     });
 
-    registry
+    // Wallets category
+    let mut wallets_registry = CommandRegistry::new();
+    wallets_registry.add_command(Box::new(crate::command::wallets::ListWalletsCommand::new(api_client.clone())));
+    wallets_registry.add_command(Box::new(crate::command::wallets::GetWalletCommand::new(api_client.clone())));
+    wallets_registry.add_command(Box::new(crate::command::wallets::ExportAddressesCommand::new(api_client.clone())));
+    wallets_registry.add_command(Box::new(crate::command::wallets::ValidateAddressesCommand::new()));
+
+    registry.add_category(CommandCategory {
+        name: "wallets".to_string(),
+        description: "Wallet related commands".to_string(),
+        registry: wallets_registry,
+    });
+
+    // Environment context
+    let mut env_registry = CommandRegistry::new();
+    env_registry.add_command(Box::new(crate::command::environments::UseEnvironmentCommand::new()));
+    env_registry.add_command(Box::new(crate::command::environments::CurrentEnvironmentCommand::new()));
+    env_registry.add_command(Box::new(crate::command::environments::ListEnvironmentsCommand::new()));
+
+    registry.add_category(CommandCategory {
+        name: "env".to_string(),
+        description: "Active environment and aliases".to_string(),
+        registry: env_registry,
+    });
+
+    // Config profiles
+    let mut profile_registry = CommandRegistry::new();
+    profile_registry.add_command(Box::new(crate::command::profiles::ListProfilesCommand::new(config.path.clone())));
+    profile_registry.add_command(Box::new(crate::command::profiles::ShowProfileCommand::new(config.path.clone())));
+    profile_registry.add_command(Box::new(crate::command::profiles::AddProfileCommand::new(config.path.clone())));
+    profile_registry.add_command(Box::new(crate::command::profiles::RemoveProfileCommand::new(config.path.clone())));
+    profile_registry.add_command(Box::new(crate::command::profiles::UseProfileCommand::new(config.path.clone())));
+    profile_registry.add_command(Box::new(crate::command::profiles::SetTokenCommand::new(config.path.clone())));
+
+    registry.add_category(CommandCategory {
+        name: "profile".to_string(),
+        description: "Config profiles for multiple projects or accounts".to_string(),
+        registry: profile_registry,
+    });
+
+    // Endpoints the commands above don't wrap
+    registry.add_command(Box::new(crate::command::api::ApiRequestCommand::new(api_client.clone())));
+
+    // Local tools
+    registry.add_command(Box::new(crate::command::mock_server::MockServerCommand::new()));
+    registry.add_command(Box::new(crate::command::completions::CompletionsCommand::new()));
+    registry.add_command(Box::new(crate::command::completions::ManpagesCommand::new()));
+
+    Ok(registry)
 }
 
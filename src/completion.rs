@@ -0,0 +1,183 @@
+use anyhow::Result;
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{self, Overrides};
+use crate::credentials;
+use crate::environment::Environments;
+
+// Values offered while completing a command line in the shell. The shell script from
+// `completions` calls back into the binary with DYNAMIC_ADMIN_COMPLETE set, so environment aliases
+// and IDs are looked up each time rather than baked into the script.
+
+// Set by the completion script to ask for completions instead of running a command
+pub const COMPLETE_ENV: &str = "DYNAMIC_ADMIN_COMPLETE";
+
+// Most recent IDs kept of each kind
+const MAX_RECENT_IDS: usize = 200;
+
+// An ID and what to show next to it, e.g. the user's email
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentId {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub label: String,
+}
+
+// Users and organizations the CLI has seen lately, most recent first
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RecentIds {
+    #[serde(default)]
+    pub users: Vec<RecentId>,
+    #[serde(default)]
+    pub organizations: Vec<RecentId>,
+}
+
+impl RecentIds {
+    // Kept next to config.toml, shared by every profile
+    pub fn path(state_dir: &Path) -> PathBuf {
+        state_dir.join("recent-ids.json")
+    }
+
+    // Missing or unreadable files are treated as empty; they only feed completions
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    // Labels are emails, phone numbers and names, so only the current user may read the file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        credentials::write_private(path, serde_json::to_string_pretty(self)?.as_bytes())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty() && self.organizations.is_empty()
+    }
+
+    // Put `id` first, replacing any older entry for it
+    pub fn add(ids: &mut Vec<RecentId>, id: &str, label: Option<&str>) {
+        ids.retain(|recent| recent.id != id);
+        ids.insert(0, RecentId {
+            id: id.to_string(),
+            label: label.unwrap_or_default().to_string(),
+        });
+        ids.truncate(MAX_RECENT_IDS);
+    }
+
+    pub fn remove(ids: &mut Vec<RecentId>, id: &str) {
+        ids.retain(|recent| recent.id != id);
+    }
+
+    // Layer `newer` over these, dropping what it saw deleted
+    pub fn merge(&mut self, newer: &RecentIds, deleted: &RecentIds) {
+        for (ids, newer, deleted) in [
+            (&mut self.users, &newer.users, &deleted.users),
+            (&mut self.organizations, &newer.organizations, &deleted.organizations),
+        ] {
+            for recent in newer.iter().rev() {
+                RecentIds::add(ids, &recent.id, Some(&recent.label));
+            }
+            for recent in deleted {
+                RecentIds::remove(ids, &recent.id);
+            }
+        }
+    }
+}
+
+// Completes the IDs behind an --<id> flag or positional, for the kinds that are remembered
+pub fn id_candidates(id: &str) -> Option<ArgValueCandidates> {
+    match id {
+        "user-id" => Some(ArgValueCandidates::new(|| recent_candidates(|ids| ids.users))),
+        "organization-id" => Some(ArgValueCandidates::new(|| recent_candidates(|ids| ids.organizations))),
+        _ => None,
+    }
+}
+
+// Completes environment aliases of the profile in use, with the ID they stand for
+pub fn environment_candidates() -> ArgValueCandidates {
+    ArgValueCandidates::new(|| environment_aliases().unwrap_or_default())
+}
+
+fn recent_candidates(kind: fn(RecentIds) -> Vec<RecentId>) -> Vec<CompletionCandidate> {
+    let Ok(state_dir) = config::config_location() else {
+        return Vec::new();
+    };
+    kind(RecentIds::load(&RecentIds::path(&state_dir)))
+        .into_iter()
+        .map(|recent| {
+            let help = (!recent.label.is_empty()).then(|| recent.label.into());
+            CompletionCandidate::new(recent.id).help(help)
+        })
+        .collect()
+}
+
+// Completing runs on every Tab, so this only reads: config.toml isn't migrated and no state is
+// created or moved
+fn environment_aliases() -> Result<Vec<CompletionCandidate>> {
+    // The command line isn't parsed yet, so --profile isn't known; use the environment and config
+    let overrides = Overrides::from_env();
+    let profile = match overrides.profile {
+        Some(profile) => profile,
+        None => {
+            let path = match overrides.config_path {
+                Some(path) => path,
+                None => config::config_location()?.join("config.toml"),
+            };
+            config::parse_config_file(&path)?.default_profile_name()
+        }
+    };
+
+    let state = Environments::read_only(&config::config_location()?, &profile, String::new()).load_state()?;
+    Ok(state
+        .aliases
+        .into_iter()
+        .map(|(alias, id)| CompletionCandidate::new(alias).help(Some(id.into())))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_ids_keep_the_latest_first() {
+        let mut recent = RecentIds::default();
+        RecentIds::add(&mut recent.users, "user-1", Some("ada@example.com"));
+        RecentIds::add(&mut recent.users, "user-2", None);
+        RecentIds::add(&mut recent.users, "user-1", Some("ada@example.com"));
+        assert_eq!(recent.users.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), ["user-1", "user-2"]);
+
+        for i in 0..MAX_RECENT_IDS {
+            RecentIds::add(&mut recent.organizations, &format!("org-{}", i), None);
+        }
+        let mut newer = RecentIds::default();
+        RecentIds::add(&mut newer.organizations, "org-new", Some("Initech"));
+        let mut deleted = RecentIds::default();
+        RecentIds::add(&mut deleted.users, "user-2", None);
+
+        recent.merge(&newer, &deleted);
+        assert_eq!(recent.organizations.len(), MAX_RECENT_IDS);
+        assert_eq!(recent.organizations[0].label, "Initech");
+        assert_eq!(recent.users.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn recent_ids_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = crate::test_support::temp_dir("recent-ids");
+        let path = dir.join("recent-ids.json");
+        let mut recent = RecentIds::default();
+        RecentIds::add(&mut recent.users, "user-1", Some("ada@example.com"));
+        recent.save(&path).unwrap();
+
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(RecentIds::load(&path).users, recent.users);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,113 +1,579 @@
 use anyhow::Result;
-use config::{Config as ConfigFile, File};
-use dialoguer::{theme::ColorfulTheme, Input};
-use serde::Deserialize;
-use std::path::PathBuf;
-use std::io::Write;
+use clap::ArgMatches;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
 use std::fs;
 
-#[derive(Debug, Deserialize)]
+use crate::credentials::{self, TokenStorage};
+use crate::prompt::{Prompter, Question};
+use crate::retry::RetryPolicy;
+
+// Profile used when none is selected and the config doesn't name a default
+pub const DEFAULT_PROFILE: &str = "default";
+// Environment variable selecting the profile, overridden by --profile
+pub const PROFILE_ENV: &str = "DYNAMIC_ADMIN_PROFILE";
+// Environment variables overriding the selected profile's settings
+pub const TOKEN_ENV: &str = "DYNAMIC_API_TOKEN";
+pub const BASE_URL_ENV: &str = "DYNAMIC_BASE_URL";
+pub const ENVIRONMENT_ID_ENV: &str = "DYNAMIC_ENVIRONMENT_ID";
+const DEFAULT_BASE_URL: &str = "https://app.dynamicauth.com";
+
+// Settings for the selected profile, as used by the rest of the CLI
+#[derive(Debug)]
 pub struct Config {
+    pub path: PathBuf,
+    pub profile: String,
     pub api_token: String,
     pub base_url: String,
     pub default_environment_id: String,
+    pub retry: RetryPolicy,
+}
+
+impl Config {
+    // Where state is saved, or None when running purely from environment variables and flags,
+    // which leaves nothing on disk
+    pub fn state_dir(&self) -> Option<&Path> {
+        self.path.exists().then(|| state_dir(&self.path))
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
+            path: PathBuf::new(),
+            profile: DEFAULT_PROFILE.to_string(),
+            api_token: String::new(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            default_environment_id: String::new(),
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+// One Dynamic project: its token, API URL and default environment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    // Only set for plaintext storage
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub api_token: String,
+    #[serde(default, skip_serializing_if = "TokenStorage::is_plaintext")]
+    pub token_storage: TokenStorage,
+    // Command printing the token, e.g. from a password manager; takes precedence over token_storage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_command: Option<String>,
+    #[serde(default = "default_base_url")]
+    pub base_url: String,
+    pub default_environment_id: String,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile {
             api_token: String::new(),
-            base_url: "https://app.dynamicauth.com".to_string(),
+            token_storage: TokenStorage::default(),
+            token_command: None,
+            base_url: default_base_url(),
             default_environment_id: String::new(),
         }
     }
 }
 
-pub fn load_config() -> Result<Config> {
-    let config_path = get_config_path()?;
-    
-    // If config doesn't exist, create it
-    if !config_path.exists() {
-        create_initial_config(&config_path)?;
+pub fn default_base_url() -> String {
+    DEFAULT_BASE_URL.to_string()
+}
+
+// Saved parameters for `exports create --template <NAME>`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportTemplate {
+    #[serde(rename = "type")]
+    pub export_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_after: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_before: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub filters: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<String>,
+}
+
+// On-disk layout of config.toml
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ConfigFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub export_templates: BTreeMap<String, ExportTemplate>,
+
+    // Flat single-account layout from before profiles, migrated into the default profile
+    #[serde(default, skip_serializing)]
+    api_token: Option<String>,
+    #[serde(default, skip_serializing)]
+    base_url: Option<String>,
+    #[serde(default, skip_serializing)]
+    default_environment_id: Option<String>,
+}
+
+impl ConfigFile {
+    // Profile used when neither --profile nor DYNAMIC_ADMIN_PROFILE is set
+    pub fn default_profile_name(&self) -> String {
+        if let Some(name) = &self.default_profile {
+            return name.clone();
+        }
+
+        // A single profile is the default whatever it's called
+        match self.profiles.keys().next() {
+            Some(only) if self.profiles.len() == 1 => only.clone(),
+            _ => DEFAULT_PROFILE.to_string(),
+        }
+    }
+
+    // Move a flat config into the default profile; returns whether anything changed
+    fn migrate(&mut self) -> bool {
+        let Some(api_token) = self.api_token.take() else {
+            return false;
+        };
+
+        let profile = Profile {
+            api_token,
+            base_url: self.base_url.take().unwrap_or_else(default_base_url),
+            default_environment_id: self.default_environment_id.take().unwrap_or_default(),
+            ..Profile::default()
+        };
+        self.profiles.entry(DEFAULT_PROFILE.to_string()).or_insert(profile);
+        self.default_profile.get_or_insert_with(|| DEFAULT_PROFILE.to_string());
+        true
     }
-    
+}
+
+// Settings layered over config.toml: environment variables, then command-line flags
+#[derive(Debug, Default)]
+pub struct Overrides {
+    pub config_path: Option<PathBuf>,
+    pub profile: Option<String>,
+    pub api_token: Option<String>,
+    pub base_url: Option<String>,
+    pub environment_id: Option<String>,
+}
+
+impl Overrides {
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| env::var(name).ok())
+    }
+
+    fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |name: &str| lookup(name).filter(|value| !value.is_empty());
+        Overrides {
+            config_path: None,
+            profile: var(PROFILE_ENV),
+            api_token: var(TOKEN_ENV),
+            base_url: var(BASE_URL_ENV),
+            environment_id: var(ENVIRONMENT_ID_ENV),
+        }
+    }
+
+    // Command-line flags take precedence over the environment variables
+    pub fn apply_flags(&mut self, matches: &ArgMatches) {
+        let flag = |id: &str| matches.get_one::<String>(id).cloned();
+        if let Some(config_path) = flag("config") {
+            self.config_path = Some(config_path.into());
+        }
+        self.profile = flag("profile").or(self.profile.take());
+        self.api_token = flag("token").or(self.api_token.take());
+        self.base_url = flag("base-url").or(self.base_url.take());
+        // A literal ID in --env can stand in for the default environment; aliases need a profile
+        self.environment_id = flag("env").filter(|env| is_uuid(env)).or(self.environment_id.take());
+    }
+
+    // Enough to run without any config file
+    fn is_complete(&self) -> bool {
+        self.api_token.is_some() && self.environment_id.is_some()
+    }
+}
+
+pub fn load_config(overrides: &Overrides, prompter: &dyn Prompter) -> Result<Config> {
+    let config_path = match &overrides.config_path {
+        Some(path) => path.clone(),
+        None => config_path()?,
+    };
+    load_config_from(config_path, overrides, prompter)
+}
+
+fn load_config_from(config_path: PathBuf, overrides: &Overrides, prompter: &dyn Prompter) -> Result<Config> {
+    // If config doesn't exist, create it, unless the overrides cover everything we need
+    if !config_path.exists() && !overrides.is_complete() {
+        if overrides.config_path.is_some() {
+            return Err(anyhow::anyhow!("Config file {:?} not found", config_path));
+        }
+        if !prompter.is_interactive() {
+            return Err(anyhow::anyhow!(
+                "No configuration found at {:?}. Set {} and {} (or pass --token and --env) when running non-interactively",
+                config_path,
+                TOKEN_ENV,
+                ENVIRONMENT_ID_ENV
+            ));
+        }
+        create_initial_config(&config_path, overrides.profile.as_deref().unwrap_or(DEFAULT_PROFILE), prompter)?;
+    }
+
     // Load config
-    let config = ConfigFile::builder()
-        .add_source(File::from(config_path))
-        .build()?;
-        
-    let config: Config = config.try_deserialize()?;
-    
+    credentials::warn_if_exposed(&config_path);
+    let file = read_config_file(&config_path)?;
+    let name = overrides.profile.clone().unwrap_or_else(|| file.default_profile_name());
+
+    let mut selected = match file.profiles.get(&name) {
+        Some(profile) => profile.clone(),
+        // Running purely from overrides, e.g. in a container
+        None if overrides.profile.is_none() && overrides.is_complete() => Profile::default(),
+        None => {
+            let available: Vec<&str> = file.profiles.keys().map(String::as_str).collect();
+            return Err(anyhow::anyhow!(
+                "Profile '{}' not found (available: {}). Add it with `profile add {}`",
+                name,
+                if available.is_empty() { "none".to_string() } else { available.join(", ") },
+                name
+            ));
+        }
+    };
+
+    selected.api_token = match &overrides.api_token {
+        Some(api_token) => api_token.clone(),
+        None => credentials::load_token(&config_path, &name, &selected, prompter)?,
+    };
+    if let Some(base_url) = &overrides.base_url {
+        selected.base_url = base_url.clone();
+    }
+    if let Some(environment_id) = &overrides.environment_id {
+        selected.default_environment_id = environment_id.clone();
+    }
+
     // Validate config
-    if config.api_token.is_empty() {
-        return Err(anyhow::anyhow!("API token is missing in configuration"));
+    if selected.api_token.is_empty() {
+        return Err(anyhow::anyhow!("API token is missing in profile '{}' (set {} or pass --token)", name, TOKEN_ENV));
+    }
+
+    if selected.default_environment_id.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Default environment ID is missing in profile '{}' (set {} or pass --env)",
+            name,
+            ENVIRONMENT_ID_ENV
+        ));
+    }
+
+    Ok(Config {
+        path: config_path,
+        api_token: selected.api_token,
+        base_url: selected.base_url,
+        default_environment_id: selected.default_environment_id,
+        retry: file.retry.clone().unwrap_or_default(),
+        profile: name,
+    })
+}
+
+// Read config.toml as it is, without migrating anything
+pub fn parse_config_file(path: &Path) -> Result<ConfigFile> {
+    if !path.exists() {
+        return Ok(ConfigFile::default());
     }
-    
-    if config.default_environment_id.is_empty() {
-        return Err(anyhow::anyhow!("Default environment ID is missing in configuration"));
+
+    let content = fs::read_to_string(path)?;
+    toml::from_str(&content).map_err(|e| anyhow::anyhow!("Invalid configuration in {:?}: {}", path, e))
+}
+
+// Read config.toml, migrating the pre-profile layout in place
+pub fn read_config_file(path: &Path) -> Result<ConfigFile> {
+    let mut file = parse_config_file(path)?;
+
+    if file.migrate() {
+        write_config_file(path, &file)?;
+        eprintln!("Migrated {:?} to the profiles layout (as profile '{}')", path, DEFAULT_PROFILE);
     }
-    
-    Ok(config)
+
+    Ok(file)
+}
+
+// Write config.toml readable only by the current user, since it may hold a token
+pub fn write_config_file(path: &Path, file: &ConfigFile) -> Result<()> {
+    credentials::write_private(path, toml::to_string_pretty(file)?.as_bytes())
 }
 
-fn get_config_path() -> Result<PathBuf> {
+// Directory holding config.toml and other persisted CLI state, which may not exist yet
+pub fn config_location() -> Result<PathBuf> {
     let mut path = dirs::config_dir()
         .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))?;
-    
     path.push("dynamic-admin-ops");
-    
-    // Create directory if it doesn't exist
-    if !path.exists() {
-        fs::create_dir_all(&path)?;
-    }
-    
+    Ok(path)
+}
+
+pub fn config_path() -> Result<PathBuf> {
+    let mut path = config_location()?;
     path.push("config.toml");
     Ok(path)
 }
 
-fn create_initial_config(config_path: &PathBuf) -> Result<()> {
+// Directory for the state kept next to a config file: environments, encrypted tokens and history
+pub fn state_dir(config_path: &Path) -> &Path {
+    config_path.parent().unwrap_or(Path::new(""))
+}
+
+// Simple UUID format validation
+pub fn is_uuid(input: &str) -> bool {
+    input.len() == 36 && input.chars().filter(|&c| c == '-').count() == 4
+}
+
+pub fn is_api_token(input: &str) -> bool {
+    input.starts_with("dyn_") && input.len() > 10
+}
+
+// Show enough of a token to recognise it without revealing it
+pub fn mask_token(token: &str) -> String {
+    let chars: Vec<char> = token.chars().collect();
+    if chars.len() <= 12 {
+        return "****".to_string();
+    }
+    let head: String = chars[..8].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}…{}", head, tail)
+}
+
+// Interactively ask for a profile's settings, storing the token in the chosen backend
+pub fn prompt_profile(prompter: &dyn Prompter, config_path: &Path, name: &str) -> Result<Profile> {
+    let api_token = prompter.password(
+        &Question::new("Enter your API token (starts with dyn_)")
+            .flag("token")
+            .validate(|input| if is_api_token(input) { Ok(()) } else { Err("Invalid API token format. It should start with 'dyn_'") }),
+    )?;
+
+    let storages = [TokenStorage::Keyring, TokenStorage::EncryptedFile, TokenStorage::Plaintext];
+    let selection = prompter.select(
+        &Question::new("Where should the token be stored?").flag("token-storage"),
+        &["OS keyring", "Passphrase-encrypted file", "Plaintext in config.toml"],
+    )?;
+    let token_storage = storages[selection];
+    credentials::store_token(config_path, name, token_storage, &api_token, prompter)?;
+
+    let base_url = prompter.text(&Question::new("Enter the base URL").flag("base-url").default(DEFAULT_BASE_URL))?;
+
+    let default_environment_id = prompter.text(
+        &Question::new("Enter your default environment ID (UUID format)")
+            .flag("environment-id")
+            .validate(|input| if is_uuid(input) { Ok(()) } else { Err("Invalid UUID format") }),
+    )?;
+
+    Ok(Profile {
+        api_token: if token_storage.is_plaintext() { api_token } else { String::new() },
+        token_storage,
+        base_url,
+        default_environment_id,
+        token_command: None,
+    })
+}
+
+fn create_initial_config(config_path: &Path, profile: &str, prompter: &dyn Prompter) -> Result<()> {
     println!("No configuration found. Let's set up your DynamicSDK Admin CLI.");
-    
-    let api_token: String = Input::with_theme(&ColorfulTheme::default())
-        .with_prompt("Enter your API token (starts with dyn_)")
-        .validate_with(|input: &String| -> Result<(), &str> {
-            if input.starts_with("dyn_") && input.len() > 10 {
-                Ok(())
-            } else {
-                Err("Invalid API token format. It should start with 'dyn_'")
-            }
-        })
-        .interact()?;
-    
-    let base_url: String = Input::with_theme(&ColorfulTheme::default())
-        .with_prompt("Enter the base URL")
-        .default("https://app.dynamicauth.com".to_string())
-        .interact()?;
-    
-    let default_environment_id: String = Input::with_theme(&ColorfulTheme::default())
-        .with_prompt("Enter your default environment ID (UUID format)")
-        .validate_with(|input: &String| -> Result<(), &str> {
-            // Simple UUID format validation
-            if input.len() == 36 && input.chars().filter(|&c| c == '-').count() == 4 {
-                Ok(())
-            } else {
-                Err("Invalid UUID format")
-            }
-        })
-        .interact()?;
-    
-    let config_content = format!(
-        "api_token = \"{}\"\n\
-         base_url = \"{}\"\n\
-         default_environment_id = \"{}\"\n",
-        api_token, base_url, default_environment_id
-    );
-    
-    // Create config file and write content
-    let mut file = fs::File::create(config_path)?;
-    file.write_all(config_content.as_bytes())?;
-    
+
+    let mut file = ConfigFile {
+        default_profile: Some(profile.to_string()),
+        ..ConfigFile::default()
+    };
+    file.profiles.insert(profile.to_string(), prompt_profile(prompter, config_path, profile)?);
+    write_config_file(config_path, &file)?;
+
     println!("Configuration saved to {:?}", config_path);
-    
+
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prompt::NonInteractivePrompter;
+
+    const ENVIRONMENT_ID: &str = "11111111-2222-3333-4444-555555555555";
+    const OTHER_ENVIRONMENT_ID: &str = "66666666-7777-8888-9999-000000000000";
+
+    #[test]
+    fn flat_configs_migrate_into_the_default_profile() {
+        let dir = crate::test_support::temp_dir("config-migrate");
+        let path = dir.join("config.toml");
+        let flat = format!(
+            "api_token = \"dyn_flat_token\"\nbase_url = \"https://example.test\"\ndefault_environment_id = \"{}\"\n",
+            ENVIRONMENT_ID
+        );
+        fs::write(&path, &flat).unwrap();
+
+        let file = read_config_file(&path).unwrap();
+        assert_eq!(file.default_profile_name(), DEFAULT_PROFILE);
+        let profile = &file.profiles[DEFAULT_PROFILE];
+        assert_eq!(profile.api_token, "dyn_flat_token");
+        assert_eq!(profile.base_url, "https://example.test");
+        assert_eq!(profile.default_environment_id, ENVIRONMENT_ID);
+
+        // The file on disk now has the profiles layout and nothing left at the top level
+        let migrated = fs::read_to_string(&path).unwrap();
+        assert_ne!(migrated, flat);
+        let reread: ConfigFile = toml::from_str(&migrated).unwrap();
+        assert!(reread.api_token.is_none() && reread.base_url.is_none() && reread.default_environment_id.is_none());
+        assert_eq!(reread.profiles[DEFAULT_PROFILE].api_token, "dyn_flat_token");
+        assert_eq!(reread.default_profile.as_deref(), Some(DEFAULT_PROFILE));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn flat_configs_without_a_base_url_use_the_default() {
+        let mut file: ConfigFile = toml::from_str("api_token = \"dyn_flat_token\"").unwrap();
+        assert!(file.migrate());
+        assert_eq!(file.profiles[DEFAULT_PROFILE].base_url, DEFAULT_BASE_URL);
+        assert!(!file.migrate());
+    }
+
+    #[test]
+    fn migrated_configs_are_left_untouched() {
+        let dir = crate::test_support::temp_dir("config-migrated");
+        let path = dir.join("config.toml");
+        let content = format!(
+            "# My accounts\ndefault_profile = \"staging\"\n\n[profiles.staging]\napi_token = \"dyn_staging_token\"\ndefault_environment_id = \"{}\"\n",
+            ENVIRONMENT_ID
+        );
+        fs::write(&path, &content).unwrap();
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+
+        let file = read_config_file(&path).unwrap();
+        assert_eq!(file.default_profile_name(), "staging");
+        assert_eq!(file.profiles["staging"].api_token, "dyn_staging_token");
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), modified);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn write_profiles(name: &str) -> PathBuf {
+        let dir = crate::test_support::temp_dir(name);
+        let mut file = ConfigFile::default();
+        file.profiles.insert(
+            DEFAULT_PROFILE.to_string(),
+            Profile {
+                api_token: "dyn_profile_token".to_string(),
+                default_environment_id: ENVIRONMENT_ID.to_string(),
+                ..Profile::default()
+            },
+        );
+        file.profiles.insert(
+            "other".to_string(),
+            Profile {
+                api_token: "dyn_other_token".to_string(),
+                base_url: "https://other.test".to_string(),
+                default_environment_id: OTHER_ENVIRONMENT_ID.to_string(),
+                ..Profile::default()
+            },
+        );
+        let path = dir.join("config.toml");
+        write_config_file(&path, &file).unwrap();
+        path
+    }
+
+    fn flags(args: &[&str]) -> ArgMatches {
+        clap::Command::new("dynamic-admin")
+            .args(crate::command::args::global_args())
+            .try_get_matches_from(std::iter::once("dynamic-admin").chain(args.iter().copied()))
+            .unwrap()
+    }
+
+    #[test]
+    fn flags_override_env_which_overrides_the_profile() {
+        let path = write_profiles("config-precedence");
+        let prompter = NonInteractivePrompter;
+        let load = |overrides: &Overrides| load_config(overrides, &prompter).unwrap();
+
+        // The profile, with defaults for what it leaves out
+        let mut overrides = Overrides::from_vars(|_| None);
+        overrides.config_path = Some(path.clone());
+        let config = load(&overrides);
+        assert_eq!(config.profile, DEFAULT_PROFILE);
+        assert_eq!(config.api_token, "dyn_profile_token");
+        assert_eq!(config.base_url, DEFAULT_BASE_URL);
+        assert_eq!(config.default_environment_id, ENVIRONMENT_ID);
+
+        // Environment variables, with empty ones ignored
+        let vars = BTreeMap::from([
+            (PROFILE_ENV, "other"),
+            (TOKEN_ENV, "dyn_env_token"),
+            (BASE_URL_ENV, "https://env.test"),
+            (ENVIRONMENT_ID_ENV, ""),
+        ]);
+        let mut overrides = Overrides::from_vars(|name| vars.get(name).map(|value| value.to_string()));
+        overrides.config_path = Some(path.clone());
+        let config = load(&overrides);
+        assert_eq!(config.profile, "other");
+        assert_eq!(config.api_token, "dyn_env_token");
+        assert_eq!(config.base_url, "https://env.test");
+        assert_eq!(config.default_environment_id, OTHER_ENVIRONMENT_ID);
+
+        // Flags; an alias in --env doesn't replace the default environment
+        overrides.apply_flags(&flags(&["--profile", "default", "--token", "dyn_flag_token", "--env", "staging"]));
+        let config = load(&overrides);
+        assert_eq!(config.profile, DEFAULT_PROFILE);
+        assert_eq!(config.api_token, "dyn_flag_token");
+        assert_eq!(config.base_url, "https://env.test");
+        assert_eq!(config.default_environment_id, ENVIRONMENT_ID);
+
+        overrides.apply_flags(&flags(&["--base-url", "https://flag.test", "--env", OTHER_ENVIRONMENT_ID]));
+        let config = load(&overrides);
+        assert_eq!(config.api_token, "dyn_flag_token");
+        assert_eq!(config.base_url, "https://flag.test");
+        assert_eq!(config.default_environment_id, OTHER_ENVIRONMENT_ID);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn unknown_profiles_list_the_available_ones() {
+        let path = write_profiles("config-unknown-profile");
+        let mut overrides = Overrides::from_vars(|_| None);
+        overrides.config_path = Some(path.clone());
+        overrides.apply_flags(&flags(&["--profile", "missing", "--token", "dyn_flag_token"]));
+
+        let error = load_config(&overrides, &NonInteractivePrompter).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Profile 'missing' not found (available: default, other). Add it with `profile add missing`"
+        );
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn missing_configs_are_not_created_non_interactively() {
+        let dir = crate::test_support::temp_dir("config-missing");
+        let path = dir.join("config.toml");
+
+        let error = load_config_from(path.clone(), &Overrides::default(), &NonInteractivePrompter).unwrap_err();
+        let message = error.to_string();
+        assert!(message.starts_with("No configuration found"), "{}", message);
+        assert!(message.contains(TOKEN_ENV) && message.contains(ENVIRONMENT_ID_ENV), "{}", message);
+        assert!(!path.exists());
+
+        // An explicit --config must exist
+        let overrides = Overrides { config_path: Some(path.clone()), ..Overrides::default() };
+        let error = load_config(&overrides, &NonInteractivePrompter).unwrap_err();
+        assert_eq!(error.to_string(), format!("Config file {:?} not found", path));
+
+        // Enough overrides run without any config
+        let overrides = Overrides {
+            api_token: Some("dyn_env_token".to_string()),
+            environment_id: Some(ENVIRONMENT_ID.to_string()),
+            ..Overrides::default()
+        };
+        let config = load_config_from(path.clone(), &overrides, &NonInteractivePrompter).unwrap();
+        assert_eq!(config.api_token, "dyn_env_token");
+        assert_eq!(config.base_url, DEFAULT_BASE_URL);
+        assert!(!path.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,279 @@
+use anyhow::Result;
+use clap::{Arg, ArgMatches};
+use colored::*;
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{self, Write};
+use std::process;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::command::args;
+use crate::environment::{EnvironmentSource, Environments};
+use crate::error::{self, Cancelled};
+use crate::output::Renderer;
+use crate::prompt::Prompter;
+
+// Everything a command gets from the invocation running it: its arguments, the profile and
+// environments it works against, how to ask questions, where output goes and whether the user
+// has asked it to stop. Commands write to the sinks here rather than the process's stdout and
+// stderr, so the same command runs from the command line, the menu or a test.
+pub struct ExecutionContext<'a> {
+    pub args: &'a ArgMatches,
+    // Definitions of the running command's arguments, set by `commands::run`
+    pub definitions: Vec<Arg>,
+    // Name of the active config profile
+    pub profile: String,
+    pub environments: Arc<Environments>,
+    pub renderer: Renderer,
+    pub prompter: Arc<dyn Prompter>,
+    // Rendered results
+    pub stdout: Box<dyn Write + Send + 'a>,
+    // Progress and status messages
+    pub stderr: Box<dyn Write + Send + 'a>,
+    // Report what would change instead of changing it
+    pub dry_run: bool,
+    // Cancelled by Ctrl-C; long-running commands check it to stop cleanly
+    pub cancel: CancellationToken,
+}
+
+impl<'a> ExecutionContext<'a> {
+    // Context for a command's matches, rendering to the process's stdout and stderr
+    pub fn new(args: &'a ArgMatches, profile: &str, environments: Arc<Environments>, prompter: Arc<dyn Prompter>) -> Self {
+        ExecutionContext {
+            args,
+            definitions: Vec::new(),
+            profile: profile.to_string(),
+            environments,
+            renderer: Renderer::from_matches(args),
+            prompter,
+            stdout: Box::new(io::stdout()),
+            stderr: Box::new(io::stderr()),
+            dry_run: args.try_get_one::<bool>("dry-run").ok().flatten().copied().unwrap_or(false),
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    // Send output somewhere other than the process's stdout and stderr
+    #[cfg(test)]
+    pub fn with_sinks(mut self, stdout: impl Write + Send + 'a, stderr: impl Write + Send + 'a) -> Self {
+        self.stdout = Box::new(stdout);
+        self.stderr = Box::new(stderr);
+        self
+    }
+
+    // The environment to work against and where it came from
+    pub fn resolve_environment(&self) -> Result<(String, EnvironmentSource)> {
+        args::resolve_environment(self.args, &self.environments)
+    }
+
+    pub fn environment_id(&self) -> Result<String> {
+        Ok(self.resolve_environment()?.0)
+    }
+
+    // Read an argument value, asking for it only when missing
+    pub fn value_or_prompt(&self, id: &str, prompt: &str) -> Result<String> {
+        args::value_or_prompt(self.args, &self.definitions, self.prompter.as_ref(), id, prompt)
+    }
+
+    // Read a value that must be one of `items`, asking with a selector when missing
+    pub fn value_or_select(&self, id: &str, prompt: &str, items: &[&str]) -> Result<String> {
+        args::value_or_select(self.args, &self.definitions, self.prompter.as_ref(), id, prompt, items)
+    }
+
+    // Ask before doing something destructive, unless --yes was given
+    pub fn confirm(&self, prompt: &str) -> Result<bool> {
+        args::confirm(self.args, self.prompter.as_ref(), prompt)
+    }
+
+    // In a dry run, say what would have been done and return true so the command stops there
+    pub fn skip_in_dry_run(&mut self, action: &str) -> Result<bool> {
+        if self.dry_run {
+            writeln!(self.stderr, "{} would {}", "Dry run:".yellow(), action)?;
+        }
+        Ok(self.dry_run)
+    }
+
+    // Fail with `Cancelled` once the user has pressed Ctrl-C, saying what stopped
+    pub fn check_cancelled(&self, message: &str) -> Result<()> {
+        if self.cancel.is_cancelled() {
+            return Err(Cancelled(message.to_string()).into());
+        }
+        Ok(())
+    }
+
+    // Render a command's result to stdout in the selected format
+    pub async fn render(&mut self, mut output: CommandOutput<'_>) -> Result<()> {
+        // A failing report is printed in full before the command fails
+        let mut failure = None;
+        while let CommandOutput::Failed { output: report, error } = output {
+            failure.get_or_insert(error);
+            output = *report;
+        }
+
+        match output {
+            CommandOutput::Empty | CommandOutput::Failed { .. } => {}
+            CommandOutput::Item(item) => self.renderer.render_item(&mut *self.stdout, &item)?,
+            CommandOutput::List { items, columns } => {
+                // Stopping early still closes the list, so what was printed stays well formed
+                let items = items.take_until(self.cancel.clone().cancelled_owned());
+                let count = self.renderer.render_stream(&mut *self.stdout, items, columns).await?;
+                self.stdout.flush()?;
+                self.check_cancelled(&format!("Cancelled after {} items", count))?;
+            }
+        }
+        self.stdout.flush()?;
+        failure.map_or(Ok(()), Err)
+    }
+}
+
+// Listens for Ctrl-C while a command runs, until dropped
+pub struct InterruptListener(JoinHandle<()>);
+
+impl Drop for InterruptListener {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+// Cancel `token` when the user presses Ctrl-C. A second Ctrl-C quits at once, for commands that
+// don't check the token.
+pub fn cancel_on_interrupt(token: CancellationToken) -> InterruptListener {
+    InterruptListener(tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        token.cancel();
+        eprintln!("\n{} press Ctrl-C again to quit now", "Stopping;".yellow());
+        if tokio::signal::ctrl_c().await.is_ok() {
+            process::exit(error::EXIT_CANCELLED);
+        }
+    }))
+}
+
+// What a command produced, rendered by whoever ran it
+pub enum CommandOutput<'a> {
+    // Nothing beyond the status messages already written
+    Empty,
+    // A single object, e.g. the result of a `get`
+    Item(Value),
+    // Objects rendered as they arrive, with the table columns shown by default
+    List {
+        items: BoxStream<'a, Result<Value>>,
+        columns: &'static [&'static str],
+    },
+    // Output that's still rendered when the command fails, e.g. a report of invalid rows
+    Failed {
+        output: Box<CommandOutput<'a>>,
+        error: anyhow::Error,
+    },
+}
+
+impl<'a> CommandOutput<'a> {
+    pub fn item<T: Serialize>(item: &T) -> Result<Self> {
+        Ok(CommandOutput::Item(serde_json::to_value(item)?))
+    }
+
+    pub fn list<S, T>(items: S, columns: &'static [&'static str]) -> Self
+    where
+        S: Stream<Item = Result<T>> + Send + 'a,
+        T: Serialize,
+    {
+        let items = items.map(|item| Ok(serde_json::to_value(item?)?)).boxed();
+        CommandOutput::List { items, columns }
+    }
+
+    // A report that decides how the command ends: rendered either way, then failing with
+    // `failure` if there is one
+    pub fn report(self, failure: Option<anyhow::Error>) -> Self {
+        match failure {
+            Some(error) => CommandOutput::Failed {
+                output: Box::new(self),
+                error,
+            },
+            None => self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{self, Command};
+    use crate::fake_api;
+    use crate::output::OutputFormat;
+    use crate::prompt::NonInteractivePrompter;
+    use async_trait::async_trait;
+    use serde_json::json;
+
+    // Reports progress and lists two people
+    struct ListPeople;
+
+    #[async_trait]
+    impl Command for ListPeople {
+        fn name(&self) -> &str {
+            "people"
+        }
+
+        fn description(&self) -> &str {
+            "List people"
+        }
+
+        async fn execute<'a>(&'a self, ctx: &mut ExecutionContext<'_>) -> Result<CommandOutput<'a>> {
+            writeln!(ctx.stderr, "Fetching people...")?;
+            let people = futures::stream::iter([Ok(json!({"name": "Ada"})), Ok(json!({"name": "Grace"}))]);
+            Ok(CommandOutput::list(people, &["name"]))
+        }
+    }
+
+    async fn run(cancel: bool) -> (Result<()>, String, String) {
+        let matches = commands::empty_matches(&ListPeople);
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let mut ctx = ExecutionContext::new(&matches, "default", fake_api::environments(), Arc::new(NonInteractivePrompter))
+            .with_sinks(&mut stdout, &mut stderr);
+        ctx.renderer = Renderer::new(OutputFormat::Jsonl, None);
+        if cancel {
+            ctx.cancel.cancel();
+        }
+        let result = commands::run(&ListPeople, &mut ctx).await;
+        drop(ctx);
+        (result, String::from_utf8(stdout).unwrap(), String::from_utf8(stderr).unwrap())
+    }
+
+    #[tokio::test]
+    async fn results_and_progress_go_to_the_sinks() {
+        let (result, stdout, stderr) = run(false).await;
+        result.unwrap();
+        assert_eq!(stdout, "{\"name\":\"Ada\"}\n{\"name\":\"Grace\"}\n");
+        assert_eq!(stderr, "Fetching people...\n");
+    }
+
+    #[tokio::test]
+    async fn failing_reports_are_rendered_before_the_error() {
+        let matches = commands::empty_matches(&ListPeople);
+        let mut stdout = Vec::new();
+        let mut ctx = ExecutionContext::new(&matches, "default", fake_api::environments(), Arc::new(NonInteractivePrompter))
+            .with_sinks(&mut stdout, io::sink());
+        ctx.renderer = Renderer::new(OutputFormat::Jsonl, None);
+
+        let report = CommandOutput::Item(json!({"invalid": 1}));
+        ctx.render(report.report(None)).await.unwrap();
+        let report = CommandOutput::Item(json!({"invalid": 2}));
+        let error = ctx.render(report.report(Some(anyhow::anyhow!("2 invalid")))).await.unwrap_err();
+        assert_eq!(error.to_string(), "2 invalid");
+        drop(ctx);
+        assert_eq!(String::from_utf8(stdout).unwrap(), "{\"invalid\":1}\n{\"invalid\":2}\n");
+    }
+
+    #[tokio::test]
+    async fn cancelling_stops_a_listing() {
+        let (result, stdout, _) = run(true).await;
+        let error = result.unwrap_err();
+        assert_eq!(error::exit_code(&error), error::EXIT_CANCELLED);
+        assert_eq!(error.to_string(), "Cancelled after 0 items");
+        assert!(stdout.is_empty());
+    }
+}
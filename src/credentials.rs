@@ -0,0 +1,296 @@
+use anyhow::Result;
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::config::{self, Profile};
+use crate::prompt::{Prompter, Question};
+
+// Keyring service name the tokens are stored under, one entry per profile
+const KEYRING_SERVICE: &str = "dynamic-admin-ops";
+// Passphrase for encrypted token files, so they can be used without a terminal
+pub const PASSPHRASE_ENV: &str = "DYNAMIC_TOKEN_PASSPHRASE";
+
+// Encrypted token file layout: magic, salt, nonce, then the ChaCha20-Poly1305 ciphertext
+const MAGIC: &[u8] = b"DYNTOK01";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+// Where a profile's API token is kept
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenStorage {
+    // api_token in config.toml
+    #[default]
+    Plaintext,
+    // The OS keyring (Secret Service on Linux and BSD, Keychain on macOS)
+    Keyring,
+    // A passphrase-encrypted file under tokens/ in the config directory
+    EncryptedFile,
+}
+
+impl TokenStorage {
+    pub fn is_plaintext(&self) -> bool {
+        *self == TokenStorage::Plaintext
+    }
+
+    // Name as written in config.toml and accepted by --token-storage
+    pub fn name(&self) -> &'static str {
+        match self {
+            TokenStorage::Plaintext => "plaintext",
+            TokenStorage::Keyring => "keyring",
+            TokenStorage::EncryptedFile => "encrypted-file",
+        }
+    }
+}
+
+// Read the API token for a profile from wherever it's stored
+pub fn load_token(config_path: &Path, profile_name: &str, profile: &Profile, prompter: &dyn Prompter) -> Result<String> {
+    if let Some(command) = &profile.token_command {
+        return run_token_command(command);
+    }
+
+    match profile.token_storage {
+        TokenStorage::Plaintext => Ok(profile.api_token.clone()),
+        TokenStorage::Keyring => keyring_entry(profile_name)?
+            .get_password()
+            .map_err(|e| anyhow::anyhow!("Could not read the token for profile '{}' from the keyring: {}", profile_name, e)),
+        TokenStorage::EncryptedFile => {
+            let path = token_file_path(config_path, profile_name);
+            let data = fs::read(&path)
+                .map_err(|e| anyhow::anyhow!("Could not read encrypted token {:?}: {}", path, e))?;
+            decrypt(&data, &passphrase(prompter, false)?)
+        }
+    }
+}
+
+// Save a token in the given backend. Plaintext tokens are written to config.toml by the caller.
+pub fn store_token(config_path: &Path, profile_name: &str, storage: TokenStorage, token: &str, prompter: &dyn Prompter) -> Result<()> {
+    match storage {
+        TokenStorage::Plaintext => Ok(()),
+        TokenStorage::Keyring => keyring_entry(profile_name)?
+            .set_password(token)
+            .map_err(|e| anyhow::anyhow!("Could not save the token to the keyring: {}", e)),
+        TokenStorage::EncryptedFile => {
+            let path = token_file_path(config_path, profile_name);
+            write_private(&path, &encrypt(token, &passphrase(prompter, true)?)?)
+        }
+    }
+}
+
+// Remove a stored token, e.g. when its profile is deleted or moved to another backend
+pub fn delete_token(config_path: &Path, profile_name: &str, storage: TokenStorage) -> Result<()> {
+    match storage {
+        TokenStorage::Plaintext => Ok(()),
+        TokenStorage::Keyring => match keyring_entry(profile_name)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(anyhow::anyhow!("Could not remove the token from the keyring: {}", e)),
+        },
+        TokenStorage::EncryptedFile => {
+            let path = token_file_path(config_path, profile_name);
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+// Write a file only the current user can read
+pub fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        // The mode only applies to new files; tighten ones that already existed
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        file.write_all(contents)?;
+    }
+
+    #[cfg(not(unix))]
+    fs::write(path, contents)?;
+
+    Ok(())
+}
+
+// Warn when a file that may hold a token can be read by other users
+pub fn warn_if_exposed(path: &Path) {
+    if let Some(warning) = exposure_warning(path) {
+        eprintln!("Warning: {}", warning);
+    }
+}
+
+fn exposure_warning(path: &Path) -> Option<String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = fs::metadata(path).ok()?.permissions().mode() & 0o777;
+        (mode & 0o077 != 0).then(|| {
+            format!("{:?} is readable by other users (mode {:o}); run `chmod 600` on it", path, mode)
+        })
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+fn keyring_entry(profile_name: &str) -> Result<keyring::Entry> {
+    // Without a native backend keyring falls back to an in-memory store, which would lose the token
+    if cfg!(windows) {
+        return Err(anyhow::anyhow!("Keyring storage isn't supported on Windows; use encrypted-file"));
+    }
+
+    keyring::Entry::new(KEYRING_SERVICE, profile_name)
+        .map_err(|e| anyhow::anyhow!("Keyring unavailable: {}", e))
+}
+
+// Encrypted tokens live next to the config file that refers to them
+fn token_file_path(config_path: &Path, profile_name: &str) -> PathBuf {
+    config::state_dir(config_path).join("tokens").join(format!("{}.enc", profile_name))
+}
+
+// Run the profile's token_command (e.g. `op read op://vault/dynamic/token`) and use its output
+fn run_token_command(command: &str) -> Result<String> {
+    #[cfg(unix)]
+    let output = process::Command::new("sh").arg("-c").arg(command).output();
+    #[cfg(not(unix))]
+    let output = process::Command::new("cmd").arg("/C").arg(command).output();
+
+    let output = output.map_err(|e| anyhow::anyhow!("Could not run token_command: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "token_command failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let token = String::from_utf8(output.stdout)?.trim().to_string();
+    if token.is_empty() {
+        return Err(anyhow::anyhow!("token_command printed no token"));
+    }
+    Ok(token)
+}
+
+// Passphrase for the encrypted token file, from the environment or a prompt
+fn passphrase(prompter: &dyn Prompter, confirm: bool) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+
+    if !prompter.is_interactive() {
+        return Err(anyhow::anyhow!("Set {} to unlock the encrypted token", PASSPHRASE_ENV));
+    }
+
+    let question = Question::new("Token passphrase");
+    prompter.password(&if confirm { question.with_confirmation() } else { question })
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("Could not derive key: {}", e))?;
+    Ok(key)
+}
+
+fn encrypt(token: &str, passphrase: &str) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?);
+    let ciphertext = cipher
+        .encrypt(&nonce, token.as_bytes())
+        .map_err(|_| anyhow::anyhow!("Could not encrypt token"))?;
+
+    Ok([MAGIC, &salt, &nonce, &ciphertext].concat())
+}
+
+fn decrypt(data: &[u8], passphrase: &str) -> Result<String> {
+    let header = MAGIC.len() + SALT_LEN + NONCE_LEN;
+    if data.len() <= header || !data.starts_with(MAGIC) {
+        return Err(anyhow::anyhow!("Not an encrypted token file"));
+    }
+
+    let salt = &data[MAGIC.len()..MAGIC.len() + SALT_LEN];
+    let nonce = Nonce::from_slice(&data[MAGIC.len() + SALT_LEN..header]);
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, salt)?);
+    let token = cipher
+        .decrypt(nonce, &data[header..])
+        .map_err(|_| anyhow::anyhow!("Wrong passphrase or corrupted token file"))?;
+    Ok(String::from_utf8(token)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypted_tokens_need_the_passphrase() {
+        let data = encrypt("dyn_secret_token", "correct horse").unwrap();
+        assert!(data.starts_with(MAGIC));
+        assert!(!String::from_utf8_lossy(&data).contains("dyn_secret_token"));
+        assert_eq!(decrypt(&data, "correct horse").unwrap(), "dyn_secret_token");
+
+        let error = decrypt(&data, "wrong horse").unwrap_err();
+        assert_eq!(error.to_string(), "Wrong passphrase or corrupted token file");
+        assert!(decrypt(b"dyn_plaintext_token", "correct horse").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_files_are_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = crate::test_support::temp_dir("credentials");
+        let path = dir.join("tokens").join("default.enc");
+        fs::write(dir.join("exposed"), "").unwrap();
+        fs::set_permissions(dir.join("exposed"), fs::Permissions::from_mode(0o644)).unwrap();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::copy(dir.join("exposed"), &path).unwrap();
+
+        // Existing files are tightened too
+        write_private(&path, b"token").unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(fs::read(&path).unwrap(), b"token");
+
+        assert_eq!(exposure_warning(&path), None);
+        assert!(exposure_warning(&dir.join("exposed")).unwrap().contains("mode 644"));
+        assert_eq!(exposure_warning(&dir.join("missing")), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn token_commands_must_print_a_token() {
+        assert_eq!(run_token_command("echo '  dyn_from_command  '").unwrap(), "dyn_from_command");
+
+        let error = run_token_command("echo locked >&2; exit 3").unwrap_err().to_string();
+        assert!(error.contains("token_command failed") && error.contains("locked"), "{}", error);
+        let error = run_token_command("true").unwrap_err();
+        assert_eq!(error.to_string(), "token_command printed no token");
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config;
+
+// Active environment and aliases, persisted next to config.toml
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct EnvironmentState {
+    pub active: Option<String>,
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+}
+
+// Where the environment used by a command came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvironmentSource {
+    Flag,
+    Session,
+    Active,
+    ConfigDefault,
+}
+
+impl EnvironmentSource {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            EnvironmentSource::Flag => "command-line flag",
+            EnvironmentSource::Session => "shell session",
+            EnvironmentSource::Active => "active environment",
+            EnvironmentSource::ConfigDefault => "profile default",
+        }
+    }
+}
+
+// Resolves environment IDs from aliases, the active environment and the profile's default
+pub struct Environments {
+    default_environment_id: String,
+    state_path: PathBuf,
+    read_only: bool,
+    // Set by `--env` before `shell`, taking precedence over the active environment
+    session_environment_id: Option<String>,
+}
+
+impl Environments {
+    // Each profile keeps its own active environment and aliases, in `state_dir` next to config.toml
+    pub fn new(state_dir: &Path, profile: &str, default_environment_id: String) -> Result<Self> {
+        let (state_path, legacy_path) = state_paths(state_dir, profile);
+        if let Some(state_dir) = state_path.parent() {
+            fs::create_dir_all(state_dir)?;
+        }
+
+        // State from before profiles belongs to the default profile
+        if let Some(legacy_path) = legacy_path {
+            fs::rename(&legacy_path, &state_path)?;
+        }
+
+        Ok(Environments {
+            default_environment_id,
+            state_path,
+            read_only: false,
+            session_environment_id: None,
+        })
+    }
+
+    // A profile's saved state for reading only, creating and moving nothing, e.g. while completing
+    // or when running without a config file
+    pub fn read_only(state_dir: &Path, profile: &str, default_environment_id: String) -> Self {
+        let (state_path, legacy_path) = state_paths(state_dir, profile);
+        Environments {
+            default_environment_id,
+            state_path: legacy_path.unwrap_or(state_path),
+            read_only: true,
+            session_environment_id: None,
+        }
+    }
+
+    // Only the given environment, with no state on disk, for tests and local tools
+    pub fn fixed(environment_id: &str) -> Self {
+        Environments {
+            default_environment_id: environment_id.to_string(),
+            state_path: PathBuf::new(),
+            read_only: true,
+            session_environment_id: None,
+        }
+    }
+
+    // The same state with the given environment used for every command, resolved once up front
+    pub fn for_session(&self, id_or_alias: &str) -> Result<Self> {
+        Ok(Environments {
+            default_environment_id: self.default_environment_id.clone(),
+            state_path: self.state_path.clone(),
+            read_only: self.read_only,
+            session_environment_id: Some(self.resolve(id_or_alias)?),
+        })
+    }
+
+    pub fn load_state(&self) -> Result<EnvironmentState> {
+        if !self.state_path.exists() {
+            return Ok(EnvironmentState::default());
+        }
+
+        let content = fs::read_to_string(&self.state_path)?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    pub fn save_state(&self, state: &EnvironmentState) -> Result<()> {
+        if self.read_only {
+            return Err(anyhow::anyhow!(
+                "Environments can't be saved without a config file; create one with `profile add`"
+            ));
+        }
+        fs::write(&self.state_path, toml::to_string_pretty(state)?)?;
+        Ok(())
+    }
+
+    // Turn an alias into its environment ID; anything else must already be an ID
+    pub fn resolve(&self, id_or_alias: &str) -> Result<String> {
+        let state = self.load_state()?;
+        if let Some(id) = state.aliases.get(id_or_alias) {
+            return Ok(id.clone());
+        }
+
+        if !config::is_uuid(id_or_alias) {
+            return Err(anyhow::anyhow!(
+                "Unknown environment alias '{}'. Use `env use <id> --alias {}` to define it",
+                id_or_alias,
+                id_or_alias
+            ));
+        }
+
+        Ok(id_or_alias.to_string())
+    }
+
+    // Environment used when none is given on the command line
+    pub fn current(&self) -> Result<(String, EnvironmentSource)> {
+        if let Some(id) = &self.session_environment_id {
+            return Ok((id.clone(), EnvironmentSource::Session));
+        }
+        match self.load_state()?.active {
+            Some(active) => Ok((self.resolve(&active)?, EnvironmentSource::Active)),
+            None => Ok((self.default_environment_id.clone(), EnvironmentSource::ConfigDefault)),
+        }
+    }
+}
+
+// Where a profile's state lives, and the file from before profiles if it still needs moving there
+fn state_paths(config_dir: &Path, profile: &str) -> (PathBuf, Option<PathBuf>) {
+    let state_path = config_dir.join("environments").join(format!("{}.toml", profile));
+    let legacy_path = config_dir.join("environments.toml");
+    let legacy = profile == config::DEFAULT_PROFILE && legacy_path.exists() && !state_path.exists();
+    (state_path, legacy.then_some(legacy_path))
+}
@@ -0,0 +1,309 @@
+use colored::*;
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::fmt;
+use std::time::Duration;
+use thiserror::Error;
+
+// Process exit codes, documented in the README for use in scripts
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_UNAUTHORIZED: i32 = 3;
+pub const EXIT_FORBIDDEN: i32 = 4;
+pub const EXIT_NOT_FOUND: i32 = 5;
+pub const EXIT_RATE_LIMITED: i32 = 6;
+pub const EXIT_VALIDATION: i32 = 7;
+pub const EXIT_SERVER: i32 = 8;
+pub const EXIT_NETWORK: i32 = 9;
+pub const EXIT_DECODE: i32 = 10;
+pub const EXIT_CANCELLED: i32 = 130;
+
+// A single invalid field reported by the API
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+// Failures talking to the Admin API
+#[derive(Debug, Error)]
+pub enum ApiError {
+    #[error("Unauthorized: {message}")]
+    Unauthorized { message: String },
+    #[error("Forbidden: {message}")]
+    Forbidden { message: String },
+    #[error("Not found: {message}")]
+    NotFound { message: String },
+    #[error("Rate limited by the API{}", retry_after.map(|d| format!(" (retry after {}s)", d.as_secs())).unwrap_or_default())]
+    RateLimited { retry_after: Option<Duration> },
+    #[error("Request rejected ({status}): {message}")]
+    Validation {
+        status: u16,
+        message: String,
+        fields: Vec<FieldError>,
+    },
+    #[error("Server error ({status}): {message}")]
+    Server { status: u16, message: String },
+    // Neither success nor an error status, e.g. a redirect that wasn't followed
+    #[error("Unexpected response ({status}): {message}")]
+    Unexpected { status: u16, message: String },
+    #[error("Network error: {0}")]
+    Network(#[from] reqwest::Error),
+    #[error("Could not decode API response: {0}")]
+    Decode(#[from] serde_json::Error),
+    #[error("No recorded response for {request}")]
+    NotRecorded { request: String },
+}
+
+impl ApiError {
+    // Build an error from a non-success response
+    pub fn from_response(status: StatusCode, retry_after: Option<Duration>, body: &str) -> Self {
+        let parsed: Option<Value> = serde_json::from_str(body).ok();
+        let message = parsed
+            .as_ref()
+            .and_then(error_message)
+            .unwrap_or_else(|| {
+                let body = body.trim();
+                if body.is_empty() {
+                    status.canonical_reason().unwrap_or("Unknown error").to_string()
+                } else {
+                    body.to_string()
+                }
+            });
+
+        match status {
+            StatusCode::UNAUTHORIZED => ApiError::Unauthorized { message },
+            StatusCode::FORBIDDEN => ApiError::Forbidden { message },
+            StatusCode::NOT_FOUND => ApiError::NotFound { message },
+            StatusCode::TOO_MANY_REQUESTS => ApiError::RateLimited { retry_after },
+            status if status.is_client_error() => ApiError::Validation {
+                status: status.as_u16(),
+                message,
+                fields: parsed.as_ref().map(field_errors).unwrap_or_default(),
+            },
+            status if status.is_server_error() => ApiError::Server {
+                status: status.as_u16(),
+                message,
+            },
+            status => ApiError::Unexpected {
+                status: status.as_u16(),
+                message,
+            },
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ApiError::Unauthorized { .. } => EXIT_UNAUTHORIZED,
+            ApiError::Forbidden { .. } => EXIT_FORBIDDEN,
+            ApiError::NotFound { .. } => EXIT_NOT_FOUND,
+            ApiError::RateLimited { .. } => EXIT_RATE_LIMITED,
+            ApiError::Validation { .. } => EXIT_VALIDATION,
+            ApiError::Server { .. } => EXIT_SERVER,
+            ApiError::Network(_) => EXIT_NETWORK,
+            ApiError::Decode(_) | ApiError::Unexpected { .. } => EXIT_DECODE,
+            ApiError::NotRecorded { .. } => EXIT_NETWORK,
+        }
+    }
+
+    // Transient failures worth another attempt: 408, 429 and 5xx other than 501 Not Implemented
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ApiError::RateLimited { .. } => true,
+            ApiError::Server { status, .. } => *status != StatusCode::NOT_IMPLEMENTED.as_u16(),
+            ApiError::Validation { status, .. } => *status == StatusCode::REQUEST_TIMEOUT.as_u16(),
+            ApiError::Network(e) => e.is_connect() || e.is_timeout() || e.is_request(),
+            _ => false,
+        }
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ApiError::RateLimited { retry_after } => *retry_after,
+            _ => None,
+        }
+    }
+
+    pub fn fields(&self) -> &[FieldError] {
+        match self {
+            ApiError::Validation { fields, .. } => fields,
+            _ => &[],
+        }
+    }
+
+    // What the user can do about it
+    pub fn hint(&self) -> Option<String> {
+        let hint = match self {
+            ApiError::Unauthorized { .. } => {
+                "The API token was rejected; it may have expired or been revoked. Create a new token in the Dynamic dashboard and update api_token in your profile".to_string()
+            }
+            ApiError::Forbidden { .. } => {
+                "The API token can't access this environment or operation. Check `env current` and the token's permissions".to_string()
+            }
+            ApiError::NotFound { .. } => {
+                "Check the ID and that it belongs to the environment shown by `env current`".to_string()
+            }
+            ApiError::RateLimited { retry_after: Some(delay) } => {
+                format!("Wait {}s before retrying", delay.as_secs())
+            }
+            ApiError::RateLimited { retry_after: None } => "Slow down and retry in a moment".to_string(),
+            ApiError::Validation { .. } => return None,
+            ApiError::Server { .. } => "The Dynamic API had a problem; retry later".to_string(),
+            ApiError::Unexpected { .. } => {
+                "The API answered with a status the CLI doesn't handle; check that base_url points at the Dynamic API".to_string()
+            }
+            ApiError::Network(_) => "Check your network connection and the base URL (the profile's base_url, DYNAMIC_BASE_URL or --base-url)".to_string(),
+            ApiError::Decode(_) => {
+                "The response wasn't what the CLI expected; check that base_url points at the Dynamic API".to_string()
+            }
+            ApiError::NotRecorded { .. } => {
+                "The cassette has no unplayed response for this request; record it again with --record".to_string()
+            }
+        };
+        Some(hint)
+    }
+}
+
+// A command stopped early because the user pressed Ctrl-C, saying how far it got
+#[derive(Debug, Error)]
+#[error("{0}")]
+pub struct Cancelled(pub String);
+
+// Exit code for any error, using the API error's code when there is one
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    if error.is::<Cancelled>() {
+        return EXIT_CANCELLED;
+    }
+    error
+        .downcast_ref::<ApiError>()
+        .map_or(EXIT_FAILURE, ApiError::exit_code)
+}
+
+// Print an error along with any field details and a hint for API failures
+pub fn print_error(e: &anyhow::Error) {
+    eprintln!("{}: {}", "Error".red(), e);
+
+    if let Some(api_error) = e.downcast_ref::<ApiError>() {
+        for field in api_error.fields() {
+            eprintln!("  - {}", field);
+        }
+        if let Some(hint) = api_error.hint() {
+            eprintln!("{}: {}", "Hint".yellow(), hint);
+        }
+    }
+}
+
+fn error_message(body: &Value) -> Option<String> {
+    ["error", "message", "detail"]
+        .iter()
+        .find_map(|key| match body.get(*key) {
+            Some(Value::String(message)) => Some(message.clone()),
+            Some(Value::Object(inner)) => inner.get("message").and_then(Value::as_str).map(str::to_string),
+            _ => None,
+        })
+}
+
+// Field errors, either `[{"field": .., "message": ..}]` under `errors` or a `{field: message}` map
+fn field_errors(body: &Value) -> Vec<FieldError> {
+    match body.get("errors").or_else(|| body.get("fields")) {
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| {
+                let field = ["field", "path", "property"]
+                    .iter()
+                    .find_map(|key| item.get(*key).and_then(Value::as_str))?;
+                let message = item
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("is invalid");
+                Some(FieldError {
+                    field: field.to_string(),
+                    message: message.to_string(),
+                })
+            })
+            .collect(),
+        Some(Value::Object(map)) => map
+            .iter()
+            .map(|(field, message)| FieldError {
+                field: field.clone(),
+                message: message.as_str().map_or_else(|| message.to_string(), str::to_string),
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: u16, body: &str) -> ApiError {
+        ApiError::from_response(StatusCode::from_u16(status).unwrap(), None, body)
+    }
+
+    #[test]
+    fn messages_come_from_the_body() {
+        let cases = [
+            (r#"{"error": "Email taken"}"#, "Email taken"),
+            (r#"{"message": "Bad cursor"}"#, "Bad cursor"),
+            (r#"{"error": {"code": "x", "message": "Nested"}}"#, "Nested"),
+            (r#"{"detail": "Try later"}"#, "Try later"),
+            ("  plain text  ", "plain text"),
+            ("", "Bad Request"),
+            (r#"{"errors": []}"#, r#"{"errors": []}"#),
+        ];
+        for (body, expected) in cases {
+            assert_eq!(response(400, body).to_string(), format!("Request rejected (400): {}", expected), "{}", body);
+        }
+    }
+
+    #[test]
+    fn field_errors_come_from_the_body() {
+        let error = response(
+            422,
+            r#"{"message": "Validation failed", "errors": [
+                {"field": "email", "message": "is taken"},
+                {"path": "phone"},
+                {"message": "no field"}
+            ]}"#,
+        );
+        assert_eq!(error.to_string(), "Request rejected (422): Validation failed");
+        let fields: Vec<String> = error.fields().iter().map(ToString::to_string).collect();
+        assert_eq!(fields, ["email: is taken", "phone: is invalid"]);
+
+        let error = response(400, r#"{"error": "Invalid", "fields": {"alias": "too long", "age": 3}}"#);
+        let fields: Vec<String> = error.fields().iter().map(ToString::to_string).collect();
+        assert_eq!(fields, ["alias: too long", "age: 3"]);
+        assert!(response(500, r#"{"errors": [{"field": "email"}]}"#).fields().is_empty());
+    }
+
+    #[test]
+    fn only_transient_statuses_are_retried() {
+        let cases = [
+            (301, false, EXIT_DECODE),
+            (304, false, EXIT_DECODE),
+            (400, false, EXIT_VALIDATION),
+            (401, false, EXIT_UNAUTHORIZED),
+            (403, false, EXIT_FORBIDDEN),
+            (404, false, EXIT_NOT_FOUND),
+            (408, true, EXIT_VALIDATION),
+            (409, false, EXIT_VALIDATION),
+            (429, true, EXIT_RATE_LIMITED),
+            (500, true, EXIT_SERVER),
+            (501, false, EXIT_SERVER),
+            (502, true, EXIT_SERVER),
+            (503, true, EXIT_SERVER),
+            (504, true, EXIT_SERVER),
+        ];
+        for (status, retryable, exit_code) in cases {
+            let error = response(status, "");
+            assert_eq!(error.is_retryable(), retryable, "{}", status);
+            assert_eq!(error.exit_code(), exit_code, "{}", status);
+        }
+    }
+}
@@ -0,0 +1,430 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::ArgMatches;
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::api::{self, AdminApi, Download, ExportRequest, OrganizationFields, PageOptions, RawRequest, UserFields};
+use crate::command;
+use crate::commands::{self, Command};
+use crate::config;
+use crate::context::ExecutionContext;
+use crate::environment::Environments;
+use crate::error::{ApiError, FieldError};
+use crate::models::{Export, Organization, OrganizationMember, User, Wallet};
+use crate::output::{OutputFormat, Renderer};
+use crate::prompt::{NonInteractivePrompter, Prompter, ScriptedPrompter};
+
+// An in-memory AdminApi for unit tests: commands run against it without a server, and tests
+// inspect its state and the calls it received afterwards.
+
+pub const ENVIRONMENT_ID: &str = "00000000-0000-4000-8000-000000000000";
+
+// Contents of every exported file
+pub const EXPORT_CONTENTS: &str = "id,email\nuser-1,ada@example.com\n";
+
+#[derive(Default)]
+pub struct FakeState {
+    pub users: Vec<User>,
+    pub organizations: Vec<Organization>,
+    pub members: BTreeMap<String, Vec<OrganizationMember>>,
+    pub wallets: Vec<Wallet>,
+    pub exports: Vec<Export>,
+    // When set, export requests are rejected with this message
+    pub reject_exports: Option<String>,
+    // Operations in the order they were called, e.g. "get_user user-1"
+    pub calls: Vec<String>,
+    next_id: usize,
+}
+
+impl FakeState {
+    fn id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}-{}", prefix, 100 + self.next_id)
+    }
+}
+
+#[derive(Default)]
+pub struct FakeAdminApi {
+    state: Mutex<FakeState>,
+}
+
+fn from_json<T: DeserializeOwned>(value: Value) -> T {
+    serde_json::from_value(value).unwrap()
+}
+
+fn not_found(kind: &str, id: &str) -> anyhow::Error {
+    ApiError::NotFound {
+        message: format!("{} {} not found", kind, id),
+    }
+    .into()
+}
+
+fn page<T: Clone + Send + 'static>(items: Vec<T>, options: PageOptions) -> BoxStream<'static, Result<T>> {
+    let limit = options.limit.unwrap_or(usize::MAX);
+    stream::iter(items.into_iter().take(limit).map(Ok)).boxed()
+}
+
+impl FakeAdminApi {
+    // Two users, an organization owned by the first and a wallet each
+    pub fn sample() -> Arc<Self> {
+        let state = FakeState {
+            users: vec![
+                from_json(json!({"id": "user-1", "email": "ada@example.com", "firstName": "Ada"})),
+                from_json(json!({"id": "user-2", "email": "grace@example.com", "firstName": "Grace"})),
+            ],
+            organizations: vec![from_json(json!({"id": "org-1", "name": "Acme Labs"}))],
+            members: BTreeMap::from([(
+                "org-1".to_string(),
+                vec![from_json(json!({"userId": "user-1", "email": "ada@example.com", "role": "owner"}))],
+            )]),
+            wallets: vec![
+                from_json(json!({
+                    "id": "wallet-1", "userId": "user-1", "chain": "EVM", "name": "metamask",
+                    "provider": "browserExtension", "publicKey": "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+                })),
+                from_json(json!({
+                    "id": "wallet-2", "userId": "user-2", "chain": "SOL", "name": "turnkeyhd",
+                    "provider": "embeddedWallet", "publicKey": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"
+                })),
+            ],
+            ..FakeState::default()
+        };
+        Arc::new(FakeAdminApi { state: Mutex::new(state) })
+    }
+
+    pub fn state(&self) -> MutexGuard<'_, FakeState> {
+        self.state.lock().unwrap()
+    }
+
+    pub fn calls(&self) -> Vec<String> {
+        self.state().calls.clone()
+    }
+
+    fn call(&self, call: String) -> MutexGuard<'_, FakeState> {
+        let mut state = self.state();
+        state.calls.push(call);
+        state
+    }
+
+    pub fn user(&self, user_id: &str) -> Option<User> {
+        self.state().users.iter().find(|user| user.id == user_id).cloned()
+    }
+
+    fn set_blocked(&self, call: String, user_id: &str, blocked: bool) -> Result<()> {
+        let mut state = self.call(call);
+        let user = state
+            .users
+            .iter_mut()
+            .find(|user| user.id == user_id)
+            .ok_or_else(|| not_found("User", user_id))?;
+        user.extra.insert("blocked".to_string(), json!(blocked));
+        Ok(())
+    }
+}
+
+// Run a command against fakes, as if from the command line, e.g. `["get", "user-1"]`, returning
+// what it printed as JSON. Nothing is asked, so arguments must cover everything.
+pub async fn execute(command: &dyn Command, args: &[&str]) -> Result<String> {
+    execute_with(command, args, no_prompter()).await
+}
+
+// Like `execute` for a command that succeeds, parsing what it printed
+pub async fn json(command: &dyn Command, args: &[&str]) -> Value {
+    let output = execute(command, args).await.unwrap();
+    serde_json::from_str(&output).unwrap_or_else(|e| panic!("{:?} printed invalid JSON ({}): {}", args, e, output))
+}
+
+// One field of every row of a listing, e.g. the IDs
+pub fn column<'a>(rows: &'a Value, field: &str) -> Vec<&'a str> {
+    rows.as_array()
+        .expect("a listing")
+        .iter()
+        .map(|row| row[field].as_str().unwrap_or_default())
+        .collect()
+}
+
+// Run a command that fails after printing a report, returning the report as JSON and the error
+pub async fn report(command: &dyn Command, args: &[&str]) -> (Value, anyhow::Error) {
+    let (stdout, result) = run(command, args, no_prompter()).await.unwrap();
+    let error = result.expect_err("the command should fail");
+    (serde_json::from_str(&stdout).unwrap(), error)
+}
+
+// Like `execute`, with questions answered by `prompter`
+pub async fn execute_with(command: &dyn Command, args: &[&str], prompter: Arc<dyn Prompter>) -> Result<String> {
+    let (stdout, result) = run(command, args, prompter).await?;
+    result.map(|_| stdout)
+}
+
+// What the command printed, and how it ended
+async fn run(command: &dyn Command, args: &[&str], prompter: Arc<dyn Prompter>) -> Result<(String, Result<()>)> {
+    let matches: ArgMatches = commands::command_cli(command)
+        .args(command::args::global_args())
+        .try_get_matches_from(args)?;
+    let mut stdout = Vec::new();
+    let mut ctx = ExecutionContext::new(&matches, config::DEFAULT_PROFILE, environments(), prompter)
+        .with_sinks(&mut stdout, io::sink());
+    ctx.renderer = Renderer::new(OutputFormat::Json, None);
+    let result = commands::run(command, &mut ctx).await;
+    drop(ctx);
+    Ok((String::from_utf8(stdout)?, result))
+}
+
+// Environments that always resolve to ENVIRONMENT_ID
+pub fn environments() -> Arc<Environments> {
+    Arc::new(Environments::fixed(ENVIRONMENT_ID))
+}
+
+// A prompter that answers nothing, so commands must get everything from their arguments
+pub fn no_prompter() -> Arc<dyn Prompter> {
+    Arc::new(NonInteractivePrompter)
+}
+
+// A prompter that gives these answers in order
+pub fn answers(answers: &[&str]) -> Arc<dyn Prompter> {
+    Arc::new(ScriptedPrompter::new(answers.iter().copied()))
+}
+
+#[async_trait]
+impl AdminApi for FakeAdminApi {
+    // Organizations
+    fn list_organizations(&self, _environment_id: &str, options: PageOptions) -> BoxStream<'_, Result<Organization>> {
+        let state = self.call("list_organizations".to_string());
+        page(state.organizations.clone(), options)
+    }
+
+    async fn get_organization(&self, _environment_id: &str, org_id: &str) -> Result<Organization> {
+        let state = self.call(format!("get_organization {}", org_id));
+        let org = state.organizations.iter().find(|org| org.id == org_id);
+        org.cloned().ok_or_else(|| not_found("Organization", org_id))
+    }
+
+    async fn create_organization(&self, _environment_id: &str, fields: &OrganizationFields) -> Result<Organization> {
+        let mut state = self.call("create_organization".to_string());
+        let mut value = serde_json::to_value(fields)?;
+        value["id"] = json!(state.id("org"));
+        let org: Organization = from_json(value);
+        state.organizations.push(org.clone());
+        Ok(org)
+    }
+
+    async fn update_organization(&self, _environment_id: &str, org_id: &str, fields: &OrganizationFields) -> Result<Organization> {
+        let mut state = self.call(format!("update_organization {}", org_id));
+        let org = state
+            .organizations
+            .iter_mut()
+            .find(|org| org.id == org_id)
+            .ok_or_else(|| not_found("Organization", org_id))?;
+        let mut value = serde_json::to_value(&*org)?;
+        json_merge(&mut value, serde_json::to_value(fields)?);
+        *org = from_json(value);
+        Ok(org.clone())
+    }
+
+    async fn delete_organization(&self, _environment_id: &str, org_id: &str) -> Result<()> {
+        let mut state = self.call(format!("delete_organization {}", org_id));
+        let count = state.organizations.len();
+        state.organizations.retain(|org| org.id != org_id);
+        if state.organizations.len() == count {
+            return Err(not_found("Organization", org_id));
+        }
+        state.members.remove(org_id);
+        Ok(())
+    }
+
+    // Organization members
+    fn list_organization_members(&self, _environment_id: &str, org_id: &str, options: PageOptions) -> BoxStream<'_, Result<OrganizationMember>> {
+        let state = self.call(format!("list_organization_members {}", org_id));
+        page(state.members.get(org_id).cloned().unwrap_or_default(), options)
+    }
+
+    async fn add_organization_member(&self, _environment_id: &str, org_id: &str, user_id: &str, role: &str) -> Result<OrganizationMember> {
+        let mut state = self.call(format!("add_organization_member {} {}", org_id, user_id));
+        let email = state.users.iter().find(|user| user.id == user_id).and_then(|user| user.email.clone());
+        let member: OrganizationMember = from_json(json!({"userId": user_id, "email": email, "role": role}));
+        state.members.entry(org_id.to_string()).or_default().push(member.clone());
+        Ok(member)
+    }
+
+    async fn set_organization_member_role(&self, _environment_id: &str, org_id: &str, user_id: &str, role: &str) -> Result<OrganizationMember> {
+        let mut state = self.call(format!("set_organization_member_role {} {} {}", org_id, user_id, role));
+        let member = state
+            .members
+            .get_mut(org_id)
+            .and_then(|members| members.iter_mut().find(|member| member.user_id == user_id))
+            .ok_or_else(|| not_found("Member", user_id))?;
+        member.role = Some(role.to_string());
+        Ok(member.clone())
+    }
+
+    async fn remove_organization_member(&self, _environment_id: &str, org_id: &str, user_id: &str) -> Result<()> {
+        let mut state = self.call(format!("remove_organization_member {} {}", org_id, user_id));
+        let members = state.members.get_mut(org_id).ok_or_else(|| not_found("Organization", org_id))?;
+        members.retain(|member| member.user_id != user_id);
+        Ok(())
+    }
+
+    // Exports
+    fn list_exports(&self, _environment_id: &str, options: PageOptions) -> BoxStream<'_, Result<Export>> {
+        let state = self.call("list_exports".to_string());
+        page(state.exports.clone(), options)
+    }
+
+    async fn get_export(&self, _environment_id: &str, export_id: &str) -> Result<Export> {
+        let state = self.call(format!("get_export {}", export_id));
+        let export = state.exports.iter().find(|export| export.id == export_id);
+        export.cloned().ok_or_else(|| not_found("Export", export_id))
+    }
+
+    // Exports finish straight away
+    async fn create_export(&self, _environment_id: &str, params: &ExportRequest, _idempotency_key: Option<&str>) -> Result<Export> {
+        let mut state = self.call(format!("create_export {}", params.export_type));
+        if let Some(message) = state.reject_exports.clone() {
+            return Err(ApiError::Validation { status: 400, message, fields: Vec::new() }.into());
+        }
+        let id = state.id("export");
+        let checksum = api::hex(&Sha256::digest(EXPORT_CONTENTS));
+        let export: Export = from_json(json!({
+            "id": id,
+            "status": "completed",
+            "model": params.export_type,
+            "format": params.format,
+            "filter": params.filter,
+            "downloadUrl": format!("/fake/exports/{}", id),
+            "fileSize": EXPORT_CONTENTS.len(),
+            "checksum": checksum,
+        }));
+        state.exports.push(export.clone());
+        Ok(export)
+    }
+
+    async fn download(&self, url: &str, dest: &Path, progress: &mut (dyn FnMut(u64, Option<u64>) + Send)) -> Result<Download> {
+        drop(self.call(format!("download {}", url)));
+        std::fs::write(dest, EXPORT_CONTENTS)?;
+        let bytes = EXPORT_CONTENTS.len() as u64;
+        progress(bytes, Some(bytes));
+        let sha256 = api::hex(&Sha256::digest(EXPORT_CONTENTS));
+        Ok(Download { bytes, sha256 })
+    }
+
+    // Wallets
+    fn list_wallets(&self, _environment_id: &str, options: PageOptions) -> BoxStream<'_, Result<Wallet>> {
+        let state = self.call("list_wallets".to_string());
+        page(state.wallets.clone(), options)
+    }
+
+    fn list_user_wallets(&self, _environment_id: &str, user_id: &str, options: PageOptions) -> BoxStream<'_, Result<Wallet>> {
+        let state = self.call(format!("list_user_wallets {}", user_id));
+        let wallets = state
+            .wallets
+            .iter()
+            .filter(|wallet| wallet.extra.get("userId").and_then(Value::as_str) == Some(user_id))
+            .cloned()
+            .collect();
+        page(wallets, options)
+    }
+
+    async fn get_wallet(&self, _environment_id: &str, wallet_id: &str) -> Result<Wallet> {
+        let state = self.call(format!("get_wallet {}", wallet_id));
+        let wallet = state.wallets.iter().find(|wallet| wallet.id == wallet_id);
+        wallet.cloned().ok_or_else(|| not_found("Wallet", wallet_id))
+    }
+
+    // Users
+    fn list_users(&self, _environment_id: &str, options: PageOptions) -> BoxStream<'_, Result<User>> {
+        let state = self.call("list_users".to_string());
+        page(state.users.clone(), options)
+    }
+
+    async fn get_user(&self, _environment_id: &str, user_id: &str) -> Result<User> {
+        let state = self.call(format!("get_user {}", user_id));
+        let user = state.users.iter().find(|user| user.id == user_id);
+        user.cloned().ok_or_else(|| not_found("User", user_id))
+    }
+
+    // Emails are unique, as they are in the real API
+    async fn create_user(&self, _environment_id: &str, fields: &UserFields, _idempotency_key: Option<&str>) -> Result<User> {
+        let mut state = self.call(format!("create_user {}", fields.email.as_deref().unwrap_or_default()));
+        if fields.email.is_some() && state.users.iter().any(|user| user.email == fields.email) {
+            return Err(ApiError::Validation {
+                status: 422,
+                message: "Validation failed".to_string(),
+                fields: vec![FieldError {
+                    field: "email".to_string(),
+                    message: "a user with this email already exists".to_string(),
+                }],
+            }
+            .into());
+        }
+
+        let mut value = serde_json::to_value(fields)?;
+        value["id"] = json!(state.id("user"));
+        let user: User = from_json(value);
+        state.users.push(user.clone());
+        Ok(user)
+    }
+
+    async fn update_user(&self, _environment_id: &str, user_id: &str, fields: &UserFields) -> Result<User> {
+        let mut state = self.call(format!("update_user {}", user_id));
+        let user = state
+            .users
+            .iter_mut()
+            .find(|user| user.id == user_id)
+            .ok_or_else(|| not_found("User", user_id))?;
+        let mut value = serde_json::to_value(&*user)?;
+        json_merge(&mut value, serde_json::to_value(fields)?);
+        *user = from_json(value);
+        Ok(user.clone())
+    }
+
+    async fn delete_user(&self, _environment_id: &str, user_id: &str) -> Result<()> {
+        let mut state = self.call(format!("delete_user {}", user_id));
+        let count = state.users.len();
+        state.users.retain(|user| user.id != user_id);
+        if state.users.len() == count {
+            return Err(not_found("User", user_id));
+        }
+        Ok(())
+    }
+
+    async fn block_user(&self, _environment_id: &str, user_id: &str) -> Result<()> {
+        self.set_blocked(format!("block_user {}", user_id), user_id, true)
+    }
+
+    async fn unblock_user(&self, _environment_id: &str, user_id: &str) -> Result<()> {
+        self.set_blocked(format!("unblock_user {}", user_id), user_id, false)
+    }
+
+    // Raw requests are echoed back rather than routed
+    async fn request(&self, environment_id: &str, request: &RawRequest) -> Result<Value> {
+        let path = request.path_in(environment_id)?;
+        drop(self.call(format!("request {} {}", request.method, path)));
+        Ok(json!({"method": request.method.as_str(), "path": path, "body": request.body}))
+    }
+
+    // Every list endpoint lists the users
+    fn paginate_request(&self, environment_id: &str, request: &RawRequest, options: PageOptions) -> BoxStream<'_, Result<Value>> {
+        let path = match request.path_in(environment_id) {
+            Ok(path) => path,
+            Err(e) => return stream::once(async { Err(e) }).boxed(),
+        };
+        let state = self.call(format!("paginate_request {}", path));
+        let users = state.users.iter().map(|user| serde_json::to_value(user).unwrap()).collect();
+        page(users, options)
+    }
+}
+
+// Shallow merge of `patch`'s fields into `target`, as the API applies updates
+fn json_merge(target: &mut Value, patch: Value) {
+    if let (Value::Object(target), Value::Object(patch)) = (target, patch) {
+        target.extend(patch);
+    }
+}
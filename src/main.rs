@@ -1,29 +1,91 @@
 use anyhow::Result;
+use clap::ArgMatches;
+use clap_complete::CompleteEnv;
 use colored::*;
-use dialoguer::{theme::ColorfulTheme, Select};
 use std::env;
+use std::path::Path;
 use std::process;
+use std::sync::Arc;
 
+mod address;
 mod commands;
+mod completion;
 mod config;
+mod context;
+mod credentials;
 mod api;
+mod cache;
+mod cassette;
 mod command;
+mod environment;
+mod error;
+#[cfg(test)]
+mod fake_api;
+mod mock_server;
+mod models;
+mod output;
+mod prompt;
+mod retry;
+mod shell;
+mod validation;
+#[cfg(test)]
+mod test_support;
 
+use api::ClientOptions;
+use cassette::Cassette;
 use commands::CommandRegistry;
+use context::ExecutionContext;
+use environment::Environments;
+use prompt::{Prompter, Question};
+use shell::Shell;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    println!("{}", "⭐️ DynamicSDK Admin Operations ⭐️".bold().cyan());
-    println!("{}", r"
-         __                                 _                           
-    ____/ /__  __ ____   ____ _ ____ ___   (_)_____    _  __ __  __ ____
-   / __  // / / // __ \ / __ `// __ `__ \ / // ___/   | |/_// / / //_  /
-  / /_/ // /_/ // / / // /_/ // / / / / // // /__ _  _>  < / /_/ /  / /_
-  \__,_/ \__, //_/ /_/ \__,_//_/ /_/ /_//_/ \___/(_)/_/|_| \__, /  /___/
-        /____/                                            /____/                                                                                                             
-    ".magenta());
+    // The completion script calls back in here to ask for candidates
+    CompleteEnv::with_factory(commands::default_cli).var(completion::COMPLETE_ENV).complete();
+
+    let args: Vec<String> = env::args().collect();
+
+    let prompter = match prompt::from_env() {
+        Ok(prompter) => prompter,
+        Err(e) => {
+            eprintln!("{}: {}", "Error".red(), e);
+            process::exit(1);
+        }
+    };
+
+    // Parse command-line arguments before loading configuration, since global flags affect how
+    // the client is set up. The command tree doesn't depend on configuration, so it's built from
+    // defaults for parsing.
+    let matches = if args.len() > 1 {
+        let defaults = commands::default_registry();
+        let matches = parse_args(&defaults, &args);
 
-    let config = match config::load_config() {
+        // Local tools don't talk to the API, so they run without loading a profile
+        if let Some((cmd, cmd_matches)) = defaults.find_command(&matches)
+            && !cmd.requires_config()
+        {
+            let environments = Arc::new(Environments::fixed(""));
+            let mut ctx = ExecutionContext::new(cmd_matches, config::DEFAULT_PROFILE, environments, prompter.clone());
+            if let Err(e) = commands::run(cmd, &mut ctx).await {
+                error::print_error(&e);
+                process::exit(error::exit_code(&e));
+            }
+            return Ok(());
+        }
+
+        Some(matches)
+    } else {
+        None
+    };
+
+    // Layer configuration: config.toml, then environment variables, then command-line flags
+    let mut overrides = config::Overrides::from_env();
+    if let Some(matches) = &matches {
+        overrides.apply_flags(matches);
+    }
+
+    let config = match config::load_config(&overrides, prompter.as_ref()) {
         Ok(cfg) => cfg,
         Err(e) => {
             eprintln!("{}: {}", "Error loading configuration".red(), e);
@@ -31,20 +93,73 @@ async fn main() -> Result<()> {
         }
     };
 
-    let registry = commands::create_command_registry(config);
-    let args: Vec<String> = env::args().collect();
-    if args.len() > 1 {
-        let cmd_path = &args[1..];
-        match execute_command_from_args(&registry, cmd_path).await {
+    let cassette = match matches.as_ref().map(open_cassette).transpose() {
+        Ok(cassette) => cassette.flatten(),
+        Err(e) => {
+            eprintln!("{}: {}", "Error".red(), e);
+            process::exit(1);
+        }
+    };
+
+    let options = ClientOptions {
+        retry: config.retry.clone(),
+        verbose: matches.as_ref().is_some_and(|m| m.get_flag("verbose")),
+        cassette,
+    };
+
+    // Running purely from environment variables and flags leaves nothing on disk
+    let default_environment_id = config.default_environment_id.clone();
+    let environments = match config.state_dir() {
+        Some(state_dir) => Environments::new(state_dir, &config.profile, default_environment_id),
+        None => Ok(Environments::read_only(config::state_dir(&config.path), &config.profile, default_environment_id)),
+    };
+    let environments = match environments {
+        Ok(environments) => Arc::new(environments),
+        Err(e) => {
+            eprintln!("{}: {}", "Error".red(), e);
+            process::exit(1);
+        }
+    };
+    let profile = config.profile.clone();
+    let history = config.state_dir().map(|state_dir| state_dir.join(shell::HISTORY_FILE));
+    let registry = match commands::create_command_registry(config, options) {
+        Ok(registry) => registry,
+        Err(e) => {
+            eprintln!("{}: {}", "Error".red(), e);
+            process::exit(1);
+        }
+    };
+
+    if let Some(matches) = matches {
+        let run = if matches.subcommand_name() == Some(shell::NAME) {
+            let environment = matches.get_one::<String>("env").map(String::as_str);
+            match Shell::new(&registry, &profile, environments, environment, history, prompter) {
+                Ok(shell) => shell.run().await,
+                Err(e) => Err(e),
+            }
+        } else {
+            execute_command_from_matches(&registry, &matches, &profile, environments, prompter).await
+        };
+        match run {
             Ok(_) => {},
             Err(e) => {
-                eprintln!("{}: {}", "Error".red(), e);
-                process::exit(1);
+                error::print_error(&e);
+                process::exit(error::exit_code(&e));
             }
         }
         return Ok(());
     }
 
+    println!("{}", "⭐️ DynamicSDK Admin Operations ⭐️".bold().cyan());
+    println!("{}", r"
+         __                                 _                           
+    ____/ /__  __ ____   ____ _ ____ ___   (_)_____    _  __ __  __ ____
+   / __  // / / // __ \ / __ `// __ `__ \ / // ___/   | |/_// / / //_  /
+  / /_/ // /_/ // / / // /_/ // / / / / // // /__ _  _>  < / /_/ /  / /_
+  \__,_/ \__, //_/ /_/ \__,_//_/ /_/ /_//_/ \___/(_)/_/|_| \__, /  /___/
+        /____/                                            /____/                                                                                                             
+    ".magenta());
+
     // Interactive mode - select category first
     let mut current_registry = &registry;
     let mut path = Vec::new();
@@ -54,9 +169,11 @@ async fn main() -> Result<()> {
         if current_registry.categories.is_empty() && current_registry.commands.len() == 1 {
             let cmd = &current_registry.commands[0];
             println!("\n{} {}", "Executing:".cyan(), path.join(" ").yellow());
-            match cmd.execute().await {
+            let matches = commands::empty_matches(cmd.as_ref());
+            let mut ctx = ExecutionContext::new(&matches, &profile, environments.clone(), prompter.clone());
+            match commands::run(cmd.as_ref(), &mut ctx).await {
                 Ok(_) => println!("{}", "Command executed successfully".green()),
-                Err(e) => eprintln!("{}: {}", "Error".red(), e),
+                Err(e) => error::print_error(&e),
             }
             break;
         }
@@ -79,14 +196,10 @@ async fn main() -> Result<()> {
         
         // Add commands
         for cmd in &current_registry.commands {
-            options.push(&cmd.name());
+            options.push(cmd.name());
         }
 
-        let selection = Select::with_theme(&ColorfulTheme::default())
-            .with_prompt("Select an option")
-            .default(0)
-            .items(&options)
-            .interact()?;
+        let selection = prompter.select(&Question::new("Select an option"), &options)?;
 
         // Handle back option
         if !path.is_empty() && selection == 0 {
@@ -115,9 +228,11 @@ async fn main() -> Result<()> {
             let cmd_idx = adjusted_idx - current_registry.categories.len();
             let cmd = &current_registry.commands[cmd_idx];
             println!("\n{} {}", "Executing:".cyan(), cmd.name().yellow());
-            match cmd.execute().await {
+            let matches = commands::empty_matches(cmd.as_ref());
+            let mut ctx = ExecutionContext::new(&matches, &profile, environments.clone(), prompter.clone());
+            match commands::run(cmd.as_ref(), &mut ctx).await {
                 Ok(_) => println!("{}", "Command executed successfully".green()),
-                Err(e) => eprintln!("{}: {}", "Error".red(), e),
+                Err(e) => error::print_error(&e),
             }
             
             // For interactive flow, we continue the loop after executing a command
@@ -127,6 +242,18 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+// The cassette named by --record or --replay, if any
+fn open_cassette(matches: &ArgMatches) -> Result<Option<Arc<Cassette>>> {
+    let cassette = if let Some(path) = matches.get_one::<String>("record") {
+        Cassette::record(Path::new(path))?
+    } else if let Some(path) = matches.get_one::<String>("replay") {
+        Cassette::replay(Path::new(path))?
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(Arc::new(cassette)))
+}
+
 // Get registry at a specific path
 fn get_registry_at_path<'a>(registry: &'a CommandRegistry, path: &[String]) -> &'a CommandRegistry {
     let mut current = registry;
@@ -141,28 +268,29 @@ fn get_registry_at_path<'a>(registry: &'a CommandRegistry, path: &[String]) -> &
     current
 }
 
-// Execute command from command-line arguments
-async fn execute_command_from_args(registry: &CommandRegistry, cmd_path: &[String]) -> Result<()> {
-    let mut current = registry;
-    let path_len = cmd_path.len();
-    
-    // Navigate to the appropriate registry
-    for (i, segment) in cmd_path.iter().enumerate() {
-        // If this is the last segment, it might be a command
-        if i == path_len - 1 {
-            // Try to find and execute command
-            if let Some(cmd) = current.commands.iter().find(|c| &c.name() == segment) {
-                return cmd.execute().await;
-            }
-        }
-        
-        // Otherwise, it should be a category
-        if let Some(category) = current.categories.iter().find(|c| &c.name == segment) {
-            current = &category.registry;
-        } else {
-            return Err(anyhow::anyhow!("Invalid command path: {}", cmd_path.join(" ")));
+// Parse command-line arguments against the registry's command tree
+fn parse_args(registry: &CommandRegistry, args: &[String]) -> ArgMatches {
+    // Usage errors and --help are reported by clap, which exits on its own
+    registry
+        .root_cli()
+        .subcommand(shell::cli())
+        .try_get_matches_from(args)
+        .unwrap_or_else(|e| e.exit())
+}
+
+// Execute the command selected on the command line
+async fn execute_command_from_matches(
+    registry: &CommandRegistry,
+    matches: &ArgMatches,
+    profile: &str,
+    environments: Arc<Environments>,
+    prompter: Arc<dyn Prompter>,
+) -> Result<()> {
+    match registry.find_command(matches) {
+        Some((cmd, cmd_matches)) => {
+            let mut ctx = ExecutionContext::new(cmd_matches, profile, environments, prompter);
+            commands::run(cmd, &mut ctx).await
         }
+        None => Err(anyhow::anyhow!("No command found")),
     }
-    
-    Err(anyhow::anyhow!("No command found at path: {}", cmd_path.join(" ")))
-}
\ No newline at end of file
+}
@@ -0,0 +1,524 @@
+use anyhow::Result;
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE, HOST, RETRY_AFTER};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+use crate::api;
+use crate::config;
+use crate::retry::IDEMPOTENCY_KEY;
+
+// In-memory stand-in for the Dynamic Admin API, serving the same /api/v0/environments/... paths
+// so base_url can point at it for demos and integration tests.
+
+// Times an export is polled before it completes
+const EXPORT_POLLS_UNTIL_READY: u32 = 2;
+
+// Fault injection and access settings
+#[derive(Debug, Clone)]
+pub struct MockOptions {
+    // Delay added to every API response
+    pub latency: Duration,
+    // Fraction of API requests (0.0 to 1.0) answered with error_status instead
+    pub error_rate: f64,
+    pub error_status: u16,
+    // Retry-After sent with injected 429s
+    pub retry_after_secs: u64,
+    // Only this token is accepted; any dyn_ token when unset
+    pub token: Option<String>,
+}
+
+impl Default for MockOptions {
+    fn default() -> Self {
+        MockOptions {
+            latency: Duration::ZERO,
+            error_rate: 0.0,
+            error_status: 500,
+            retry_after_secs: 1,
+            token: None,
+        }
+    }
+}
+
+// Data the server starts with, loaded from a JSON file with any of these keys
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Fixtures {
+    pub users: Vec<Value>,
+    pub organizations: Vec<Value>,
+    // Members by organization ID
+    pub members: BTreeMap<String, Vec<Value>>,
+    pub wallets: Vec<Value>,
+    pub exports: Vec<Value>,
+}
+
+impl Fixtures {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Could not read fixtures {:?}: {}", path, e))?;
+        serde_json::from_str(&content).map_err(|e| anyhow::anyhow!("Invalid fixtures {:?}: {}", path, e))
+    }
+
+    // A small data set covering every object type
+    pub fn sample() -> Self {
+        let org_id = mock_id("org", 1);
+        let users: Vec<Value> = ["ada@example.com", "grace@example.com", "alan@example.com"]
+            .iter()
+            .enumerate()
+            .map(|(i, email)| json!({ "id": mock_id("user", i + 1), "email": email, "createdAt": now() }))
+            .collect();
+        let wallets = vec![
+            json!({ "id": mock_id("wallet", 1), "userId": users[0]["id"], "chain": "EVM", "publicKey": "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed", "name": "metamask", "provider": "browserExtension" }),
+            json!({ "id": mock_id("wallet", 2), "userId": users[0]["id"], "chain": "SOL", "publicKey": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA", "name": "turnkeyhd", "provider": "embeddedWallet" }),
+            json!({ "id": mock_id("wallet", 3), "userId": users[1]["id"], "chain": "BTC", "publicKey": "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", "name": "turnkeyhd", "provider": "embeddedWallet" }),
+        ];
+        let members = BTreeMap::from([(
+            org_id.clone(),
+            vec![json!({ "userId": users[0]["id"], "email": users[0]["email"], "role": "owner", "createdAt": now() })],
+        )]);
+
+        Fixtures {
+            users,
+            organizations: vec![json!({ "id": org_id, "name": "Acme Labs", "createdAt": now() })],
+            members,
+            wallets,
+            exports: Vec::new(),
+        }
+    }
+
+    // Add generated users, e.g. to exercise pagination
+    pub fn with_generated_users(mut self, count: usize) -> Self {
+        let start = self.users.len();
+        self.users.extend((start..start + count).map(|i| {
+            json!({ "id": mock_id("user", i + 1), "email": format!("user{}@example.com", i + 1), "createdAt": now() })
+        }));
+        self
+    }
+}
+
+struct Mock {
+    options: MockOptions,
+    store: Mutex<Store>,
+}
+
+#[derive(Default)]
+struct Store {
+    data: Fixtures,
+    // Export ID -> times polled
+    polls: HashMap<String, u32>,
+    // Export ID -> file contents, fixed when the export completes
+    files: HashMap<String, Vec<u8>>,
+    // Responses to POSTs by idempotency key, replayed when the key is reused
+    idempotent: HashMap<String, (StatusCode, Value)>,
+    next_id: usize,
+}
+
+impl Store {
+    fn new_id(&mut self, kind: &str) -> String {
+        self.next_id += 1;
+        mock_id(kind, 1000 + self.next_id)
+    }
+}
+
+// Serve until the listener fails; each connection gets its own task
+pub async fn serve(listener: TcpListener, fixtures: Fixtures, options: MockOptions) -> Result<()> {
+    let mock = Arc::new(Mock {
+        options,
+        store: Mutex::new(Store {
+            data: fixtures,
+            ..Store::default()
+        }),
+    });
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let mock = mock.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| handle(mock.clone(), request));
+            let _ = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await;
+        });
+    }
+}
+
+type MockResponse = Response<Full<Bytes>>;
+
+async fn handle(mock: Arc<Mock>, request: Request<Incoming>) -> Result<MockResponse, Infallible> {
+    let (parts, body) = request.into_parts();
+    let path = parts.uri.path().to_string();
+    let query = query_params(parts.uri.query());
+    let header = |name: &str| parts.headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+
+    // Export files are served like a signed URL, without auth or faults
+    if let Some(export_id) = path.strip_prefix("/mock/files/") {
+        let store = mock.store.lock().unwrap();
+        // Seeded exports that are already complete are built on demand
+        let file = store
+            .files
+            .get(export_id)
+            .cloned()
+            .or_else(|| find(&store.data.exports, export_id).map(|export| export_file(&store.data, export)));
+        return Ok(match file {
+            Some(file) => file_response(file),
+            None => error(StatusCode::NOT_FOUND, "File not found"),
+        });
+    }
+
+    if !mock.options.latency.is_zero() {
+        tokio::time::sleep(mock.options.latency).await;
+    }
+
+    if mock.options.error_rate > 0.0 && fastrand::f64() < mock.options.error_rate {
+        return Ok(injected_fault(&mock.options));
+    }
+
+    let token = header(AUTHORIZATION.as_str()).and_then(|value| value.strip_prefix("Bearer ").map(str::to_string));
+    let authorized = match (&token, &mock.options.token) {
+        (Some(token), Some(expected)) => token == expected,
+        (Some(token), None) => config::is_api_token(token),
+        (None, _) => false,
+    };
+    if !authorized {
+        return Ok(error(StatusCode::UNAUTHORIZED, "Invalid or missing API token"));
+    }
+
+    let bytes = match body.collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(e) => return Ok(error(StatusCode::BAD_REQUEST, &e.to_string())),
+    };
+    let body: Value = match serde_json::from_slice(&bytes) {
+        _ if bytes.is_empty() => Value::Null,
+        Ok(body) => body,
+        Err(e) => return Ok(error(StatusCode::BAD_REQUEST, &format!("Invalid JSON body: {}", e))),
+    };
+
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let route = match segments.as_slice() {
+        ["api", "v0", "environments", environment_id, rest @ ..] if config::is_uuid(environment_id) => rest.to_vec(),
+        ["api", "v0", "environments", _, ..] => return Ok(error(StatusCode::NOT_FOUND, "Environment not found")),
+        _ => return Ok(error(StatusCode::NOT_FOUND, "No such endpoint")),
+    };
+
+    let idempotency_key = header(IDEMPOTENCY_KEY).filter(|_| parts.method == Method::POST);
+    let mut store = mock.store.lock().unwrap();
+    if let Some(key) = &idempotency_key
+        && let Some((status, body)) = store.idempotent.get(key)
+    {
+        return Ok(json_response(*status, body));
+    }
+
+    let host = header(HOST.as_str()).unwrap_or_else(|| "127.0.0.1".to_string());
+    let (status, response) = route_request(&mut store, &parts.method, &route, &query, body, &host);
+    if let Some(key) = idempotency_key
+        && status.is_success()
+    {
+        store.idempotent.insert(key, (status, response.clone()));
+    }
+
+    Ok(json_response(status, &response))
+}
+
+fn route_request(
+    store: &mut Store,
+    method: &Method,
+    route: &[&str],
+    query: &HashMap<String, String>,
+    body: Value,
+    host: &str,
+) -> (StatusCode, Value) {
+    let data = &mut store.data;
+    match (method.clone(), route) {
+        // Users
+        (Method::GET, ["users"]) => page(&data.users, "users", query),
+        (Method::POST, ["users"]) => {
+            let Some(mut fields) = body.as_object().cloned() else {
+                return invalid("body", "expected a JSON object");
+            };
+            let email = fields.get("email").and_then(Value::as_str).map(str::to_string);
+            if email.is_none() && !fields.contains_key("phoneNumber") {
+                return invalid("email", "an email or phone number is required");
+            }
+            if let Some(email) = &email
+                && data.users.iter().any(|user| user["email"].as_str() == Some(email))
+            {
+                return invalid("email", "a user with this email already exists");
+            }
+
+            let id = store.new_id("user");
+            let data = &mut store.data;
+            if let Some(Value::Array(wallets)) = fields.remove("wallets") {
+                for (i, wallet) in wallets.into_iter().enumerate() {
+                    let mut wallet = wallet.as_object().cloned().unwrap_or_default();
+                    wallet.insert("id".to_string(), json!(format!("{}-w{}", id, i + 1)));
+                    wallet.insert("userId".to_string(), json!(id));
+                    data.wallets.push(Value::Object(wallet));
+                }
+            }
+            fields.insert("id".to_string(), json!(id));
+            fields.insert("createdAt".to_string(), json!(now()));
+            let user = Value::Object(fields);
+            data.users.push(user.clone());
+            (StatusCode::CREATED, user)
+        }
+        (Method::GET, ["users", id]) => get(&data.users, id, "User"),
+        (Method::PUT, ["users", id]) => update(&mut data.users, id, body, "User"),
+        (Method::DELETE, ["users", id]) => remove(&mut data.users, id, "User"),
+        (Method::PUT, ["users", id, action @ ("block" | "unblock")]) => {
+            update(&mut data.users, id, json!({ "blocked": *action == "block" }), "User")
+        }
+        (Method::GET, ["users", id, "wallets"]) => {
+            if find(&data.users, id).is_none() {
+                return not_found("User");
+            }
+            let wallets: Vec<Value> = data.wallets.iter().filter(|w| w["userId"] == *id).cloned().collect();
+            page(&wallets, "wallets", query)
+        }
+
+        // Organizations and members
+        (Method::GET, ["organizations"]) => page(&data.organizations, "organizations", query),
+        (Method::POST, ["organizations"]) => {
+            if body.get("name").and_then(Value::as_str).is_none_or(str::is_empty) {
+                return invalid("name", "is required");
+            }
+            let id = store.new_id("org");
+            (StatusCode::CREATED, insert(&mut store.data.organizations, id, body))
+        }
+        (Method::GET, ["organizations", id]) => get(&data.organizations, id, "Organization"),
+        (Method::PUT, ["organizations", id]) => update(&mut data.organizations, id, body, "Organization"),
+        (Method::DELETE, ["organizations", id]) => {
+            data.members.remove(*id);
+            remove(&mut data.organizations, id, "Organization")
+        }
+        (Method::GET, ["organizations", id, "members"]) => {
+            if find(&data.organizations, id).is_none() {
+                return not_found("Organization");
+            }
+            page(data.members.get(*id).map_or(&[][..], Vec::as_slice), "members", query)
+        }
+        (Method::POST, ["organizations", id, "members"]) => {
+            if find(&data.organizations, id).is_none() {
+                return not_found("Organization");
+            }
+            let user_id = body.get("userId").and_then(Value::as_str).unwrap_or_default();
+            let Some(user) = find(&data.users, user_id).cloned() else {
+                return invalid("userId", "no user with this ID");
+            };
+            let members = data.members.entry(id.to_string()).or_default();
+            if members.iter().any(|m| m["userId"] == user_id) {
+                return invalid("userId", "already a member");
+            }
+            let member = json!({ "userId": user_id, "email": user["email"], "role": body["role"], "createdAt": now() });
+            members.push(member.clone());
+            (StatusCode::CREATED, member)
+        }
+        (Method::PUT, ["organizations", id, "members", user_id]) => {
+            match data.members.get_mut(*id).and_then(|m| m.iter_mut().find(|m| m["userId"] == *user_id)) {
+                Some(member) => {
+                    member["role"] = body["role"].clone();
+                    (StatusCode::OK, member.clone())
+                }
+                None => not_found("Member"),
+            }
+        }
+        (Method::DELETE, ["organizations", id, "members", user_id]) => match data.members.get_mut(*id) {
+            Some(members) if members.iter().any(|m| m["userId"] == *user_id) => {
+                members.retain(|m| m["userId"] != *user_id);
+                (StatusCode::NO_CONTENT, Value::Null)
+            }
+            _ => not_found("Member"),
+        },
+
+        // Wallets
+        (Method::GET, ["wallets"]) => page(&data.wallets, "wallets", query),
+        (Method::GET, ["wallets", id]) => get(&data.wallets, id, "Wallet"),
+
+        // Exports complete after a few polls, like the real API
+        (Method::GET, ["exports"]) => page(&data.exports, "exports", query),
+        (Method::POST, ["exports"]) => {
+            let id = store.new_id("export");
+            let export = json!({
+                "id": id,
+                "status": "pending",
+                "model": body["type"],
+                "format": body["format"],
+                "filter": body["filter"],
+                "createdAt": now(),
+            });
+            (StatusCode::CREATED, insert(&mut store.data.exports, id, export))
+        }
+        (Method::GET, ["exports", id]) => {
+            let polls = store.polls.entry(id.to_string()).or_default();
+            *polls += 1;
+            let ready = *polls > EXPORT_POLLS_UNTIL_READY;
+
+            let Some(export) = find(&store.data.exports, id).cloned() else {
+                return not_found("Export");
+            };
+            if !ready || export["status"] == "completed" {
+                return (StatusCode::OK, export);
+            }
+
+            let file = export_file(&store.data, &export);
+            let completed = json!({
+                "status": "completed",
+                "fileName": format!("{}.{}", id, export["format"].as_str().unwrap_or("csv")),
+                "fileSize": file.len(),
+                "checksum": format!("sha256:{}", api::hex(&Sha256::digest(&file))),
+                "downloadUrl": format!("http://{}/mock/files/{}", host, id),
+                "completedAt": now(),
+            });
+            store.files.insert(id.to_string(), file);
+            update(&mut store.data.exports, id, completed, "Export")
+        }
+
+        _ => (StatusCode::NOT_FOUND, json!({ "error": "No such endpoint" })),
+    }
+}
+
+// A page of a list, using the limit/offset parameters the client sends
+fn page(items: &[Value], key: &str, query: &HashMap<String, String>) -> (StatusCode, Value) {
+    let offset = query.get("offset").and_then(|v| v.parse().ok()).unwrap_or(0);
+    let limit = query.get("limit").and_then(|v| v.parse().ok()).unwrap_or(100);
+    let mut page = Map::new();
+    page.insert("count".to_string(), json!(items.len()));
+    page.insert(key.to_string(), items.iter().skip(offset).take(limit).cloned().collect());
+    (StatusCode::OK, Value::Object(page))
+}
+
+fn find<'a>(items: &'a [Value], id: &str) -> Option<&'a Value> {
+    items.iter().find(|item| item["id"] == id)
+}
+
+fn get(items: &[Value], id: &str, kind: &str) -> (StatusCode, Value) {
+    match find(items, id) {
+        Some(item) => (StatusCode::OK, item.clone()),
+        None => not_found(kind),
+    }
+}
+
+fn insert(items: &mut Vec<Value>, id: String, fields: Value) -> Value {
+    let mut item = fields.as_object().cloned().unwrap_or_default();
+    item.insert("id".to_string(), json!(id));
+    item.entry("createdAt").or_insert_with(|| json!(now()));
+    let item = Value::Object(item);
+    items.push(item.clone());
+    item
+}
+
+// Merge fields into an object, like the API's PUT endpoints
+fn update(items: &mut [Value], id: &str, fields: Value, kind: &str) -> (StatusCode, Value) {
+    let Some(item) = items.iter_mut().find(|item| item["id"] == id) else {
+        return not_found(kind);
+    };
+    if let (Some(item), Value::Object(fields)) = (item.as_object_mut(), fields) {
+        item.extend(fields);
+        item.insert("updatedAt".to_string(), json!(now()));
+    }
+    (StatusCode::OK, item.clone())
+}
+
+fn remove(items: &mut Vec<Value>, id: &str, kind: &str) -> (StatusCode, Value) {
+    let before = items.len();
+    items.retain(|item| item["id"] != id);
+    if items.len() == before {
+        return not_found(kind);
+    }
+    (StatusCode::NO_CONTENT, Value::Null)
+}
+
+fn not_found(kind: &str) -> (StatusCode, Value) {
+    (StatusCode::NOT_FOUND, json!({ "error": format!("{} not found", kind) }))
+}
+
+fn invalid(field: &str, message: &str) -> (StatusCode, Value) {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        json!({ "error": "Validation failed", "errors": [{ "field": field, "message": message }] }),
+    )
+}
+
+// Contents of an export's file: its model's objects as CSV or a JSON array
+fn export_file(data: &Fixtures, export: &Value) -> Vec<u8> {
+    let items = match export["model"].as_str() {
+        Some("organizations") => &data.organizations,
+        Some("wallets") => &data.wallets,
+        _ => &data.users,
+    };
+
+    if export["format"] == "json" {
+        return serde_json::to_vec_pretty(items).unwrap_or_default();
+    }
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let _ = writer.write_record(["id", "email", "name", "createdAt"]);
+    for item in items {
+        let cell = |key: &str| item[key].as_str().unwrap_or_default().to_string();
+        let _ = writer.write_record([cell("id"), cell("email"), cell("name"), cell("createdAt")]);
+    }
+    writer.into_inner().unwrap_or_default()
+}
+
+fn injected_fault(options: &MockOptions) -> MockResponse {
+    let status = StatusCode::from_u16(options.error_status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let mut response = error(status, "Injected fault");
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, options.retry_after_secs.into());
+    }
+    response
+}
+
+fn error(status: StatusCode, message: &str) -> MockResponse {
+    json_response(status, &json!({ "error": message }))
+}
+
+fn json_response(status: StatusCode, body: &Value) -> MockResponse {
+    let body = if status == StatusCode::NO_CONTENT {
+        Bytes::new()
+    } else {
+        Bytes::from(serde_json::to_vec(body).unwrap_or_default())
+    };
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Full::new(body))
+        .unwrap()
+}
+
+fn file_response(contents: Vec<u8>) -> MockResponse {
+    Response::builder()
+        .header(CONTENT_TYPE, "application/octet-stream")
+        .body(Full::new(Bytes::from(contents)))
+        .unwrap()
+}
+
+fn query_params(query: Option<&str>) -> HashMap<String, String> {
+    query
+        .unwrap_or_default()
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+// IDs that say what they are, e.g. user-1
+fn mock_id(kind: &str, n: usize) -> String {
+    format!("{}-{}", kind, n)
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+// Typed views of Admin API objects. Fields the CLI doesn't know about are kept in `extra`
+// so they survive a round trip and still show up in JSON/YAML output.
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone_number: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_visit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wallets: Option<Vec<Wallet>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified_credentials: Option<Vec<VerifiedCredential>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Wallet {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_selected_at: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Wallet {
+    // Family of the wallet's chain code, e.g. "evm" for EVM or ETH; None for other chains
+    pub fn chain_family(&self) -> Option<&'static str> {
+        crate::address::chain_family(self.chain.as_deref()?)
+    }
+
+    // Matches a provider filter against the provider or wallet name, e.g. embeddedWallet or metamask
+    pub fn has_provider(&self, provider: &str) -> bool {
+        [&self.provider, &self.name]
+            .iter()
+            .any(|value| value.as_deref().is_some_and(|v| v.eq_ignore_ascii_case(provider)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiedCredential {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone_number: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_identifier: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wallet_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wallet_provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oauth_provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_selected_at: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Organization {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub website_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+// A user's membership in an organization
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizationMember {
+    pub user_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Export {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<u64>,
+    // SHA-256 of the file, as hex and optionally prefixed with `sha256:`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Export {
+    fn status(&self) -> String {
+        self.status.as_deref().unwrap_or_default().to_lowercase()
+    }
+
+    // Finished successfully, so the file can be downloaded
+    pub fn is_ready(&self) -> bool {
+        matches!(self.status().as_str(), "completed" | "complete" | "succeeded" | "done")
+    }
+
+    // Finished without producing a file
+    pub fn is_failed(&self) -> bool {
+        matches!(self.status().as_str(), "failed" | "error" | "cancelled" | "canceled" | "expired")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::DeserializeOwned;
+
+    // Explicit nulls deserialize to None and are skipped on the way out, so compare without them
+    fn strip_nulls(value: Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .filter(|(_, v)| !v.is_null())
+                    .map(|(k, v)| (k, strip_nulls(v)))
+                    .collect(),
+            ),
+            Value::Array(items) => Value::Array(items.into_iter().map(strip_nulls).collect()),
+            other => other,
+        }
+    }
+
+    fn assert_round_trip<T: DeserializeOwned + Serialize>(fixture: &str) -> T {
+        let original: Value = serde_json::from_str(fixture).unwrap();
+        let typed: T = serde_json::from_value(original.clone()).unwrap();
+        let serialized = serde_json::to_value(&typed).unwrap();
+        assert_eq!(strip_nulls(serialized), strip_nulls(original));
+        typed
+    }
+
+    #[test]
+    fn user_round_trips() {
+        let user: User = assert_round_trip(include_str!("../tests/fixtures/user.json"));
+        assert_eq!(user.email.as_deref(), Some("ada@example.com"));
+        assert_eq!(user.wallets.as_ref().map(Vec::len), Some(2));
+        assert_eq!(user.verified_credentials.as_ref().map(Vec::len), Some(2));
+        assert!(user.extra.contains_key("mfaBackupCodeAcknowledgement"));
+    }
+
+    #[test]
+    fn wallet_round_trips() {
+        let wallet: Wallet = assert_round_trip(include_str!("../tests/fixtures/wallet.json"));
+        assert_eq!(wallet.chain.as_deref(), Some("SOL"));
+        assert_eq!(wallet.chain_family(), Some("solana"));
+        assert!(wallet.has_provider("EmbeddedWallet") && wallet.has_provider("turnkeyhd"));
+        assert!(wallet.extra.contains_key("walletBookName"));
+    }
+
+    #[test]
+    fn organization_round_trips() {
+        let org: Organization = assert_round_trip(include_str!("../tests/fixtures/organization.json"));
+        assert_eq!(org.name.as_deref(), Some("Acme Labs"));
+    }
+
+    #[test]
+    fn organization_member_round_trips() {
+        let member: OrganizationMember = assert_round_trip(include_str!("../tests/fixtures/organization_member.json"));
+        assert_eq!(member.role.as_deref(), Some("admin"));
+    }
+
+    #[test]
+    fn export_round_trips() {
+        let export: Export = assert_round_trip(include_str!("../tests/fixtures/export.json"));
+        assert_eq!(export.status.as_deref(), Some("completed"));
+        assert!(export.download_url.is_some());
+    }
+}
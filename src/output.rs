@@ -0,0 +1,616 @@
+use anyhow::Result;
+use clap::{ArgMatches, ValueEnum};
+use futures::{Stream, TryStreamExt};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::io::{self, IsTerminal, Write};
+use std::time::Duration;
+
+// Output formats selectable with --output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Jsonl,
+    Yaml,
+    Csv,
+}
+
+// Narrowest a table column is squeezed to when the terminal is too small
+const MIN_COLUMN_WIDTH: usize = 6;
+const COLUMN_GAP: &str = "  ";
+// Rows buffered to size table columns before streaming the rest
+const TABLE_SAMPLE_ROWS: usize = 100;
+
+// Renders command results to a writer in the selected format
+pub struct Renderer {
+    format: OutputFormat,
+    columns: Option<Vec<String>>,
+}
+
+impl Renderer {
+    pub fn new(format: OutputFormat, columns: Option<Vec<String>>) -> Self {
+        Renderer { format, columns }
+    }
+
+    // Read --output and --columns, defaulting to a table on a terminal and JSON when piped
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        let format = matches
+            .try_get_one::<OutputFormat>("output")
+            .ok()
+            .flatten()
+            .copied()
+            .unwrap_or_else(|| {
+                if io::stdout().is_terminal() {
+                    OutputFormat::Table
+                } else {
+                    OutputFormat::Json
+                }
+            });
+
+        let columns = matches
+            .try_get_many::<String>("columns")
+            .ok()
+            .flatten()
+            .map(|values| values.cloned().collect());
+
+        Renderer::new(format, columns)
+    }
+
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    // Writer that renders list items one at a time as they arrive
+    pub fn list_writer<'w>(&self, out: &'w mut (dyn Write + Send), default_columns: &[&str]) -> ListWriter<'w> {
+        ListWriter {
+            format: self.format,
+            selected: self.columns.clone(),
+            default_columns: default_columns.iter().map(|c| c.to_string()).collect(),
+            columns: None,
+            out,
+            table: None,
+            count: 0,
+        }
+    }
+
+    // Render a stream of items incrementally, returning how many were written
+    pub async fn render_stream<S, T>(&self, out: &mut (dyn Write + Send), items: S, default_columns: &[&str]) -> Result<usize>
+    where
+        S: Stream<Item = Result<T>>,
+        T: Serialize,
+    {
+        let mut writer = self.list_writer(out, default_columns);
+        let mut items = std::pin::pin!(items);
+        while let Some(item) = items.try_next().await? {
+            writer.write(&serde_json::to_value(&item)?)?;
+        }
+        writer.finish()
+    }
+
+    // Render a single object, e.g. the result of a `get` command
+    pub fn render_item<T: Serialize>(&self, out: &mut dyn Write, item: &T) -> Result<()> {
+        let item = project(&serde_json::to_value(item)?, self.columns.as_deref());
+
+        match self.format {
+            OutputFormat::Table => {
+                let mut table = TableWriter::new(None);
+                match &item {
+                    Value::Object(map) => {
+                        for (key, value) in map {
+                            table.push(out, vec![key.clone(), cell(Some(value))])?;
+                        }
+                    }
+                    other => table.push(out, vec![cell(Some(other))])?,
+                }
+                table.finish(out)?;
+            }
+            OutputFormat::Csv => {
+                let columns = scalar_keys(&item);
+                csv_record(out, &columns)?;
+                csv_record(out, columns.iter().map(|c| cell(lookup(&item, c))))?;
+            }
+            OutputFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(&item)?)?,
+            OutputFormat::Jsonl => writeln!(out, "{}", serde_json::to_string(&item)?)?,
+            OutputFormat::Yaml => write!(out, "{}", serde_yaml::to_string(&item)?)?,
+        }
+
+        Ok(())
+    }
+}
+
+// Incremental writer for list output
+pub struct ListWriter<'w> {
+    format: OutputFormat,
+    selected: Option<Vec<String>>,
+    default_columns: Vec<String>,
+    columns: Option<Vec<String>>,
+    out: &'w mut (dyn Write + Send),
+    table: Option<TableWriter>,
+    count: usize,
+}
+
+impl ListWriter<'_> {
+    pub fn write(&mut self, item: &Value) -> Result<()> {
+        if self.columns.is_none() {
+            self.start(Some(item))?;
+        }
+        let columns = self.columns.as_deref().unwrap_or_default();
+
+        match self.format {
+            OutputFormat::Table => {
+                let row = columns.iter().map(|c| cell(lookup(item, c))).collect();
+                if let Some(table) = &mut self.table {
+                    table.push(self.out, row)?;
+                }
+            }
+            OutputFormat::Csv if !columns.is_empty() => {
+                csv_record(self.out, columns.iter().map(|c| cell(lookup(item, c))))?;
+            }
+            OutputFormat::Csv => {}
+            OutputFormat::Json => {
+                let pretty = serde_json::to_string_pretty(&project(item, self.selected.as_deref()))?;
+                let separator = if self.count == 0 { "[\n" } else { ",\n" };
+                write!(self.out, "{}  {}", separator, pretty.replace('\n', "\n  "))?;
+            }
+            OutputFormat::Jsonl => {
+                writeln!(self.out, "{}", serde_json::to_string(&project(item, self.selected.as_deref()))?)?;
+            }
+            OutputFormat::Yaml => {
+                let entry = [project(item, self.selected.as_deref())];
+                write!(self.out, "{}", serde_yaml::to_string(&entry)?)?;
+            }
+        }
+
+        self.count += 1;
+        Ok(())
+    }
+
+    // Flush anything still buffered and close open structures
+    pub fn finish(mut self) -> Result<usize> {
+        if self.columns.is_none() {
+            self.start(None)?;
+        }
+
+        match self.format {
+            OutputFormat::Table => {
+                if let Some(table) = &mut self.table {
+                    table.finish(self.out)?;
+                }
+            }
+            OutputFormat::Csv => {}
+            OutputFormat::Json if self.count == 0 => writeln!(self.out, "[]")?,
+            OutputFormat::Json => writeln!(self.out, "\n]")?,
+            OutputFormat::Yaml if self.count == 0 => writeln!(self.out, "[]")?,
+            OutputFormat::Jsonl | OutputFormat::Yaml => {}
+        }
+
+        self.out.flush()?;
+        Ok(self.count)
+    }
+
+    // Settle the columns (from --columns, the command defaults or the first item) and write headers
+    fn start(&mut self, first: Option<&Value>) -> Result<()> {
+        let columns = match (&self.selected, first) {
+            (Some(selected), _) => selected.clone(),
+            _ if !self.default_columns.is_empty() => self.default_columns.clone(),
+            (None, Some(first)) => scalar_keys(first),
+            (None, None) => Vec::new(),
+        };
+
+        match self.format {
+            OutputFormat::Table if !columns.is_empty() => {
+                let header = columns.iter().map(|c| c.to_uppercase()).collect();
+                self.table = Some(TableWriter::new(Some(header)));
+            }
+            OutputFormat::Csv if !columns.is_empty() => csv_record(self.out, &columns)?,
+            _ => {}
+        }
+
+        self.columns = Some(columns);
+        Ok(())
+    }
+}
+
+// Write one CSV record, flushed straight through so rows stream as they're written
+fn csv_record<I, F>(out: &mut dyn Write, fields: I) -> Result<()>
+where
+    I: IntoIterator<Item = F>,
+    F: AsRef<[u8]>,
+{
+    let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(out);
+    writer.write_record(fields)?;
+    writer.flush()?;
+    Ok(())
+}
+
+// Keep only the selected --columns, if any
+fn project(item: &Value, columns: Option<&[String]>) -> Value {
+    match columns {
+        Some(columns) => {
+            let mut map = Map::new();
+            for column in columns {
+                map.insert(column.clone(), lookup(item, column).cloned().unwrap_or(Value::Null));
+            }
+            Value::Object(map)
+        }
+        None => item.clone(),
+    }
+}
+
+// Scalar fields of an object, used when a command has no default columns
+fn scalar_keys(item: &Value) -> Vec<String> {
+    match item {
+        Value::Object(map) => map
+            .iter()
+            .filter(|(_, value)| !value.is_object() && !value.is_array())
+            .map(|(key, _)| key.clone())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Look up a dotted path such as `wallets.0.address`
+pub fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |current, segment| match current {
+        Value::Object(map) => map.get(segment),
+        Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => None,
+    })
+}
+
+// Format a value for a table or CSV cell
+pub fn cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.replace(['\n', '\r'], " "),
+        Some(other) => other.to_string(),
+    }
+}
+
+// Aligned table output. Column widths come from the first rows, shrunk to fit the terminal;
+// later rows are written straight through using those widths.
+struct TableWriter {
+    header: Option<Vec<String>>,
+    pending: Vec<Vec<String>>,
+    widths: Option<Vec<usize>>,
+    terminal_width: Option<usize>,
+}
+
+impl TableWriter {
+    fn new(header: Option<Vec<String>>) -> Self {
+        TableWriter {
+            header,
+            pending: Vec::new(),
+            widths: None,
+            terminal_width: terminal_width(),
+        }
+    }
+
+    fn push(&mut self, out: &mut dyn Write, row: Vec<String>) -> Result<()> {
+        if self.widths.is_some() {
+            return self.write_row(out, &row);
+        }
+
+        self.pending.push(row);
+        if self.pending.len() >= TABLE_SAMPLE_ROWS {
+            self.flush_pending(out)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut dyn Write) -> Result<()> {
+        if self.widths.is_none() {
+            self.flush_pending(out)?;
+        }
+        Ok(())
+    }
+
+    fn flush_pending(&mut self, out: &mut dyn Write) -> Result<()> {
+        let column_count = self
+            .header
+            .as_ref()
+            .map(|h| h.len())
+            .unwrap_or_else(|| self.pending.iter().map(|r| r.len()).max().unwrap_or(0));
+
+        let mut widths = vec![0; column_count];
+        for row in self.header.iter().chain(self.pending.iter()) {
+            for (i, value) in row.iter().enumerate().take(column_count) {
+                widths[i] = widths[i].max(console::measure_text_width(value));
+            }
+        }
+
+        if let Some(available) = self.terminal_width {
+            fit_widths(&mut widths, available);
+        }
+        self.widths = Some(widths);
+
+        if let Some(header) = self.header.clone() {
+            self.write_row(out, &header)?;
+        }
+        for row in std::mem::take(&mut self.pending) {
+            self.write_row(out, &row)?;
+        }
+        Ok(())
+    }
+
+    fn write_row(&self, out: &mut dyn Write, row: &[String]) -> Result<()> {
+        let widths = self.widths.as_deref().unwrap_or_default();
+        let last = widths.len().saturating_sub(1);
+
+        let mut line = String::new();
+        for (i, value) in row.iter().enumerate().take(widths.len()) {
+            // Only truncate when there's a terminal to fit; piped tables keep full values
+            let value = if self.terminal_width.is_some() {
+                truncate(value, widths[i])
+            } else {
+                value.into()
+            };
+            if i == last {
+                line.push_str(&value);
+            } else {
+                line.push_str(&console::pad_str(&value, widths[i], console::Alignment::Left, None));
+                line.push_str(COLUMN_GAP);
+            }
+        }
+        writeln!(out, "{}", line.trim_end())?;
+        Ok(())
+    }
+}
+
+fn truncate(value: &str, width: usize) -> std::borrow::Cow<'_, str> {
+    if console::measure_text_width(value) <= width {
+        return value.into();
+    }
+    console::truncate_str(value, width, "…")
+}
+
+fn terminal_width() -> Option<usize> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    console::Term::stdout().size_checked().map(|(_, cols)| cols as usize)
+}
+
+fn fit_widths(widths: &mut [usize], available: usize) {
+    let gaps = COLUMN_GAP.len() * widths.len().saturating_sub(1);
+    while widths.iter().sum::<usize>() + gaps > available {
+        let (widest, width) = match widths.iter().enumerate().max_by_key(|(_, w)| **w) {
+            Some((i, w)) => (i, *w),
+            None => return,
+        };
+        if width <= MIN_COLUMN_WIDTH {
+            return;
+        }
+        widths[widest] -= 1;
+    }
+}
+
+// Spinner on stderr for waiting on the API; hidden when stderr isn't a terminal
+pub fn spinner(message: &str) -> ProgressBar {
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_message(message.to_string());
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    spinner
+}
+
+// Progress bar on stderr counting items, e.g. rows of an import
+pub fn progress_bar(total: u64) -> ProgressBar {
+    let bar = ProgressBar::new(total);
+    bar.set_style(
+        ProgressStyle::with_template("{bar:40.cyan/blue} {pos}/{len} {msg} ({eta})")
+            .unwrap_or_else(|_| ProgressStyle::default_bar()),
+    );
+    bar
+}
+
+// Byte progress bar on stderr, for downloads of a known or unknown size
+pub fn byte_progress(total: Option<u64>) -> ProgressBar {
+    match total {
+        Some(total) => {
+            let bar = ProgressBar::new(total);
+            bar.set_style(
+                ProgressStyle::with_template("{bar:40.cyan/blue} {bytes}/{total_bytes} ({eta})")
+                    .unwrap_or_else(|_| ProgressStyle::default_bar()),
+            );
+            bar
+        }
+        None => {
+            let bar = ProgressBar::new_spinner();
+            bar.set_style(
+                ProgressStyle::with_template("{spinner} {bytes} downloaded")
+                    .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+            );
+            bar
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render_list(format: OutputFormat, columns: Option<&[&str]>, items: &[Value]) -> String {
+        let columns = columns.map(|columns| columns.iter().map(|c| c.to_string()).collect());
+        let mut out = Vec::new();
+        let mut writer = Renderer::new(format, columns).list_writer(&mut out, &[]);
+        for item in items {
+            writer.write(item).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), items.len());
+        String::from_utf8(out).unwrap()
+    }
+
+    fn users(count: usize) -> Vec<Value> {
+        (1..=count)
+            .map(|i| json!({"id": format!("user-{}", i), "email": format!("user{}@example.com", i)}))
+            .collect()
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        let cases = [
+            ("plain", "plain\n"),
+            ("a,b", "\"a,b\"\n"),
+            ("say \"hi\"", "\"say \"\"hi\"\"\"\n"),
+            ("two\nlines", "\"two\nlines\"\n"),
+            ("", "\"\"\n"),
+        ];
+        for (field, expected) in cases {
+            let mut out = Vec::new();
+            csv_record(&mut out, [field]).unwrap();
+            assert_eq!(String::from_utf8(out).unwrap(), expected, "{:?}", field);
+        }
+
+        let items = [json!({"id": "user-1", "name": "Lovelace, Ada", "bio": "said \"hi\""})];
+        assert_eq!(
+            render_list(OutputFormat::Csv, None, &items),
+            "id,name,bio\nuser-1,\"Lovelace, Ada\",\"said \"\"hi\"\"\"\n"
+        );
+    }
+
+    #[test]
+    fn json_lists_are_framed_for_any_length() {
+        let cases = [
+            (0, "[]\n"),
+            (1, "[\n  {\n    \"id\": \"user-1\",\n    \"email\": \"user1@example.com\"\n  }\n]\n"),
+            (3, ""),
+        ];
+        for (count, expected) in cases {
+            let items = users(count);
+            let output = render_list(OutputFormat::Json, None, &items);
+            if !expected.is_empty() {
+                assert_eq!(output, expected);
+            }
+            let parsed: Value = serde_json::from_str(&output).unwrap();
+            assert_eq!(parsed, Value::Array(items), "{}", output);
+        }
+    }
+
+    #[test]
+    fn line_formats_write_one_entry_per_item() {
+        let cases = [
+            (OutputFormat::Jsonl, 0, ""),
+            (
+                OutputFormat::Jsonl,
+                2,
+                "{\"id\":\"user-1\",\"email\":\"user1@example.com\"}\n{\"id\":\"user-2\",\"email\":\"user2@example.com\"}\n",
+            ),
+            (OutputFormat::Yaml, 0, "[]\n"),
+            (
+                OutputFormat::Yaml,
+                2,
+                "- id: user-1\n  email: user1@example.com\n- id: user-2\n  email: user2@example.com\n",
+            ),
+        ];
+        for (format, count, expected) in cases {
+            assert_eq!(render_list(format, None, &users(count)), expected, "{:?} with {} items", format, count);
+        }
+
+        let mut out = Vec::new();
+        Renderer::new(OutputFormat::Yaml, None).render_item(&mut out, &users(1)[0]).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "id: user-1\nemail: user1@example.com\n");
+    }
+
+    #[test]
+    fn cells_flatten_values() {
+        let cases = [
+            (None, ""),
+            (Some(json!(null)), ""),
+            (Some(json!("two\nlines")), "two lines"),
+            (Some(json!(3)), "3"),
+            (Some(json!(true)), "true"),
+            (Some(json!({"plan": "pro"})), r#"{"plan":"pro"}"#),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(cell(value.as_ref()), expected, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn scalar_fields_are_the_fallback_columns() {
+        let user = json!({"id": "user-1", "email": "a@example.com", "metadata": {}, "wallets": []});
+        assert_eq!(scalar_keys(&user), ["id", "email"]);
+        assert!(scalar_keys(&json!("user-1")).is_empty());
+    }
+
+    #[test]
+    fn columns_select_nested_fields() {
+        let user = json!({
+            "id": "user-1",
+            "metadata": {"plan": "pro"},
+            "wallets": [{"address": "0xabc"}, {"address": "0xdef"}],
+        });
+        let cases: [(&str, Option<Value>); 5] = [
+            ("id", Some(json!("user-1"))),
+            ("metadata.plan", Some(json!("pro"))),
+            ("wallets.1.address", Some(json!("0xdef"))),
+            ("wallets.2.address", None),
+            ("metadata.plan.name", None),
+        ];
+        for (path, expected) in &cases {
+            assert_eq!(lookup(&user, path), expected.as_ref(), "{}", path);
+        }
+
+        let columns: Vec<String> = cases.iter().map(|(path, _)| path.to_string()).collect();
+        assert_eq!(
+            project(&user, Some(&columns)),
+            json!({
+                "id": "user-1",
+                "metadata.plan": "pro",
+                "wallets.1.address": "0xdef",
+                "wallets.2.address": null,
+                "metadata.plan.name": null,
+            })
+        );
+        assert_eq!(project(&user, None), user);
+
+        let columns = ["id", "wallets.0.address"];
+        assert_eq!(
+            render_list(OutputFormat::Csv, Some(&columns), std::slice::from_ref(&user)),
+            "id,wallets.0.address\nuser-1,0xabc\n"
+        );
+        assert_eq!(
+            render_list(OutputFormat::Jsonl, Some(&columns), &[user]),
+            "{\"id\":\"user-1\",\"wallets.0.address\":\"0xabc\"}\n"
+        );
+    }
+
+    #[test]
+    fn tables_shrink_the_widest_columns_to_fit() {
+        let cases: [(&[usize], usize, &[usize]); 4] = [
+            (&[10, 20], 100, &[10, 20]),
+            (&[10, 20], 24, &[10, 12]),
+            (&[10, 20, 30], 30, &[9, 9, 8]),
+            // Columns never shrink below the minimum, even if the table still overflows
+            (&[10, 10], 5, &[MIN_COLUMN_WIDTH, MIN_COLUMN_WIDTH]),
+        ];
+        for (widths, available, expected) in cases {
+            let mut fitted = widths.to_vec();
+            fit_widths(&mut fitted, available);
+            assert_eq!(fitted, expected, "{:?} in {}", widths, available);
+        }
+
+        let rows = [
+            vec!["ID".to_string(), "EMAIL".to_string()],
+            vec!["user-1".to_string(), "a.very.long.address@example.com".to_string()],
+        ];
+        let table = |terminal_width| {
+            let mut table = TableWriter {
+                header: Some(rows[0].clone()),
+                pending: Vec::new(),
+                widths: None,
+                terminal_width,
+            };
+            let mut out = Vec::new();
+            table.push(&mut out, rows[1].clone()).unwrap();
+            table.finish(&mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(table(Some(24)), "ID      EMAIL\nuser-1  a.very.long.add…\n");
+        // Piped output keeps whole values
+        assert_eq!(table(None), "ID      EMAIL\nuser-1  a.very.long.address@example.com\n");
+    }
+}
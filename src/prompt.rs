@@ -0,0 +1,335 @@
+use anyhow::Result;
+use dialoguer::{theme::ColorfulTheme, Confirm, Input, MultiSelect, Password, Select};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+// Questions asked of the user. Commands go through a `Prompter` rather than dialoguer directly,
+// so the same flow can run on a terminal, from a script of answers, or fail fast naming the flag
+// to pass when nobody is there to answer.
+
+// File of scripted answers, one per line, used instead of the terminal when set
+pub const ANSWERS_ENV: &str = "DYNAMIC_ADMIN_ANSWERS";
+
+// Checks an answer, returning why it's rejected
+pub type Validator = fn(&str) -> Result<(), &'static str>;
+
+// The command-line argument that answers a question instead, named when we can't ask
+#[derive(Debug, Clone, PartialEq)]
+pub enum Argument {
+    // A long flag, e.g. `user-id` for --user-id
+    Flag(String),
+    // A positional value, by its value name, e.g. `FILE`
+    Positional(String),
+}
+
+// A question and how to answer it
+#[derive(Clone)]
+pub struct Question {
+    pub prompt: String,
+    pub argument: Option<Argument>,
+    pub default: Option<String>,
+    pub allow_empty: bool,
+    // Ask for a password twice
+    pub confirmation: bool,
+    pub validator: Option<Validator>,
+}
+
+impl Question {
+    pub fn new(prompt: &str) -> Self {
+        Question {
+            prompt: prompt.to_string(),
+            argument: None,
+            default: None,
+            allow_empty: false,
+            confirmation: false,
+            validator: None,
+        }
+    }
+
+    pub fn flag(mut self, flag: &str) -> Self {
+        self.argument = Some(Argument::Flag(flag.to_string()));
+        self
+    }
+
+    pub fn positional(mut self, value_name: &str) -> Self {
+        self.argument = Some(Argument::Positional(value_name.to_string()));
+        self
+    }
+
+    pub fn default(mut self, default: &str) -> Self {
+        self.default = Some(default.to_string());
+        self
+    }
+
+    pub fn allow_empty(mut self) -> Self {
+        self.allow_empty = true;
+        self
+    }
+
+    pub fn with_confirmation(mut self) -> Self {
+        self.confirmation = true;
+        self
+    }
+
+    pub fn validate(mut self, validator: Validator) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    fn check(&self, answer: &str) -> Result<()> {
+        match self.validator.map(|validate| validate(answer)) {
+            Some(Err(e)) => Err(anyhow::anyhow!("Invalid answer to '{}': {}", self.prompt, e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub trait Prompter: Send + Sync {
+    // Whether questions get answered, by a person or a script standing in for one
+    fn is_interactive(&self) -> bool;
+    fn text(&self, question: &Question) -> Result<String>;
+    fn password(&self, question: &Question) -> Result<String>;
+    // Index of the chosen item
+    fn select(&self, question: &Question, items: &[&str]) -> Result<usize>;
+    // Indices of the chosen items
+    fn multi_select(&self, question: &Question, items: &[&str]) -> Result<Vec<usize>>;
+    // Defaults to no
+    fn confirm(&self, question: &Question) -> Result<bool>;
+}
+
+// The prompter for this process: scripted answers if DYNAMIC_ADMIN_ANSWERS is set, the terminal
+// if stdin is one, and otherwise none
+pub fn from_env() -> Result<Arc<dyn Prompter>> {
+    if let Ok(path) = std::env::var(ANSWERS_ENV) {
+        return Ok(Arc::new(ScriptedPrompter::from_file(Path::new(&path))?));
+    }
+    if std::io::stdin().is_terminal() {
+        Ok(Arc::new(TerminalPrompter))
+    } else {
+        Ok(Arc::new(NonInteractivePrompter))
+    }
+}
+
+// Asks on the terminal with dialoguer
+pub struct TerminalPrompter;
+
+impl Prompter for TerminalPrompter {
+    fn is_interactive(&self) -> bool {
+        true
+    }
+
+    fn text(&self, question: &Question) -> Result<String> {
+        let theme = ColorfulTheme::default();
+        let mut input = Input::<String>::with_theme(&theme)
+            .with_prompt(&question.prompt)
+            .allow_empty(question.allow_empty);
+        if let Some(default) = &question.default {
+            input = input.default(default.clone());
+        }
+        if let Some(validate) = question.validator {
+            input = input.validate_with(move |answer: &String| validate(answer));
+        }
+        Ok(input.interact_text()?)
+    }
+
+    fn password(&self, question: &Question) -> Result<String> {
+        let theme = ColorfulTheme::default();
+        let mut input = Password::with_theme(&theme).with_prompt(&question.prompt);
+        if question.confirmation {
+            input = input.with_confirmation(format!("Confirm {}", question.prompt.to_lowercase()), "The entries don't match");
+        }
+        if let Some(validate) = question.validator {
+            input = input.validate_with(move |answer: &String| validate(answer));
+        }
+        Ok(input.interact()?)
+    }
+
+    fn select(&self, question: &Question, items: &[&str]) -> Result<usize> {
+        Ok(Select::with_theme(&ColorfulTheme::default())
+            .with_prompt(&question.prompt)
+            .default(0)
+            .items(items)
+            .interact()?)
+    }
+
+    fn multi_select(&self, question: &Question, items: &[&str]) -> Result<Vec<usize>> {
+        Ok(MultiSelect::with_theme(&ColorfulTheme::default())
+            .with_prompt(&question.prompt)
+            .items(items)
+            .interact()?)
+    }
+
+    fn confirm(&self, question: &Question) -> Result<bool> {
+        Ok(Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(&question.prompt)
+            .default(false)
+            .interact()?)
+    }
+}
+
+// Answers questions in order from a list, for tests and scripted runs. Choices are answered with
+// the item's text or its index, several comma-separated for multi-select; confirmations with
+// yes or no. An empty answer takes the question's default.
+pub struct ScriptedPrompter {
+    answers: Mutex<VecDeque<String>>,
+}
+
+impl ScriptedPrompter {
+    pub fn new<I: IntoIterator<Item = S>, S: Into<String>>(answers: I) -> Self {
+        ScriptedPrompter {
+            answers: Mutex::new(answers.into_iter().map(Into::into).collect()),
+        }
+    }
+
+    // One answer per line
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Could not read answers from {}: {}", path.display(), e))?;
+        Ok(ScriptedPrompter::new(contents.lines()))
+    }
+
+    fn answer(&self, question: &Question) -> Result<String> {
+        let answer = self
+            .answers
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| anyhow::anyhow!("No scripted answer for '{}'", question.prompt))?;
+        let answer = match &question.default {
+            Some(default) if answer.is_empty() => default.clone(),
+            _ => answer,
+        };
+        question.check(&answer)?;
+        Ok(answer)
+    }
+
+    fn choose(question: &Question, items: &[&str], answer: &str) -> Result<usize> {
+        let answer = answer.trim();
+        items
+            .iter()
+            .position(|item| item.eq_ignore_ascii_case(answer))
+            .or_else(|| answer.parse().ok().filter(|index| *index < items.len()))
+            .ok_or_else(|| anyhow::anyhow!("'{}' isn't one of the choices for '{}': {}", answer, question.prompt, items.join(", ")))
+    }
+}
+
+impl Prompter for ScriptedPrompter {
+    fn is_interactive(&self) -> bool {
+        true
+    }
+
+    fn text(&self, question: &Question) -> Result<String> {
+        let answer = self.answer(question)?;
+        if answer.is_empty() && !question.allow_empty {
+            return Err(anyhow::anyhow!("Empty answer to '{}'", question.prompt));
+        }
+        Ok(answer)
+    }
+
+    fn password(&self, question: &Question) -> Result<String> {
+        self.answer(question)
+    }
+
+    fn select(&self, question: &Question, items: &[&str]) -> Result<usize> {
+        Self::choose(question, items, &self.answer(question)?)
+    }
+
+    fn multi_select(&self, question: &Question, items: &[&str]) -> Result<Vec<usize>> {
+        self.answer(question)?
+            .split(',')
+            .filter(|answer| !answer.trim().is_empty())
+            .map(|answer| Self::choose(question, items, answer))
+            .collect()
+    }
+
+    fn confirm(&self, question: &Question) -> Result<bool> {
+        match self.answer(question)?.trim().to_lowercase().as_str() {
+            "y" | "yes" | "true" => Ok(true),
+            "" | "n" | "no" | "false" => Ok(false),
+            answer => Err(anyhow::anyhow!("Answer '{}' with yes or no, not '{}'", question.prompt, answer)),
+        }
+    }
+}
+
+// Used when nobody can answer: every question fails, naming the argument that would have answered it
+pub struct NonInteractivePrompter;
+
+impl NonInteractivePrompter {
+    fn refuse<T>(&self, question: &Question) -> Result<T> {
+        Err(match &question.argument {
+            Some(Argument::Flag(flag)) if flag == "yes" => {
+                anyhow::anyhow!("Refusing to continue without confirmation; pass --yes")
+            }
+            Some(Argument::Flag(flag)) => anyhow::anyhow!("Missing required argument --{}", flag),
+            Some(Argument::Positional(value_name)) => anyhow::anyhow!("Missing required argument <{}>", value_name),
+            None => anyhow::anyhow!("Can't ask '{}' without a terminal", question.prompt),
+        })
+    }
+}
+
+impl Prompter for NonInteractivePrompter {
+    fn is_interactive(&self) -> bool {
+        false
+    }
+
+    fn text(&self, question: &Question) -> Result<String> {
+        self.refuse(question)
+    }
+
+    fn password(&self, question: &Question) -> Result<String> {
+        self.refuse(question)
+    }
+
+    fn select(&self, question: &Question, _items: &[&str]) -> Result<usize> {
+        self.refuse(question)
+    }
+
+    fn multi_select(&self, question: &Question, _items: &[&str]) -> Result<Vec<usize>> {
+        self.refuse(question)
+    }
+
+    fn confirm(&self, question: &Question) -> Result<bool> {
+        self.refuse(question)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripted_answers_are_used_in_order() {
+        let prompter = ScriptedPrompter::new(["ada@example.com", "", "admin", "1,owner", "yes", ""]);
+        let question = Question::new("Role").flag("role");
+
+        assert_eq!(prompter.text(&Question::new("Email")).unwrap(), "ada@example.com");
+        assert_eq!(prompter.text(&Question::new("Base URL").default("https://example.com")).unwrap(), "https://example.com");
+        assert_eq!(prompter.select(&question, &["owner", "admin"]).unwrap(), 1);
+        assert_eq!(prompter.multi_select(&question, &["owner", "admin"]).unwrap(), [1, 0]);
+        assert!(prompter.confirm(&Question::new("Delete?")).unwrap());
+        assert!(!prompter.confirm(&Question::new("Delete?")).unwrap());
+        assert!(prompter.text(&Question::new("More")).unwrap_err().to_string().contains("No scripted answer"));
+    }
+
+    #[test]
+    fn scripted_answers_are_validated() {
+        let prompter = ScriptedPrompter::new(["not-a-uuid", "viewer"]);
+        let uuid = Question::new("Environment").validate(|v| if v.len() == 36 { Ok(()) } else { Err("not a UUID") });
+        assert!(prompter.text(&uuid).is_err());
+        assert!(prompter.select(&Question::new("Role"), &["owner", "admin"]).is_err());
+    }
+
+    #[test]
+    fn non_interactive_names_the_flag() {
+        let prompter = NonInteractivePrompter;
+        let error = prompter.text(&Question::new("Enter user ID").flag("user-id")).unwrap_err();
+        assert_eq!(error.to_string(), "Missing required argument --user-id");
+        let error = prompter.confirm(&Question::new("Delete?").flag("yes")).unwrap_err();
+        assert!(error.to_string().contains("pass --yes"));
+        let error = prompter.text(&Question::new("Enter file").positional("FILE")).unwrap_err();
+        assert_eq!(error.to_string(), "Missing required argument <FILE>");
+    }
+}
@@ -0,0 +1,100 @@
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+
+// Header that makes a non-idempotent request safe to retry
+pub const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+
+// Retry settings, read from the optional [retry] section of config.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    // Total attempts per request, including the first
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 30_000,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    // Only idempotent methods are retried, unless the caller supplied an idempotency key
+    pub fn allows(&self, method: &Method, headers: &HeaderMap) -> bool {
+        let idempotent = matches!(
+            *method,
+            Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE
+        );
+        idempotent || headers.contains_key(IDEMPOTENCY_KEY)
+    }
+
+    // Delay before the next attempt. A server-provided Retry-After wins; if it asks for longer
+    // than max_delay_ms we give up rather than block for that long.
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        let max_delay = Duration::from_millis(self.max_delay_ms);
+
+        if let Some(retry_after) = retry_after {
+            return (retry_after <= max_delay).then_some(retry_after);
+        }
+
+        let exponent = attempt.saturating_sub(1).min(16);
+        let delay = Duration::from_millis(self.base_delay_ms.saturating_mul(1 << exponent)).min(max_delay);
+
+        if self.jitter {
+            // Somewhere between half and the full backoff, so parallel clients spread out
+            let half = delay / 2;
+            Some(half + half.mul_f64(fastrand::f64()))
+        } else {
+            Some(delay)
+        }
+    }
+}
+
+// Parse a Retry-After header, given either as seconds or an HTTP date
+pub fn parse_retry_after(value: &HeaderValue) -> Option<Duration> {
+    let value = value.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(at.duration_since(SystemTime::now()).unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn retry_after(value: &str) -> Option<Duration> {
+        parse_retry_after(&HeaderValue::from_str(value).unwrap())
+    }
+
+    #[test]
+    fn retry_after_takes_seconds_or_a_date() {
+        assert_eq!(retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(retry_after(" 0 "), Some(Duration::ZERO));
+
+        // HTTP dates have whole seconds, so a date two minutes out is a little under that
+        let at = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(120));
+        let delay = retry_after(&at).unwrap();
+        assert!(delay > Duration::from_secs(115) && delay <= Duration::from_secs(120), "{:?}", delay);
+
+        // A date already past means retry now
+        assert_eq!(retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+
+        for invalid in ["", "-5", "1.5", "soon", "2015-10-21T07:28:00Z"] {
+            assert_eq!(retry_after(invalid), None, "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,417 @@
+use anyhow::Result;
+use clap::parser::ValueSource;
+use clap_complete::engine;
+use colored::*;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+use rustyline::{CompletionType, Context, Editor, Helper};
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::commands::{self, CommandRegistry};
+use crate::context::ExecutionContext;
+use crate::environment::Environments;
+use crate::error;
+use crate::prompt::Prompter;
+
+// An interactive session: commands are typed at a prompt showing the profile and environment,
+// and run relative to the category the shell is in. `cd` moves between categories, Tab completes
+// from the command tree, and history is kept next to config.toml between sessions.
+
+// Subcommand that starts the shell
+pub const NAME: &str = "shell";
+
+// Kept next to config.toml, shared by every profile
+pub const HISTORY_FILE: &str = "shell-history";
+const HISTORY_SIZE: usize = 1000;
+
+// Handled by the shell rather than the command tree
+const BUILTINS: [&str; 5] = ["cd", "ls", "help", "exit", "quit"];
+
+// Flags that set up the API client, so they're fixed for the session
+const SESSION_FLAGS: [&str; 7] = ["config", "token", "base-url", "profile", "verbose", "record", "replay"];
+
+pub fn cli() -> clap::Command {
+    clap::Command::new(NAME).about("Start an interactive shell with history and tab completion")
+}
+
+pub struct Shell<'a> {
+    registry: &'a CommandRegistry,
+    profile: String,
+    environments: Arc<Environments>,
+    // Not kept when running without a config file
+    history: Option<PathBuf>,
+    prompter: Arc<dyn Prompter>,
+}
+
+impl<'a> Shell<'a> {
+    // `environment` is the ID or alias given with `--env` before `shell`, used for the whole session
+    pub fn new(
+        registry: &'a CommandRegistry,
+        profile: &str,
+        environments: Arc<Environments>,
+        environment: Option<&str>,
+        history: Option<PathBuf>,
+        prompter: Arc<dyn Prompter>,
+    ) -> Result<Self> {
+        let environments = match environment {
+            Some(id_or_alias) => Arc::new(environments.for_session(id_or_alias)?),
+            None => environments,
+        };
+        Ok(Shell {
+            registry,
+            profile: profile.to_string(),
+            environments,
+            history,
+            prompter,
+        })
+    }
+
+    // Read and run commands until `exit` or end of input
+    pub async fn run(&self) -> Result<()> {
+        let editor_config = rustyline::Config::builder()
+            .max_history_size(HISTORY_SIZE)?
+            .history_ignore_dups(true)?
+            .completion_type(CompletionType::List)
+            .build();
+        let mut editor: Editor<Navigator, FileHistory> = Editor::with_config(editor_config)?;
+        editor.set_helper(Some(Navigator::new(self.registry.root_cli())));
+
+        // A missing file just means this is the first session
+        if let Some(history) = &self.history {
+            editor.load_history(history).ok();
+        }
+
+        eprintln!(
+            "{}",
+            "Type a command, `ls` to see what's here, `cd <category>` to move and `exit` to leave".blue()
+        );
+
+        loop {
+            let prompt = self.prompt(&navigator(&mut editor).path);
+            let line = match editor.readline(&prompt) {
+                Ok(line) => line,
+                // Ctrl-C drops the line, Ctrl-D leaves
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break,
+                Err(e) => return Err(e.into()),
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            // Saved as we go, so a command that takes the process down doesn't lose the session
+            editor.add_history_entry(line)?;
+            if let Some(history) = &self.history
+                && let Err(e) = editor.append_history(history)
+            {
+                eprintln!("{}: Could not save history to {}: {}", "Warning".yellow(), history.display(), e);
+            }
+
+            let Some(words) = shlex::split(line) else {
+                error::print_error(&anyhow::anyhow!("Unbalanced quotes in '{}'", line));
+                continue;
+            };
+
+            let navigator = navigator(&mut editor);
+            let target = words.get(1).map_or(".", String::as_str);
+            let result = match words.first().map(String::as_str) {
+                None => Ok(()),
+                Some("exit" | "quit") => break,
+                Some("cd") => navigator.cd(words.get(1).map_or("/", String::as_str)),
+                Some("ls") => navigator.list(target).map(|entries| print_entries(&entries)),
+                Some("help") if words.len() == 1 => navigator.list(target).map(|entries| {
+                    print_help();
+                    print_entries(&entries);
+                }),
+                Some(_) => {
+                    let command_line = navigator.command_line(&words);
+                    self.execute(navigator.cli.clone(), command_line).await
+                }
+            };
+            if let Err(e) = result {
+                error::print_error(&e);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Run a typed command, given as a full command line
+    async fn execute(&self, cli: clap::Command, command_line: Vec<String>) -> Result<()> {
+        let matches = match cli.try_get_matches_from(command_line) {
+            Ok(matches) => matches,
+            // Usage errors and --help are reported by clap, without leaving the shell
+            Err(e) => {
+                e.print()?;
+                return Ok(());
+            }
+        };
+
+        let (cmd, cmd_matches) = self
+            .registry
+            .find_command(&matches)
+            .ok_or_else(|| anyhow::anyhow!("No command found"))?;
+        if let Some(flag) = SESSION_FLAGS
+            .iter()
+            .find(|flag| cmd_matches.value_source(flag) == Some(ValueSource::CommandLine))
+        {
+            return Err(anyhow::anyhow!(
+                "--{} can't change inside the shell; pass it before `{}` instead",
+                flag,
+                NAME
+            ));
+        }
+
+        let mut ctx = ExecutionContext::new(cmd_matches, &self.profile, self.environments.clone(), self.prompter.clone());
+        commands::run(cmd, &mut ctx).await
+    }
+
+    // e.g. `default:staging users> `
+    fn prompt(&self, path: &[String]) -> String {
+        let mut prompt = format!("{}:{}", self.profile, self.environment_label());
+        for category in path {
+            prompt.push(' ');
+            prompt.push_str(category);
+        }
+        prompt + "> "
+    }
+
+    // The environment commands run against: its alias if it has one, else the start of its ID
+    fn environment_label(&self) -> String {
+        let Ok((id, _)) = self.environments.current() else {
+            return "?".to_string();
+        };
+        let alias = self
+            .environments
+            .load_state()
+            .ok()
+            .and_then(|state| state.aliases.into_iter().find(|(_, aliased)| *aliased == id));
+        match alias {
+            Some((alias, _)) => alias,
+            None if id.is_empty() => "no environment".to_string(),
+            None => id.chars().take(8).collect(),
+        }
+    }
+}
+
+// The shell always sets its helper before reading lines
+fn navigator(editor: &mut Editor<Navigator, FileHistory>) -> &mut Navigator {
+    editor.helper_mut().expect("shell editor has a helper")
+}
+
+fn print_help() {
+    println!("{}", "Shell commands:".bold());
+    println!("  {:<16} Move into a category; `cd ..` goes up and `cd` alone to the top", "cd <category>");
+    println!("  {:<16} List the commands and categories here, or in another category", "ls [category]");
+    println!("  {:<16} Show this help; `<command> --help` describes a command", "help");
+    println!("  {:<16} Leave the shell, as does Ctrl-D", "exit");
+    println!();
+}
+
+fn print_entries(entries: &[(String, String)]) {
+    for (name, about) in entries {
+        if name.ends_with('/') {
+            println!("  {} {}", format!("{:<16}", name).cyan(), about);
+        } else {
+            println!("  {:<16} {}", name, about);
+        }
+    }
+}
+
+// Tracks the category the shell is in, placing and completing typed commands relative to it
+struct Navigator {
+    cli: clap::Command,
+    path: Vec<String>,
+}
+
+impl Navigator {
+    fn new(cli: clap::Command) -> Self {
+        Navigator { cli, path: Vec::new() }
+    }
+
+    // The command tree node for a category path
+    fn node(&self, path: &[String]) -> &clap::Command {
+        path.iter()
+            .fold(&self.cli, |node, category| node.find_subcommand(category).unwrap_or(node))
+    }
+
+    // A category path from category names separated by /, with .. for the parent and a leading /
+    // for the top
+    fn resolve(&self, target: &str) -> Result<Vec<String>> {
+        let mut path = if target.starts_with('/') { Vec::new() } else { self.path.clone() };
+        for segment in target.split('/').filter(|segment| !segment.is_empty() && *segment != ".") {
+            if segment == ".." {
+                path.pop();
+                continue;
+            }
+            match self.node(&path).find_subcommand(segment) {
+                Some(category) if category.has_subcommands() => path.push(category.get_name().to_string()),
+                Some(_) => return Err(anyhow::anyhow!("'{}' is a command, not a category", segment)),
+                None if path.is_empty() => return Err(anyhow::anyhow!("No category '{}'", segment)),
+                None => return Err(anyhow::anyhow!("No category '{}' in {}", segment, path.join(" "))),
+            }
+        }
+        Ok(path)
+    }
+
+    fn cd(&mut self, target: &str) -> Result<()> {
+        self.path = self.resolve(target)?;
+        Ok(())
+    }
+
+    // Names and descriptions in a category, subcategories first and marked with a trailing /
+    fn list(&self, target: &str) -> Result<Vec<(String, String)>> {
+        let path = self.resolve(target)?;
+        let node = self.node(&path);
+        let about = |cmd: &clap::Command| cmd.get_about().map(|about| about.to_string()).unwrap_or_default();
+
+        let categories = node
+            .get_subcommands()
+            .filter(|cmd| cmd.has_subcommands())
+            .map(|cmd| (format!("{}/", cmd.get_name()), about(cmd)));
+        let commands = node
+            .get_subcommands()
+            .filter(|cmd| !cmd.has_subcommands())
+            .map(|cmd| (cmd.get_name().to_string(), about(cmd)));
+        Ok(categories.chain(commands).collect())
+    }
+
+    // The full command line for typed words: under the current category, unless the first word
+    // is only found at the top, so `users get <ID>` works from anywhere
+    fn command_line(&self, words: &[String]) -> Vec<String> {
+        let from_top = words.first().is_some_and(|first| {
+            self.node(&self.path).find_subcommand(first).is_none() && self.cli.find_subcommand(first).is_some()
+        });
+        let path = if from_top { &[][..] } else { &self.path[..] };
+
+        std::iter::once(commands::BIN_NAME.to_string())
+            .chain(path.iter().cloned())
+            .chain(words.iter().cloned())
+            .collect()
+    }
+
+    // Where the word being typed at the end of `line` starts, and what it could be
+    fn candidates(&self, line: &str) -> (usize, Vec<Pair>) {
+        let start = line.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let (before, word) = line.split_at(start);
+        let words = shlex::split(before).unwrap_or_else(|| before.split_whitespace().map(String::from).collect());
+
+        let mut candidates: Vec<(String, Option<String>)> = Vec::new();
+        match words.first().map(String::as_str) {
+            Some("cd" | "ls") => {
+                // Complete the last category in a path like organizations/mem
+                let parent = &word[..word.rfind('/').map_or(0, |i| i + 1)];
+                if let Ok(entries) = self.list(if parent.is_empty() { "." } else { parent }) {
+                    candidates.extend(
+                        entries
+                            .into_iter()
+                            .filter(|(name, _)| name.ends_with('/'))
+                            .map(|(name, _)| (format!("{}{}", parent, name), None)),
+                    );
+                }
+            }
+            first => {
+                if first.is_none() {
+                    candidates.extend(BUILTINS.iter().map(|builtin| (builtin.to_string(), None)));
+                }
+                let mut args: Vec<OsString> = self.command_line(&words).into_iter().map(Into::into).collect();
+                args.push(word.into());
+                let index = args.len() - 1;
+                let current_dir = std::env::current_dir().ok();
+                if let Ok(found) = engine::complete(&mut self.cli.clone(), args, index, current_dir.as_deref()) {
+                    candidates.extend(found.into_iter().filter(|candidate| !candidate.is_hide_set()).map(|candidate| {
+                        let help = candidate.get_help().map(|help| help.to_string());
+                        (candidate.get_value().to_string_lossy().into_owned(), help)
+                    }));
+                }
+            }
+        }
+
+        let pairs = candidates
+            .into_iter()
+            .filter(|(value, _)| value.starts_with(word))
+            .map(|(value, help)| Pair {
+                display: match help {
+                    Some(help) => format!("{} ({})", value, help),
+                    None => value.clone(),
+                },
+                replacement: value,
+            })
+            .collect();
+        (start, pairs)
+    }
+}
+
+impl Completer for Navigator {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        Ok(self.candidates(&line[..pos]))
+    }
+}
+
+impl Highlighter for Navigator {
+    fn highlight_prompt<'b, 's: 'b, 'p: 'b>(&'s self, prompt: &'p str, _default: bool) -> Cow<'b, str> {
+        Cow::Owned(prompt.cyan().to_string())
+    }
+}
+
+impl Hinter for Navigator {
+    type Hint = String;
+}
+
+impl Validator for Navigator {}
+
+impl Helper for Navigator {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn navigator() -> Navigator {
+        Navigator::new(commands::default_registry().root_cli())
+    }
+
+    fn completions(navigator: &Navigator, line: &str) -> Vec<String> {
+        navigator.candidates(line).1.into_iter().map(|pair| pair.replacement).collect()
+    }
+
+    #[test]
+    fn commands_run_relative_to_the_category() {
+        let mut navigator = navigator();
+        navigator.cd("organizations/members").unwrap();
+        assert_eq!(navigator.path, ["organizations", "members"]);
+        assert_eq!(navigator.command_line(&["list".into()]), ["dynamic-admin", "organizations", "members", "list"]);
+        // Words only found at the top run from there
+        assert_eq!(navigator.command_line(&["users".into(), "list".into()]), ["dynamic-admin", "users", "list"]);
+
+        navigator.cd("../..").unwrap();
+        assert!(navigator.path.is_empty());
+        assert!(navigator.cd("users/get").is_err());
+        assert!(navigator.cd("nowhere").is_err());
+        navigator.cd("/users").unwrap();
+        assert_eq!(navigator.list(".").unwrap()[0].0, "list");
+        assert_eq!(navigator.list("/organizations").unwrap()[0].0, "members/");
+    }
+
+    #[test]
+    fn tab_completes_builtins_commands_and_categories() {
+        let mut navigator = navigator();
+        assert_eq!(completions(&navigator, "e"), ["exit", "exports", "env"]);
+        assert_eq!(completions(&navigator, "cd organizations/m"), ["organizations/members/"]);
+        assert_eq!(navigator.candidates("users bl").0, 6);
+        assert_eq!(completions(&navigator, "users bl"), ["block"]);
+
+        navigator.cd("users").unwrap();
+        assert_eq!(completions(&navigator, "unb"), ["unblock"]);
+        assert!(completions(&navigator, "list --pa").contains(&"--page-size".to_string()));
+    }
+}
@@ -0,0 +1,9 @@
+use std::path::PathBuf;
+
+// A fresh directory for files a test reads or writes
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("dynamic-admin-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
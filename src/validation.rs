@@ -0,0 +1,24 @@
+// Offline format checks for values sent to the API, so typos fail fast with a clear message
+
+// Loose email check: one @ with something on both sides and a dot in the domain
+pub fn is_email(value: &str) -> bool {
+    match value.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !value.chars().any(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+// Phone numbers in E.164 form, e.g. +14155550123
+pub fn is_phone_number(value: &str) -> bool {
+    match value.strip_prefix('+') {
+        Some(digits) => (7..=15).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_digit()),
+        None => false,
+    }
+}
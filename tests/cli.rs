@@ -0,0 +1,457 @@
+// End-to-end tests: run the CLI binary against its own mock-server
+
+use serde_json::Value;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStderr, Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const BIN: &str = env!("CARGO_BIN_EXE_dynamic-admin");
+const TOKEN: &str = "dyn_integration_test";
+const ENVIRONMENT_ID: &str = "00000000-0000-4000-8000-000000000000";
+
+static NEXT_HOME: AtomicUsize = AtomicUsize::new(0);
+
+// A mock server on a free port, with its own config directory so tests never touch the real one
+struct MockServer {
+    child: Child,
+    // Kept open so the server's startup messages don't hit a closed pipe
+    _stderr: BufReader<ChildStderr>,
+    url: String,
+    home: PathBuf,
+}
+
+impl MockServer {
+    fn start(args: &[&str]) -> Self {
+        let home = std::env::temp_dir().join(format!(
+            "dynamic-admin-test-{}-{}",
+            std::process::id(),
+            NEXT_HOME.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&home).unwrap();
+
+        let mut child = Command::new(BIN)
+            .args(["mock-server", "--port", "0"])
+            .args(args)
+            .env("NO_COLOR", "1")
+            .env("HOME", &home)
+            .env("XDG_CONFIG_HOME", &home)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        // First line: "Mock Dynamic API listening on http://127.0.0.1:<port>"
+        let mut stderr = BufReader::new(child.stderr.take().unwrap());
+        let mut line = String::new();
+        stderr.read_line(&mut line).unwrap();
+        let url = line.split_whitespace().last().unwrap().to_string();
+
+        MockServer {
+            child,
+            _stderr: stderr,
+            url,
+            home,
+        }
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(BIN);
+        command
+            .args(args)
+            .env("NO_COLOR", "1")
+            .env("HOME", &self.home)
+            .env("XDG_CONFIG_HOME", &self.home)
+            .env("DYNAMIC_API_TOKEN", TOKEN)
+            .env("DYNAMIC_BASE_URL", &self.url)
+            .env("DYNAMIC_ENVIRONMENT_ID", ENVIRONMENT_ID)
+            .stdin(Stdio::null());
+        command
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        self.command(args).output().unwrap()
+    }
+
+    // Run a command that must succeed and parse its JSON output
+    fn json(&self, args: &[&str]) -> Value {
+        let output = self.run(&[args, &["-o", "json"]].concat());
+        assert!(output.status.success(), "{:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+        serde_json::from_slice(&output.stdout).unwrap()
+    }
+
+    fn file(&self, name: &str) -> PathBuf {
+        self.home.join(name)
+    }
+
+    // A profile for this server in `dir/config.toml`; state is only saved next to a config file
+    fn write_config(&self, dir: &Path) -> PathBuf {
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join("config.toml");
+        let profile = format!(
+            "[profiles.default]\napi_token = \"{}\"\nbase_url = \"{}\"\ndefault_environment_id = \"{}\"\n",
+            TOKEN, self.url, ENVIRONMENT_ID
+        );
+        fs::write(&path, profile).unwrap();
+        path
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_dir_all(&self.home);
+    }
+}
+
+fn exit_code(output: &Output) -> i32 {
+    output.status.code().unwrap_or(-1)
+}
+
+#[test]
+fn user_lifecycle() {
+    let server = MockServer::start(&[]);
+
+    let user = server.json(&["users", "create", "--email", "new@example.com", "--first-name", "New"]);
+    let id = user["id"].as_str().unwrap();
+    assert_eq!(server.json(&["users", "get", id])["firstName"], "New");
+
+    let updated = server.json(&["users", "update", id, "--alias", "newbie"]);
+    assert_eq!(updated["alias"], "newbie");
+
+    assert!(server.run(&["users", "block", id, "--yes"]).status.success());
+    assert_eq!(server.json(&["users", "get", id])["blocked"], true);
+    assert!(server.run(&["users", "unblock", id]).status.success());
+
+    // Destructive commands refuse to run unattended without --yes
+    assert!(!server.run(&["users", "delete", id]).status.success());
+
+    // Scripted answers stand in for the terminal
+    let answers = server.file("answers.txt");
+    fs::write(&answers, format!("{}\nno\n", id)).unwrap();
+    let declined = server.command(&["users", "delete"]).env("DYNAMIC_ADMIN_ANSWERS", &answers).output().unwrap();
+    assert!(declined.status.success());
+    assert!(server.run(&["users", "get", id]).status.success());
+
+    assert!(server.run(&["users", "delete", id, "--yes"]).status.success());
+    assert_eq!(exit_code(&server.run(&["users", "get", id])), 5);
+}
+
+#[test]
+fn listings_follow_pages() {
+    let server = MockServer::start(&["--users", "120"]);
+
+    let output = server.run(&["users", "list", "--all", "--page-size", "50", "-o", "jsonl"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 123);
+
+    let limited = server.json(&["users", "list", "--limit", "5"]);
+    assert_eq!(limited.as_array().unwrap().len(), 5);
+}
+
+#[test]
+fn api_passthrough() {
+    let server = MockServer::start(&["--users", "120"]);
+
+    let user = server.json(&["api", "POST", "environments/{env}/users", "-f", "email=raw@example.com"]);
+    let path = format!("/api/v0/environments/{}/users/{}", ENVIRONMENT_ID, user["id"].as_str().unwrap());
+    assert_eq!(server.json(&["api", "get", &path])["email"], "raw@example.com");
+
+    let output = server.run(&["api", "GET", "environments/{env}/users", "--paginate", "--page-size", "50", "-o", "jsonl"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 124);
+
+    assert!(server.run(&["api", "DELETE", &path]).status.success());
+    assert_eq!(exit_code(&server.run(&["api", "GET", &path])), 5);
+}
+
+#[test]
+fn completion_offers_recent_ids() {
+    let server = MockServer::start(&[]);
+    server.write_config(&server.home.join("dynamic-admin-ops"));
+    let complete = |args: &[&str]| {
+        let output = server
+            .command(&[&["--", "dynamic-admin"], args].concat())
+            .env("DYNAMIC_ADMIN_COMPLETE", "fish")
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let user = server.json(&["users", "create", "--email", "tab@example.com"]);
+    let id = user["id"].as_str().unwrap();
+    assert!(complete(&["users", "get", ""]).contains(&format!("{}\ttab@example.com", id)));
+    assert!(complete(&["users", "bl"]).contains("block"));
+
+    assert!(server.run(&["users", "delete", id, "--yes"]).status.success());
+    assert!(!complete(&["users", "get", ""]).contains(id));
+}
+
+#[test]
+fn completion_only_reads_config() {
+    let server = MockServer::start(&[]);
+    // A config and environment state from before profiles, which running a command would migrate
+    let dir = server.home.join("dynamic-admin-ops");
+    fs::create_dir_all(&dir).unwrap();
+    let config = format!("api_token = \"{}\"\nbase_url = \"{}\"\n", TOKEN, server.url);
+    fs::write(dir.join("config.toml"), &config).unwrap();
+    fs::write(dir.join("environments.toml"), format!("[aliases]\nstaging = \"{}\"\n", ENVIRONMENT_ID)).unwrap();
+
+    let output = server
+        .command(&["--", "dynamic-admin", "users", "list", "--env", ""])
+        .env("DYNAMIC_ADMIN_COMPLETE", "fish")
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&format!("staging\t{}", ENVIRONMENT_ID)));
+    assert_eq!(fs::read_to_string(dir.join("config.toml")).unwrap(), config);
+    assert!(dir.join("environments.toml").exists() && !dir.join("environments").exists());
+}
+
+#[test]
+fn state_is_kept_next_to_the_loaded_config() {
+    let server = MockServer::start(&[]);
+    let staging = "11111111-1111-4111-8111-111111111111";
+
+    // Running purely from environment variables leaves nothing behind
+    assert!(server.run(&["users", "list"]).status.success());
+    let output = server.run(&["env", "use", staging]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("without a config file"));
+    assert_eq!(fs::read_dir(&server.home).unwrap().count(), 0);
+
+    // --config keeps state in that file's directory, not the default one
+    let dir = server.file("elsewhere");
+    let config = server.write_config(&dir);
+    let config = config.to_str().unwrap();
+    assert!(server.run(&["--config", config, "env", "use", staging, "--alias", "staging"]).status.success());
+    assert!(server.run(&["--config", config, "users", "create", "--email", "kept@example.com"]).status.success());
+    assert!(dir.join("environments/default.toml").exists());
+    assert!(dir.join("recent-ids.json").exists());
+    assert!(!server.file("dynamic-admin-ops").exists());
+}
+
+#[test]
+fn shell_runs_typed_commands() {
+    let server = MockServer::start(&[]);
+    server.write_config(&server.home.join("dynamic-admin-ops"));
+    let mut shell = server
+        .command(&["shell"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let session = "users create --email shell@example.com -o json\n\
+                   cd users\n\
+                   get 'not a user' -o json\n\
+                   list --limit 1 --token other\n\
+                   cd /nowhere\n\
+                   exit\n\
+                   users list\n";
+    shell.stdin.take().unwrap().write_all(session.as_bytes()).unwrap();
+    let output = shell.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stdout.contains("shell@example.com"));
+    assert!(stderr.contains("Not found"), "{}", stderr);
+    assert!(stderr.contains("--token can't change inside the shell"));
+    assert!(stderr.contains("No category 'nowhere'"));
+
+    // History outlives the session, and nothing after `exit` ran
+    let history = fs::read_to_string(server.home.join("dynamic-admin-ops/shell-history")).unwrap();
+    assert!(history.contains("cd users") && !history.contains("users list"));
+}
+
+#[test]
+fn shell_sessions_keep_the_env_given_before_them() {
+    let server = MockServer::start(&[]);
+    server.write_config(&server.home.join("dynamic-admin-ops"));
+    let staging = "11111111-1111-4111-8111-111111111111";
+    assert!(server.run(&["env", "use", staging, "--alias", "staging"]).status.success());
+    assert!(server.run(&["env", "use", ENVIRONMENT_ID]).status.success());
+
+    let mut shell = server
+        .command(&["--env", "staging", "shell"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let session = "env current -o jsonl\n\
+                   env current --env 22222222-2222-4222-8222-222222222222 -o jsonl\n";
+    shell.stdin.take().unwrap().write_all(session.as_bytes()).unwrap();
+    let output = shell.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let current: Vec<Value> = stdout
+        .lines()
+        .filter(|line| line.starts_with('{'))
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(current[0]["id"], staging);
+    assert_eq!(current[0]["source"], "shell session");
+    assert_eq!(current[1]["id"], "22222222-2222-4222-8222-222222222222");
+    assert_eq!(current[1]["source"], "command-line flag");
+
+    // Unknown aliases stop the shell before it starts
+    let output = server.run(&["--env", "nowhere", "shell"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown environment alias 'nowhere'"));
+}
+
+#[test]
+fn organizations_and_members() {
+    let server = MockServer::start(&[]);
+
+    let org = server.json(&["organizations", "create", "--name", "Initech", "--website-url", "https://initech.example"]);
+    let org_id = org["id"].as_str().unwrap();
+    assert_eq!(server.json(&["organizations", "list"]).as_array().unwrap().len(), 2);
+    assert_eq!(server.json(&["organizations", "update", org_id, "--description", "TPS"])["description"], "TPS");
+
+    let member = server.json(&["organizations", "members", "add", org_id, "user-2", "--role", "admin"]);
+    assert_eq!(member["role"], "admin");
+    server.json(&["organizations", "members", "set-role", org_id, "user-2", "--role", "owner"]);
+    let members = server.json(&["organizations", "members", "list", org_id]);
+    assert_eq!(members[0]["role"], "owner");
+
+    assert!(server.run(&["organizations", "members", "remove", org_id, "user-2", "-y"]).status.success());
+    assert!(server.run(&["organizations", "delete", org_id, "-y"]).status.success());
+    assert_eq!(exit_code(&server.run(&["organizations", "get", org_id])), 5);
+}
+
+#[test]
+fn export_waits_and_downloads() {
+    let server = MockServer::start(&[]);
+    let path = server.file("users.csv");
+
+    let export = server.json(&["exports", "create", "--type", "users", "--download", path.to_str().unwrap()]);
+    assert_eq!(export["status"], "completed");
+
+    let contents = fs::read_to_string(&path).unwrap();
+    assert!(contents.starts_with("id,email"));
+    assert!(contents.contains("ada@example.com"));
+
+    // The file is what was exported, so later writes don't break its size and checksum
+    let id = export["id"].as_str().unwrap();
+    server.json(&["users", "create", "--email", "late@example.com"]);
+    let again = server.file("again.csv");
+    assert!(server.run(&["exports", "download", id, "--file", again.to_str().unwrap()]).status.success());
+    assert_eq!(fs::read_to_string(&again).unwrap(), contents);
+}
+
+#[test]
+fn import_creates_valid_rows_and_resumes() {
+    let server = MockServer::start(&[]);
+    let file = server.file("users.jsonl");
+    fs::write(
+        &file,
+        "{\"email\":\"one@example.com\"}\n{\"email\":\"bad\"}\n{\"email\":\"two@example.com\",\"walletAddress\":\"0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed\",\"chain\":\"EVM\"}\n",
+    )
+    .unwrap();
+    let file = file.to_str().unwrap();
+
+    // The invalid row fails the run but doesn't stop the others
+    assert!(!server.run(&["users", "import", file]).status.success());
+    assert_eq!(server.json(&["users", "list"]).as_array().unwrap().len(), 5);
+
+    // Fixed and resumed, only the remaining row is created
+    fs::write(file, fs::read_to_string(file).unwrap().replace("\"bad\"", "\"three@example.com\"")).unwrap();
+    assert!(server.run(&["users", "import", file, "--resume"]).status.success());
+    assert_eq!(server.json(&["users", "list"]).as_array().unwrap().len(), 6);
+
+    // Addresses are normalized before they're sent
+    let wallets = server.json(&["wallets", "list", "--chain", "evm"]);
+    assert_eq!(wallets.as_array().unwrap().len(), 2);
+    assert_eq!(wallets[1]["publicKey"], "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+}
+
+#[test]
+fn wallets_filter_export_and_validate() {
+    let server = MockServer::start(&[]);
+
+    assert_eq!(server.json(&["wallets", "list", "--user-id", "user-1"]).as_array().unwrap().len(), 2);
+    assert_eq!(server.json(&["wallets", "list", "--provider", "embeddedWallet"]).as_array().unwrap().len(), 2);
+    let found = server.json(&["wallets", "list", "--address", "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"]);
+    assert_eq!(found[0]["id"], "wallet-1");
+
+    let addresses = server.json(&["wallets", "addresses"]);
+    assert_eq!(addresses["solana"][0], "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+    // Validation runs offline, without any profile
+    let output = Command::new(BIN)
+        .args(["wallets", "validate", "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"])
+        .env("HOME", &server.home)
+        .env("XDG_CONFIG_HOME", &server.home)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert_eq!(exit_code(&output), 1);
+}
+
+#[test]
+fn api_failures_map_to_exit_codes() {
+    let server = MockServer::start(&["--accept-token", TOKEN]);
+    assert_eq!(exit_code(&server.run(&["users", "get", "user-404"])), 5);
+    assert_eq!(exit_code(&server.run(&["users", "create", "--email", "ada@example.com"])), 7);
+    assert_eq!(exit_code(&server.run(&["--token", "dyn_some_other_token", "users", "list"])), 3);
+
+    let failing = MockServer::start(&["--error-rate", "1", "--error-status", "500"]);
+    assert_eq!(exit_code(&failing.run(&["users", "list"])), 8);
+
+    let limited = MockServer::start(&["--error-rate", "1", "--error-status", "429", "--retry-after", "0"]);
+    assert_eq!(exit_code(&limited.run(&["users", "list"])), 6);
+}
+
+#[test]
+fn raw_api_failures_map_to_exit_codes() {
+    let server = MockServer::start(&[]);
+    let output = server.run(&["api", "GET", "/api/v0/nowhere"]);
+    assert_eq!(exit_code(&output), 5);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Not found: No such endpoint"));
+    let output = server.run(&["api", "POST", "environments/{env}/users", "-f", "email=ada@example.com"]);
+    assert_eq!(exit_code(&output), 7);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("email"));
+
+    let failing = MockServer::start(&["--error-rate", "1", "--error-status", "500"]);
+    let output = failing.run(&["api", "GET", "environments/{env}/users"]);
+    assert_eq!(exit_code(&output), 8);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Server error (500): Injected fault"));
+}
+
+#[test]
+fn cassettes_replay_without_the_server() {
+    let server = MockServer::start(&[]);
+    let listing = server.file("list.jsonl");
+    let creation = server.file("create.jsonl");
+    let (listing, creation) = (listing.to_str().unwrap(), creation.to_str().unwrap());
+
+    let recorded = server.json(&["--record", listing, "users", "list"]);
+    let created = server.json(&["--record", creation, "users", "create", "--email", "new@example.com"]);
+    let contents = fs::read_to_string(listing).unwrap() + &fs::read_to_string(creation).unwrap();
+    assert!(!contents.contains(TOKEN) && !contents.contains("ada@example.com") && !contents.contains("new@example.com"));
+
+    // Replayed responses come back without touching the network, scrubbed of PII
+    let replay = |cassette: &str, args: &[&str]| {
+        server
+            .command(&[&["--replay", cassette], args, &["-o", "json"]].concat())
+            .env("DYNAMIC_BASE_URL", "http://127.0.0.1:9")
+            .output()
+            .unwrap()
+    };
+
+    let listed: Value = serde_json::from_slice(&replay(listing, &["users", "list"]).stdout).unwrap();
+    assert_eq!(listed.as_array().unwrap().len(), recorded.as_array().unwrap().len());
+    assert_eq!(listed[0]["id"], recorded[0]["id"]);
+    assert_ne!(listed[0]["email"], recorded[0]["email"]);
+
+    // Requests are matched on their scrubbed form, and unrecorded ones fail like a network error
+    let replayed: Value = serde_json::from_slice(&replay(creation, &["users", "create", "--email", "new@example.com"]).stdout).unwrap();
+    assert_eq!(replayed["id"], created["id"]);
+    assert_eq!(exit_code(&replay(creation, &["users", "create", "--email", "other@example.com"])), 9);
+}